@@ -0,0 +1,176 @@
+// ============================================================================
+// src/cli.rs
+// ============================================================================
+use crate::executor::Executor;
+use crate::tools::BinaryTools;
+use rust_mcp_sdk::schema::{
+    schema_utils::CallToolError, CallToolRequestParams, CallToolResult, ContentBlock,
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Author recorded on notes added from the command line.
+const AUTHOR: &str = "cli";
+
+/// One line of a batch script. Journal lines have the same fields and can be
+/// used as scripts directly.
+#[derive(Debug, Deserialize)]
+pub struct BatchStep {
+    pub tool: String,
+    #[serde(default)]
+    pub arguments: Option<Map<String, Value>>,
+}
+
+/// Parses the `--args` of `run`, which must be a JSON object.
+pub fn parse_arguments(args: Option<&str>) -> Result<Option<Map<String, Value>>, String> {
+    match args {
+        None => Ok(None),
+        Some(text) => match serde_json::from_str(text) {
+            Ok(Value::Object(map)) => Ok(Some(map)),
+            Ok(_) => Err("--args must be a JSON object".to_string()),
+            Err(e) => Err(format!("Invalid --args JSON: {}", e)),
+        },
+    }
+}
+
+pub fn read_script(path: &Path) -> Result<Vec<BatchStep>, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Cannot open script '{}': {}", path.display(), e))?;
+    let mut steps = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Cannot read script line {}: {}", i + 1, e))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let step = serde_json::from_str(line)
+            .map_err(|e| format!("Invalid script line {}: {}", i + 1, e))?;
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+/// Parses and runs one tool call, taking the state changes it made. Missing
+/// arguments are passed as an empty object, for tools without required ones.
+pub async fn call(
+    executor: &Executor,
+    tool: &str,
+    arguments: Option<Map<String, Value>>,
+) -> CallToolResult {
+    let params = CallToolRequestParams {
+        name: tool.to_string(),
+        arguments: Some(arguments.unwrap_or_default()),
+    };
+    match BinaryTools::try_from(params) {
+        Ok(tool) => executor.call(tool, AUTHOR).await.0,
+        Err(e) => CallToolResult::from(CallToolError::new(e)),
+    }
+}
+
+/// Loads `file` with load_binary, so `run` and `batch` can start from it.
+pub async fn preload(executor: &Executor, file: &Path) -> CallToolResult {
+    let mut arguments = Map::new();
+    arguments.insert("path".to_string(), Value::String(file.display().to_string()));
+    call(executor, "load_binary", Some(arguments)).await
+}
+
+/// Prints a result as its text content, or as JSON with `json`. In text
+/// mode, content that isn't text is printed as JSON on its own line.
+pub fn print_result(tool: &str, result: &CallToolResult, json: bool) {
+    if json {
+        let line = serde_json::json!({ "tool": tool, "result": result });
+        println!("{}", line);
+        return;
+    }
+    for block in &result.content {
+        match block {
+            ContentBlock::TextContent(text) => println!("{}", text.text),
+            other => println!("{}", serde_json::to_string(other).unwrap_or_default()),
+        }
+    }
+}
+
+pub fn is_error(result: &CallToolResult) -> bool {
+    result.is_error.unwrap_or(false)
+}
+
+/// Runs a script in order, stopping at the first failed step unless
+/// `keep_going`. Returns the number of failed steps.
+pub async fn batch(executor: &Executor, steps: Vec<BatchStep>, json: bool, keep_going: bool) -> usize {
+    let mut failed = 0;
+    for (i, step) in steps.into_iter().enumerate() {
+        if !json {
+            println!("▶ {} {}", i + 1, step.tool);
+        }
+        let result = call(executor, &step.tool, step.arguments).await;
+        print_result(&step.tool, &result, json);
+        if is_error(&result) {
+            failed += 1;
+            if !keep_going {
+                break;
+            }
+        }
+    }
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn step(tool: &str, arguments: Value) -> BatchStep {
+        BatchStep { tool: tool.to_string(), arguments: arguments.as_object().cloned() }
+    }
+
+    #[test]
+    fn run_arguments_must_be_an_object() {
+        assert_eq!(parse_arguments(None).unwrap(), None);
+        assert_eq!(parse_arguments(Some(r#"{"offset": 16}"#)).unwrap().unwrap()["offset"], json!(16));
+        assert_eq!(parse_arguments(Some("[16]")).unwrap_err(), "--args must be a JSON object");
+        assert!(parse_arguments(Some("offset=16")).unwrap_err().starts_with("Invalid --args JSON"));
+    }
+
+    #[test]
+    fn scripts_skip_blank_and_comment_lines() {
+        let path = std::env::temp_dir().join(format!("cli-test-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "# triage\n\n{\"tool\":\"get_info\"}\n  {\"tool\":\"read_bytes\",\"arguments\":{\"offset\":0,\"length\":2}}\n").unwrap();
+        let steps = read_script(&path).unwrap();
+        assert_eq!(steps.iter().map(|s| s.tool.as_str()).collect::<Vec<_>>(), ["get_info", "read_bytes"]);
+        assert!(steps[0].arguments.is_none());
+
+        std::fs::write(&path, "{\"tool\":\"get_info\"}\n{\"arguments\":{}}\n").unwrap();
+        assert!(read_script(&path).unwrap_err().starts_with("Invalid script line 2"));
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_script(&path).unwrap_err().starts_with("Cannot open script"));
+    }
+
+    #[tokio::test]
+    async fn batches_stop_at_the_first_failure_unless_told_otherwise() {
+        let script = || vec![
+            step("load_hex", json!({ "hex": "4d5a9000" })),
+            step("read_bytes", json!({ "offset": 8, "length": 2 })),
+            step("no_such_tool", json!({})),
+            step("add_note", json!({ "note": "checked" })),
+        ];
+
+        let executor = Executor::new(None);
+        assert_eq!(batch(&executor, script(), true, false).await, 1);
+        assert!(executor.state.read().await.analysis_notes.is_empty());
+
+        let executor = Executor::new(None);
+        assert_eq!(batch(&executor, script(), true, true).await, 2);
+        let s = executor.state.read().await;
+        assert_eq!(s.buffer, b"MZ\x90\x00");
+        assert_eq!(s.analysis_notes[0].author, AUTHOR);
+    }
+
+    #[tokio::test]
+    async fn missing_arguments_are_an_empty_object() {
+        let executor = Executor::new(None);
+        assert!(!is_error(&call(&executor, "get_info", None).await));
+        assert!(is_error(&call(&executor, "read_bytes", None).await));
+    }
+}
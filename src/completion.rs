@@ -0,0 +1,194 @@
+// ============================================================================
+// src/completion.rs
+// ============================================================================
+use crate::state::ServerState;
+use rust_mcp_sdk::schema::{CompleteResult, CompleteResultCompletion};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+
+/// The protocol caps a completion response at 100 values.
+const MAX_VALUES: usize = 100;
+
+/// Completes a prompt or resource template argument from the current state.
+/// Arguments are matched by name, so `path` completes the same way wherever it
+/// appears.
+pub fn complete_argument(
+    state: &ServerState,
+    roots: &[PathBuf],
+    argument: &str,
+    value: &str,
+) -> CompleteResult {
+    let mut values: Vec<String> = match argument {
+        "path" => return result(complete_path(roots, value)),
+        "bookmark" => state.bookmarks.keys().cloned().collect(),
+        "segment" => state.segments.iter().filter_map(|seg| seg.label.clone()).collect(),
+        "index" => (0..state.segments.len()).map(|i| i.to_string()).collect(),
+        "buffer" => {
+            let mut names = vec!["active".to_string()];
+            if state.file_loaded.is_some() {
+                names.push(state.buffer_name());
+            }
+            names
+        }
+        _ => Vec::new(),
+    };
+
+    let needle = value.to_lowercase();
+    values.retain(|candidate| candidate.to_lowercase().starts_with(&needle));
+    values.sort();
+    values.dedup();
+    result(values)
+}
+
+/// Lists directory entries matching `value`, only inside the allowed roots.
+/// Relative values are resolved against each root; an empty value lists the
+/// roots themselves.
+fn complete_path(roots: &[PathBuf], value: &str) -> Vec<String> {
+    if value.is_empty() {
+        return roots.iter().map(|root| with_separator(root.display().to_string())).collect();
+    }
+
+    let (dir, prefix) = match value.rfind(['/', MAIN_SEPARATOR]) {
+        Some(i) => (&value[..=i], &value[i + 1..]),
+        None => ("", value),
+    };
+
+    let mut values = Vec::new();
+    let bases: Vec<PathBuf> = if Path::new(dir).is_absolute() {
+        vec![PathBuf::from(dir)]
+    } else {
+        roots.iter().map(|root| root.join(dir)).collect()
+    };
+
+    for base in bases {
+        if !is_under_roots(roots, &base) {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(&base) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                continue;
+            }
+            let mut candidate = format!("{}{}", dir, name);
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                candidate = with_separator(candidate);
+            }
+            values.push(candidate);
+        }
+    }
+
+    values.sort();
+    values.dedup();
+    values
+}
+
+fn is_under_roots(roots: &[PathBuf], path: &Path) -> bool {
+    let Ok(path) = path.canonicalize() else {
+        return false;
+    };
+    roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| path.starts_with(root))
+}
+
+fn with_separator(mut path: String) -> String {
+    if !path.ends_with('/') && !path.ends_with(MAIN_SEPARATOR) {
+        path.push(MAIN_SEPARATOR);
+    }
+    path
+}
+
+fn result(values: Vec<String>) -> CompleteResult {
+    let total = values.len();
+    CompleteResult {
+        completion: CompleteResultCompletion {
+            has_more: Some(total > MAX_VALUES),
+            total: Some(total as i64),
+            values: values.into_iter().take(MAX_VALUES).collect(),
+        },
+        meta: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// `root/{a.exe, bin/, .hidden}` next to `root2/` and `outside/secret`,
+    /// with `root/link` pointing at `outside` where symlinks exist.
+    fn tree(name: &str) -> PathBuf {
+        let base = std::env::temp_dir().join(format!("completion-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(base.join("root/bin")).unwrap();
+        fs::create_dir_all(base.join("root2")).unwrap();
+        fs::create_dir_all(base.join("outside")).unwrap();
+        fs::write(base.join("root/a.exe"), b"MZ").unwrap();
+        fs::write(base.join("root/.hidden"), b"").unwrap();
+        fs::write(base.join("outside/secret"), b"").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(base.join("outside"), base.join("root/link")).unwrap();
+        base
+    }
+
+    #[test]
+    fn paths_must_resolve_inside_a_root() {
+        let base = tree("roots");
+        let roots = [base.join("root")];
+
+        assert!(is_under_roots(&roots, &base.join("root")));
+        assert!(is_under_roots(&roots, &base.join("root/bin/../a.exe")));
+        assert!(!is_under_roots(&roots, &base.join("root/../outside")));
+        assert!(!is_under_roots(&roots, &base.join("outside/secret")));
+        assert!(!is_under_roots(&roots, &base.join("root2")), "a shared name prefix isn't containment");
+        assert!(!is_under_roots(&roots, &base.join("root/missing")));
+        assert!(!is_under_roots(&[], &base.join("root")));
+        #[cfg(unix)]
+        assert!(!is_under_roots(&roots, &base.join("root/link")));
+
+        fs::remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn completions_stay_inside_the_roots() {
+        let base = tree("complete");
+        let root = base.join("root");
+        let roots = [root.clone()];
+        let complete = |value: &str| complete_path(&roots, value);
+
+        assert_eq!(complete(""), [with_separator(root.display().to_string())]);
+        assert_eq!(complete("a"), ["a.exe"]);
+        assert_eq!(complete("b"), [format!("bin{}", MAIN_SEPARATOR)]);
+        assert_eq!(complete("."), [".hidden"]);
+        assert!(!complete("x").iter().any(|v| v.starts_with('.')));
+        assert_eq!(complete("bin/"), Vec::<String>::new());
+
+        let inside = format!("{}/a", root.display());
+        assert_eq!(complete(&inside), [format!("{}/a.exe", root.display())]);
+        for escape in ["../", "../outside/", "bin/../../outside/s", "link/", "/"] {
+            assert_eq!(complete(escape), Vec::<String>::new(), "{}", escape);
+        }
+        assert_eq!(complete(&format!("{}/outside/", base.display())), Vec::<String>::new());
+        assert!(complete_path(&[], "a").is_empty());
+
+        fs::remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn state_values_complete_by_prefix() {
+        let mut state = ServerState::new();
+        state.replace_buffer(vec![0; 4], "Image.bin".to_string());
+        let values = |argument: &str, value: &str| complete_argument(&state, &[], argument, value).completion.values;
+
+        assert_eq!(values("buffer", ""), ["Image.bin", "active"]);
+        assert_eq!(values("buffer", "im"), ["Image.bin"]);
+        assert!(values("unknown", "").is_empty());
+
+        let many = result((0..150).map(|i| i.to_string()).collect());
+        assert_eq!(many.completion.values.len(), MAX_VALUES);
+        assert_eq!((many.completion.total, many.completion.has_more), (Some(150), Some(true)));
+    }
+}
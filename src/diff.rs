@@ -0,0 +1,342 @@
+// ============================================================================
+// src/diff.rs
+// ============================================================================
+
+/// Middles at most this long (old + new) are aligned exactly with Myers' algorithm.
+const MYERS_LIMIT: usize = 64 * 1024;
+
+/// Edits Myers' algorithm may find before the middle is split at an anchor instead.
+const MAX_EDITS: usize = 1024;
+
+/// Length of the blocks used as anchors to split large middles.
+const ANCHOR_BLOCK: usize = 32;
+
+/// Anchor candidates tried per split.
+const ANCHOR_CANDIDATES: usize = 16;
+
+/// Splits at most this deep; deeper middles are reported as replaced.
+const MAX_DEPTH: usize = 48;
+
+/// One step of an edit script turning `old` into `new`, as a run length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    /// Bytes present in both
+    Equal(usize),
+    /// Bytes of `old` missing from `new`
+    Delete(usize),
+    /// Bytes of `new` missing from `old`
+    Insert(usize),
+}
+
+/// A changed region: `old_length` bytes at `old_offset` became `new_length`
+/// bytes at `new_offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub old_offset: usize,
+    pub old_length: usize,
+    pub new_offset: usize,
+    pub new_length: usize,
+}
+
+impl Region {
+    pub fn kind(&self) -> &'static str {
+        match (self.old_length, self.new_length) {
+            (0, _) => "inserted",
+            (_, 0) => "deleted",
+            _ => "changed",
+        }
+    }
+}
+
+/// Aligns two buffers, detecting insertions and deletions rather than only
+/// comparing byte by byte. Exact for small differences; large differences are
+/// split at matching blocks first, so the script is short but not always minimal.
+pub fn diff(old: &[u8], new: &[u8]) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    align(old, new, 0, &mut edits);
+    edits
+}
+
+/// Appends an edit, merging it into the previous one of the same kind.
+fn push(edits: &mut Vec<Edit>, edit: Edit) {
+    let extended = match (edits.last_mut(), edit) {
+        (_, Edit::Equal(0) | Edit::Delete(0) | Edit::Insert(0)) => return,
+        (Some(Edit::Equal(n)), Edit::Equal(m))
+        | (Some(Edit::Delete(n)), Edit::Delete(m))
+        | (Some(Edit::Insert(n)), Edit::Insert(m)) => {
+            *n += m;
+            true
+        }
+        _ => false,
+    };
+    if !extended {
+        edits.push(edit);
+    }
+}
+
+fn align(old: &[u8], new: &[u8], depth: usize, edits: &mut Vec<Edit>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest.iter().rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old_rest[..old_rest.len() - suffix];
+    let new_mid = &new_rest[..new_rest.len() - suffix];
+
+    push(edits, Edit::Equal(prefix));
+    if old_mid.is_empty() || new_mid.is_empty() {
+        push(edits, Edit::Delete(old_mid.len()));
+        push(edits, Edit::Insert(new_mid.len()));
+    } else if let Some(script) = (old_mid.len() + new_mid.len() <= MYERS_LIMIT)
+        .then(|| myers(old_mid, new_mid, MAX_EDITS))
+        .flatten()
+    {
+        script.into_iter().for_each(|edit| push(edits, edit));
+    } else if let Some((old_at, new_at)) = (depth < MAX_DEPTH)
+        .then(|| anchor(old_mid, new_mid))
+        .flatten()
+    {
+        align(&old_mid[..old_at], &new_mid[..new_at], depth + 1, edits);
+        align(&old_mid[old_at..], &new_mid[new_at..], depth + 1, edits);
+    } else {
+        push(edits, Edit::Delete(old_mid.len()));
+        push(edits, Edit::Insert(new_mid.len()));
+    }
+    push(edits, Edit::Equal(suffix));
+}
+
+/// Myers' O(ND) shortest edit script, or `None` if it needs more than `max_d` edits.
+fn myers(old: &[u8], new: &[u8], max_d: usize) -> Option<Vec<Edit>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max_d = max_d.min(old.len() + new.len()) as isize;
+    let offset = max_d + 1;
+    let mut v = vec![0isize; 2 * max_d as usize + 3];
+    // v before each round d, for diagonals -(d+1)..=d+1
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=max_d {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let i = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) { v[i + 1] } else { v[i - 1] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Edit> {
+    let mut reversed = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            reversed.push(Edit::Equal(1));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            reversed.push(if x == prev_x { Edit::Insert(1) } else { Edit::Delete(1) });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    let mut edits = Vec::new();
+    reversed.into_iter().rev().for_each(|edit| push(&mut edits, edit));
+    edits
+}
+
+/// Finds a block of `new` that also occurs in `old`, trying blocks from the
+/// middle outwards and preferring the occurrence nearest the proportional
+/// position. Blocks of a single repeated byte, like padding, are skipped.
+fn anchor(old: &[u8], new: &[u8]) -> Option<(usize, usize)> {
+    if old.len() < ANCHOR_BLOCK || new.len() < ANCHOR_BLOCK {
+        return None;
+    }
+    let span = new.len() - ANCHOR_BLOCK;
+    let stride = span / ANCHOR_CANDIDATES + 1;
+    let middle = span / 2;
+
+    for i in 0..ANCHOR_CANDIDATES {
+        let delta = i.div_ceil(2) * stride;
+        let new_at = if i % 2 == 0 { Some(middle + delta) } else { middle.checked_sub(delta) };
+        let Some(new_at) = new_at.filter(|&at| at <= span) else { continue };
+        let block = &new[new_at..new_at + ANCHOR_BLOCK];
+        if block.iter().all(|&b| b == block[0]) {
+            continue;
+        }
+        let expected = (new_at as u128 * old.len() as u128 / new.len() as u128) as usize;
+        let found = old.windows(ANCHOR_BLOCK)
+            .enumerate()
+            .filter(|(_, window)| window[0] == block[0] && *window == block)
+            .map(|(old_at, _)| old_at)
+            .min_by_key(|old_at| old_at.abs_diff(expected));
+        if let Some(old_at) = found {
+            return Some((old_at, new_at));
+        }
+    }
+    None
+}
+
+/// Changed regions of an edit script. Regions separated by fewer than
+/// `merge_gap` equal bytes are reported as one.
+pub fn regions(edits: &[Edit], merge_gap: usize) -> Vec<Region> {
+    let mut regions: Vec<Region> = Vec::new();
+    let (mut old_at, mut new_at) = (0, 0);
+    let mut open = false;
+    for edit in edits {
+        match *edit {
+            Edit::Equal(n) => {
+                old_at += n;
+                new_at += n;
+                open = false;
+                continue;
+            }
+            Edit::Delete(n) => old_at += n,
+            Edit::Insert(n) => new_at += n,
+        }
+        let (old_len, new_len) = match *edit {
+            Edit::Delete(n) => (n, 0),
+            Edit::Insert(n) => (0, n),
+            Edit::Equal(_) => (0, 0),
+        };
+        match regions.last_mut() {
+            Some(last) if open || new_at - new_len - (last.new_offset + last.new_length) < merge_gap => {
+                last.old_length = old_at - last.old_offset;
+                last.new_length = new_at - last.new_offset;
+            }
+            _ => regions.push(Region {
+                old_offset: old_at - old_len,
+                old_length: old_len,
+                new_offset: new_at - new_len,
+                new_length: new_len,
+            }),
+        }
+        open = true;
+    }
+    regions
+}
+
+/// Bytes kept, deleted and inserted by an edit script.
+pub fn totals(edits: &[Edit]) -> (usize, usize, usize) {
+    edits.iter().fold((0, 0, 0), |(equal, deleted, inserted), edit| match *edit {
+        Edit::Equal(n) => (equal + n, deleted, inserted),
+        Edit::Delete(n) => (equal, deleted + n, inserted),
+        Edit::Insert(n) => (equal, deleted, inserted + n),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic bytes that look random (a 64-bit LCG's high byte).
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    /// Rebuilds `new` from `old` and the script, checking equal runs really match.
+    fn apply(old: &[u8], new: &[u8], edits: &[Edit]) -> Vec<u8> {
+        let (mut x, mut y) = (0, 0);
+        let mut out = Vec::new();
+        for edit in edits {
+            match *edit {
+                Edit::Equal(n) => {
+                    assert_eq!(old[x..x + n], new[y..y + n]);
+                    out.extend_from_slice(&old[x..x + n]);
+                    x += n;
+                    y += n;
+                }
+                Edit::Delete(n) => x += n,
+                Edit::Insert(n) => {
+                    out.extend_from_slice(&new[y..y + n]);
+                    y += n;
+                }
+            }
+        }
+        assert_eq!(x, old.len());
+        out
+    }
+
+    #[test]
+    fn empty_inputs() {
+        assert_eq!(diff(b"", b""), []);
+        assert_eq!(diff(b"", b"abc"), [Edit::Insert(3)]);
+        assert_eq!(diff(b"abc", b""), [Edit::Delete(3)]);
+        assert_eq!(myers(b"", b"", MAX_EDITS), Some(vec![]));
+        assert_eq!(myers(b"", b"ab", MAX_EDITS), Some(vec![Edit::Insert(2)]));
+        assert!(regions(&[], 0).is_empty());
+    }
+
+    #[test]
+    fn identical_inputs() {
+        let data = noise(5000, 7);
+        assert_eq!(diff(&data, &data), [Edit::Equal(5000)]);
+        assert_eq!(myers(&data, &data, 0), Some(vec![Edit::Equal(5000)]));
+        assert!(regions(&diff(&data, &data), 16).is_empty());
+    }
+
+    #[test]
+    fn myers_finds_a_shortest_script() {
+        let (old, new) = (b"abcabba", b"cbabac");
+        let edits = myers(old, new, MAX_EDITS).unwrap();
+        assert_eq!(apply(old, new, &edits), new);
+        let (equal, deleted, inserted) = totals(&edits);
+        assert_eq!((equal, deleted + inserted), (4, 5));
+        assert_eq!(myers(old, new, 4), None);
+    }
+
+    #[test]
+    fn small_edits_are_found_exactly() {
+        let old = noise(4000, 1);
+        let mut new = old.clone();
+        new[100] ^= 0xFF;
+        new.splice(2000..2000, [1, 2, 3]);
+        new.drain(3000..3010);
+        let edits = diff(&old, &new);
+        assert_eq!(apply(&old, &new, &edits), new);
+        let found: Vec<_> = regions(&edits, 1).iter().map(|r| (r.kind(), r.old_length, r.new_length)).collect();
+        assert_eq!(found, [("changed", 1, 1), ("inserted", 0, 3), ("deleted", 10, 0)]);
+    }
+
+    #[test]
+    fn too_many_edits_fall_back_to_a_replacement() {
+        let (old, new) = (noise(4096, 1), noise(4096, 2));
+        assert_eq!(myers(&old, &new, MAX_EDITS), None);
+        assert_eq!(diff(&old, &new), [Edit::Delete(4096), Edit::Insert(4096)]);
+    }
+
+    #[test]
+    fn large_middles_are_split_at_anchors() {
+        let old = noise(200_000, 3);
+        let mut new = old.clone();
+        new[10_000] ^= 1;
+        new.splice(100_000..100_000, noise(2000, 4));
+        new[190_000] ^= 1;
+        let edits = diff(&old, &new);
+        assert_eq!(apply(&old, &new, &edits), new);
+        let (_, deleted, inserted) = totals(&edits);
+        assert!(deleted <= 2 && inserted <= 2002, "{} {}", deleted, inserted);
+        assert_eq!(regions(&edits, 16).len(), 3);
+    }
+}
@@ -0,0 +1,158 @@
+// ============================================================================
+// src/entropy.rs
+// ============================================================================
+
+/// Shannon entropy, in bits per byte, of a byte histogram.
+fn shannon(counts: &[u64; 256], total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let total = total as f64;
+    counts.iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f64 / total;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+/// Shannon entropy of `data` in bits per byte: 0 for a single repeated
+/// value, 8 for uniformly distributed bytes.
+pub fn entropy(data: &[u8]) -> f64 {
+    let mut counts = [0u64; 256];
+    data.iter().for_each(|&b| counts[b as usize] += 1);
+    shannon(&counts, data.len() as u64)
+}
+
+/// Start offsets of the windows a profile covers: every `step` bytes while a
+/// whole window fits, plus one window ending at `len` if the steps fall short
+/// of it, or one window over all of `len` if it is shorter.
+pub fn window_starts(len: usize, window: usize, step: usize) -> impl Iterator<Item = usize> {
+    let count = match len {
+        0 => 0,
+        _ if len <= window => 1,
+        _ => (len - window) / step + 1,
+    };
+    let last = len.saturating_sub(window);
+    let tail = (last > 0 && !last.is_multiple_of(step)).then_some(last);
+    (0..count).map(move |i| i * step).chain(tail)
+}
+
+/// Entropy of each window of `data`, keeping a running histogram so
+/// overlapping windows only count the bytes that entered and left.
+/// `keep_going` is called with the offset reached before each window and
+/// stops the profile when it returns false.
+pub fn profile(
+    data: &[u8],
+    window: usize,
+    step: usize,
+    mut keep_going: impl FnMut(usize) -> bool,
+) -> Option<Vec<f64>> {
+    let mut counts = [0u64; 256];
+    let (mut from, mut to) = (0, 0);
+    let mut points = Vec::new();
+    for start in window_starts(data.len(), window, step) {
+        if !keep_going(start) {
+            return None;
+        }
+        let end = (start + window).min(data.len());
+        if start >= to {
+            counts = [0; 256];
+            from = start;
+            to = start;
+        }
+        data[from..start].iter().for_each(|&b| counts[b as usize] -= 1);
+        data[to..end].iter().for_each(|&b| counts[b as usize] += 1);
+        (from, to) = (start, end);
+        points.push(shannon(&counts, (end - start) as u64));
+    }
+    Some(points)
+}
+
+/// Runs of consecutive windows whose entropy satisfies `flagged`, as
+/// (offset, length) byte ranges; touching or overlapping windows are merged.
+/// `points` are the entropies `profile` returned for the same windows.
+pub fn regions(
+    points: &[f64],
+    len: usize,
+    window: usize,
+    step: usize,
+    flagged: impl Fn(f64) -> bool,
+) -> Vec<(usize, usize)> {
+    let mut regions: Vec<(usize, usize)> = Vec::new();
+    let starts = window_starts(len, window, step).zip(points);
+    for (start, _) in starts.filter(|&(_, &point)| flagged(point)) {
+        let end = (start + window).min(len);
+        match regions.last_mut() {
+            Some((offset, length)) if *offset + *length >= start => *length = end - *offset,
+            _ => regions.push((start, end - start)),
+        }
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic bytes that look random (a 64-bit LCG's high byte).
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn entropy_of_constant_and_random_data() {
+        assert_eq!(entropy(&[]), 0.0);
+        assert_eq!(entropy(&[0x41; 4096]), 0.0);
+        assert_eq!(entropy(&[0, 1, 0, 1]), 1.0);
+        let every_byte: Vec<u8> = (0..=255).collect();
+        assert!((entropy(&every_byte) - 8.0).abs() < 1e-9);
+        let random = entropy(&noise(1 << 16));
+        assert!(random > 7.99 && random <= 8.0, "{}", random);
+    }
+
+    #[test]
+    fn windows_reach_the_end_of_the_data() {
+        let starts = |len, window, step| window_starts(len, window, step).collect::<Vec<_>>();
+        assert!(starts(0, 16, 16).is_empty());
+        assert_eq!(starts(10, 16, 16), [0]);
+        assert_eq!(starts(64, 16, 16), [0, 16, 32, 48]);
+        assert_eq!(starts(70, 16, 16), [0, 16, 32, 48, 54]);
+        assert_eq!(starts(70, 16, 8), [0, 8, 16, 24, 32, 40, 48, 54]);
+    }
+
+    #[test]
+    fn profile_matches_each_window() {
+        let mut data = noise(300);
+        data.extend([0u8; 200]);
+        data.extend(noise(37));
+        for (window, step) in [(64, 64), (64, 16), (50, 70)] {
+            let points = profile(&data, window, step, |_| true).unwrap();
+            let expected: Vec<f64> = window_starts(data.len(), window, step)
+                .map(|start| entropy(&data[start..(start + window).min(data.len())]))
+                .collect();
+            assert_eq!(points.len(), expected.len());
+            for (point, expected) in points.iter().zip(expected) {
+                assert!((point - expected).abs() < 1e-9);
+            }
+        }
+        assert_eq!(profile(&data, 64, 64, |at| at < 128), None);
+    }
+
+    #[test]
+    fn regions_include_the_tail_window() {
+        let mut data = vec![0u8; 100];
+        data.extend(noise(20));
+        let points = profile(&data, 32, 32, |_| true).unwrap();
+        assert_eq!(points.len(), 4);
+        assert_eq!(regions(&points, data.len(), 32, 32, |e| e >= 3.0), [(88, 32)]);
+        assert_eq!(regions(&points, data.len(), 32, 32, |e| e <= 1.0), [(0, 96)]);
+    }
+}
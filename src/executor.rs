@@ -0,0 +1,153 @@
+// ============================================================================
+// src/executor.rs
+// ============================================================================
+use crate::history::{self, History, Snapshot};
+use crate::jobs::JobContext;
+use crate::state::{ServerState, StateChange};
+use crate::tools::BinaryTools;
+use rust_mcp_sdk::schema::{schema_utils::CallToolError, CallToolResult};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard, RwLock};
+
+/// Runs tools against an analysis state. Shared by the MCP handler and the
+/// headless command-line modes, which have no client to sample, elicit or
+/// report progress to.
+pub struct Executor {
+    pub state: Arc<RwLock<ServerState>>,
+    /// Undo and redo stacks, shared by everyone using the state
+    pub history: RwLock<History>,
+    /// Directory save_bytes and create_patch may write into; writing is refused without one
+    pub output_dir: Option<PathBuf>,
+    /// Taken by calls that change the state, so the changes pending when one
+    /// finishes are all its own
+    turn: Mutex<()>,
+}
+
+/// A tool call in progress. One that may change the state holds the
+/// executor's turn until it is dropped, and its snapshot until `finish`.
+pub struct Call<'a> {
+    tool_name: String,
+    turn: Option<MutexGuard<'a, ()>>,
+    before: Option<Snapshot>,
+}
+
+impl Executor {
+    pub fn new(output_dir: Option<PathBuf>) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(ServerState::new())),
+            history: RwLock::new(History::default()),
+            output_dir,
+            turn: Mutex::new(()),
+        }
+    }
+
+    /// Whether a tool can only run with an MCP client on the other end.
+    pub fn needs_client(tool_name: &str) -> bool {
+        matches!(
+            tool_name,
+            "summarize_region" | "propose_bookmark_name" | "classify_strings"
+                | "list_tool_groups" | "set_tool_group"
+        )
+    }
+
+    /// Whether a call may change the state: the recorded ones, and undo and redo.
+    fn changes_state(tool: &BinaryTools) -> bool {
+        !history::call_touches(tool).is_empty()
+            || matches!(tool, BinaryTools::Undo(_) | BinaryTools::Redo(_))
+    }
+
+    /// Starts a call: waits for the turn if it may change the state, then
+    /// captures what it could change if it is recorded in the history.
+    pub async fn begin(&self, tool: &BinaryTools) -> Call<'_> {
+        let turn = if Self::changes_state(tool) {
+            Some(self.turn.lock().await)
+        } else {
+            None
+        };
+        let before = if history::call_touches(tool).is_empty() {
+            None
+        } else {
+            Some(Snapshot::before(&*self.state.read().await, tool))
+        };
+        Call { tool_name: tool.tool_name(), turn, before }
+    }
+
+    /// Takes the changes the call made and records it in the history. The turn
+    /// is kept until the call is dropped, so the caller can journal it first.
+    pub async fn finish(&self, call: &mut Call<'_>) -> Vec<StateChange> {
+        if call.turn.is_none() {
+            return Vec::new();
+        }
+        let changes = self.state.write().await.take_changes();
+        if let Some(before) = call.before.take() {
+            self.history.write().await.record(&call.tool_name, before, &changes);
+        }
+        changes
+    }
+
+    /// Runs a tool that doesn't need a client. `author` is recorded on notes.
+    pub async fn run(
+        &self,
+        tool: BinaryTools,
+        author: &str,
+        job: JobContext,
+    ) -> Result<CallToolResult, CallToolError> {
+        match tool {
+            BinaryTools::LoadBinary(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::LoadBytes(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::LoadHex(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::ReadBytes(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::Hexdump(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::SearchPattern(tool) => tool.call_tool(&self.state, job).await,
+            BinaryTools::ExtractSegment(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::ExportRange(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::ExportSegment(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::SaveBytes(tool) => {
+                tool.call_tool(&self.state, self.output_dir.as_deref()).await
+            }
+            BinaryTools::ListSegments(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::RemoveSegment(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::RelabelSegment(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::AddBookmark(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::ListBookmarks(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::RenameBookmark(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::RemoveBookmark(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::BookmarksAt(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::ReadString(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::ReadInteger(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::CalculateHash(tool) => tool.call_tool(&self.state, job).await,
+            BinaryTools::CompareHashes(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::CalculateEntropy(tool) => tool.call_tool(&self.state, job).await,
+            BinaryTools::GetInfo(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::DiffBuffers(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::CreatePatch(tool) => {
+                tool.call_tool(&self.state, self.output_dir.as_deref()).await
+            }
+            BinaryTools::ApplyPatch(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::AddNote(tool) => tool.call_tool(&self.state, author).await,
+            BinaryTools::ListNotes(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::UpdateNote(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::DeleteNote(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::SetOutput(tool) => tool.call_tool(&self.state).await,
+            BinaryTools::Undo(tool) => tool.call_tool(&self.state, &self.history).await,
+            BinaryTools::Redo(tool) => tool.call_tool(&self.state, &self.history).await,
+            BinaryTools::ListHistory(tool) => tool.call_tool(&self.history).await,
+            other => Err(CallToolError::from_message(format!(
+                "Tool '{}' needs an MCP client",
+                other.tool_name()
+            ))),
+        }
+    }
+
+    /// Runs a tool and records it in the history, settling errors into an
+    /// error result. Returns the state changes it made.
+    pub async fn call(&self, tool: BinaryTools, author: &str) -> (CallToolResult, Vec<StateChange>) {
+        let mut call = self.begin(&tool).await;
+        let result = self.run(tool, author, JobContext::default())
+            .await
+            .unwrap_or_else(CallToolResult::from);
+        let changes = self.finish(&mut call).await;
+        (result, changes)
+    }
+}
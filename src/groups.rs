@@ -0,0 +1,141 @@
+// ============================================================================
+// src/groups.rs
+// ============================================================================
+use std::collections::BTreeSet;
+
+/// A named set of tools that can be switched on or off as a unit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ToolGroup {
+    Core,
+    Annotation,
+    Editing,
+    Crypto,
+    Sampling,
+}
+
+impl ToolGroup {
+    pub const ALL: [ToolGroup; 5] = [
+        ToolGroup::Core,
+        ToolGroup::Annotation,
+        ToolGroup::Editing,
+        ToolGroup::Crypto,
+        ToolGroup::Sampling,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToolGroup::Core => "core",
+            ToolGroup::Annotation => "annotation",
+            ToolGroup::Editing => "editing",
+            ToolGroup::Crypto => "crypto",
+            ToolGroup::Sampling => "sampling",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ToolGroup::Core => "Loading, reading and searching the buffer; tool group management",
+            ToolGroup::Annotation => "Bookmarks, segments, notes and the final output",
+            ToolGroup::Editing => "Comparing buffers and creating or applying patches",
+            ToolGroup::Crypto => "Hashing, similarity digests and entropy of the buffer and its ranges",
+            ToolGroup::Sampling => "Questions to the client's model, recorded as model-generated notes",
+        }
+    }
+
+    /// The core group carries the group management tools and can never be disabled.
+    pub fn can_disable(&self) -> bool {
+        *self != ToolGroup::Core
+    }
+
+    pub fn parse(name: &str) -> Option<ToolGroup> {
+        ToolGroup::ALL
+            .into_iter()
+            .find(|g| g.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Returns the group a tool belongs to, or `None` for a tool this server
+    /// doesn't know. Every registered tool is listed explicitly.
+    pub fn of_tool(tool_name: &str) -> Option<ToolGroup> {
+        match tool_name {
+            "load_binary" | "load_bytes" | "load_hex" | "read_bytes" | "hexdump"
+            | "search_pattern" | "read_string" | "read_integer" | "get_info" | "export_range"
+            | "save_bytes" | "undo" | "redo" | "list_history" | "list_tool_groups" | "set_tool_group" => {
+                Some(ToolGroup::Core)
+            }
+            "extract_segment" | "export_segment" | "list_segments" | "remove_segment"
+            | "relabel_segment" | "add_bookmark" | "list_bookmarks" | "rename_bookmark"
+            | "remove_bookmark" | "bookmarks_at" | "add_note" | "list_notes" | "update_note"
+            | "delete_note" | "set_output" => Some(ToolGroup::Annotation),
+            "diff_buffers" | "create_patch" | "apply_patch" => Some(ToolGroup::Editing),
+            "calculate_hash" | "compare_hashes" | "calculate_entropy" => Some(ToolGroup::Crypto),
+            "summarize_region" | "propose_bookmark_name" | "classify_strings" => {
+                Some(ToolGroup::Sampling)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The set of tool groups active for a session.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToolGroups(BTreeSet<ToolGroup>);
+
+impl ToolGroups {
+    pub fn all() -> Self {
+        Self(ToolGroup::ALL.into_iter().collect())
+    }
+
+    /// Parses a comma separated list of group names. `core` is always included.
+    pub fn from_list(list: &str) -> Result<Self, String> {
+        let mut groups = BTreeSet::from([ToolGroup::Core]);
+        for name in list.split(',').filter(|n| !n.trim().is_empty()) {
+            let group = ToolGroup::parse(name)
+                .ok_or_else(|| format!("Unknown tool group '{}'", name.trim()))?;
+            groups.insert(group);
+        }
+        Ok(Self(groups))
+    }
+
+    pub fn contains(&self, group: ToolGroup) -> bool {
+        self.0.contains(&group)
+    }
+
+    /// Unknown tools are left for the dispatcher to reject.
+    pub fn is_tool_enabled(&self, tool_name: &str) -> bool {
+        ToolGroup::of_tool(tool_name).is_none_or(|group| self.contains(group))
+    }
+
+    /// Enables or disables a group, returning whether the set changed.
+    pub fn set(&mut self, group: ToolGroup, enabled: bool) -> Result<bool, String> {
+        if enabled {
+            Ok(self.0.insert(group))
+        } else if !group.can_disable() {
+            Err(format!("Tool group '{}' cannot be disabled", group.name()))
+        } else {
+            Ok(self.0.remove(&group))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_tool_has_a_group() {
+        for tool in crate::tools::tool_list() {
+            assert!(ToolGroup::of_tool(&tool.name).is_some(), "{} has no group", tool.name);
+        }
+    }
+
+    #[test]
+    fn core_cannot_be_disabled() {
+        let mut groups = ToolGroups::from_list("editing").unwrap();
+        assert!(groups.contains(ToolGroup::Core));
+        assert!(groups.set(ToolGroup::Core, false).is_err());
+        assert_eq!(groups.set(ToolGroup::Editing, false), Ok(true));
+        assert!(!groups.is_tool_enabled("apply_patch"));
+        assert!(groups.is_tool_enabled("load_binary"));
+        assert!(ToolGroups::from_list("core,formats").is_err());
+    }
+}
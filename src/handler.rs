@@ -1,76 +1,704 @@
 // ============================================================================
 // src/handler.rs
 // ============================================================================
+use crate::completion;
+use crate::executor::Executor;
+use crate::groups::{ToolGroup, ToolGroups};
+use crate::jobs::{JobContext, Progress};
+use crate::journal::Journal;
+use crate::logging;
+use crate::prompts;
+use crate::resources;
+use crate::sampling::{self, SamplingRequest};
+use crate::session::{self, session_key, SessionState};
 use crate::tools::BinaryTools;
-use crate::state::ServerState;
+use crate::state::{Note, ServerState, StateChange};
 use async_trait::async_trait;
 use rust_mcp_sdk::schema::{
-    schema_utils::CallToolError, CallToolRequest, CallToolResult, 
-    ListToolsRequest, ListToolsResult, RpcError,
+    schema_utils::CallToolError, BooleanSchema, CallToolRequest, CallToolResult,
+    CancelledNotification, CompleteRequest, CompleteRequestParamsRef, CompleteResult,
+    ElicitRequestedSchema, ElicitResult, ElicitResultAction, ElicitResultContentValue, GetPromptRequest,
+    GetPromptResult, ListPromptsRequest, ListPromptsResult, ListResourceTemplatesRequest,
+    ListResourceTemplatesResult, ListResourcesRequest, ListResourcesResult, ListToolsRequest,
+    ListToolsResult, LoggingLevel, LoggingMessageNotificationParams, PrimitiveSchemaDefinition,
+    ProgressNotification, ProgressNotificationParams, ProgressToken, ReadResourceRequest,
+    ReadResourceResult, ResourceUpdatedNotificationParams, Result as EmptyResult, RpcError,
+    SetLevelRequest, SubscribeRequest, TextContent, UnsubscribeRequest,
 };
+use rust_mcp_sdk::session_store::SessionStore;
 use rust_mcp_sdk::{mcp_server::ServerHandler, McpServer};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+
+/// Reports state changes to the log and to every session. Cloned into
+/// front-ends that change the state outside of MCP requests.
+#[derive(Clone)]
+pub struct Publisher {
+    state: Arc<RwLock<ServerState>>,
+    sessions: Arc<RwLock<HashMap<String, SessionState>>>,
+    /// Print the full state dump to stderr after every change
+    debug_state: bool,
+}
+
+impl Publisher {
+    /// Logs the changes a call made and notifies every session subscribed to
+    /// an affected resource.
+    pub async fn publish(&self, changes: Vec<StateChange>) {
+        if changes.is_empty() {
+            return;
+        }
+        let events: Vec<_> = {
+            let s = self.state.read().await;
+            if self.debug_state {
+                s.display();
+            }
+            changes.iter().map(|c| logging::change_event(&s, *c)).collect()
+        };
+
+        for (change, event) in changes.iter().zip(&events) {
+            tracing::info!(change = ?change, details = %event, "State changed");
+        }
+        for event in events {
+            self.log(LoggingLevel::Info, event).await;
+        }
+
+        let list_changed = changes.iter().any(|c| resources::changes_resource_list(*c));
+        let sessions = self.sessions.read().await;
+        for session in sessions.values() {
+            for uri in &session.subscriptions {
+                let Ok(parsed) = resources::BinaryUri::parse(uri) else { continue };
+                if changes.iter().any(|c| parsed.affected_by(*c)) {
+                    let params = ResourceUpdatedNotificationParams { uri: uri.clone() };
+                    if let Err(e) = session.runtime.send_resource_updated(params).await {
+                        tracing::warn!("Failed to send resources/updated for {}: {}", uri, e);
+                    }
+                }
+            }
+            if list_changed {
+                if let Err(e) = session.runtime.send_resource_list_changed(None).await {
+                    tracing::warn!("Failed to send resources/list_changed: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Sends a notifications/message to every session whose level allows it.
+    pub async fn log(&self, level: LoggingLevel, data: serde_json::Value) {
+        let sessions = self.sessions.read().await;
+        for session in sessions.values() {
+            if !logging::is_enabled(session.log_level, level) {
+                continue;
+            }
+            let params = LoggingMessageNotificationParams {
+                data: data.clone(),
+                level,
+                logger: Some(logging::LOGGER.to_string()),
+            };
+            if let Err(e) = session.runtime.send_logging_message(params).await {
+                tracing::warn!("Failed to send notifications/message: {}", e);
+            }
+        }
+    }
+}
+
+/// Drops the handler's sessions once the HTTP server has forgotten them, on
+/// disconnect or when their transport closes. The SDK gives handlers no
+/// disconnect hook, so its session store is polled every `every`.
+pub async fn prune_sessions(
+    sessions: Arc<RwLock<HashMap<String, SessionState>>>,
+    store: Arc<dyn SessionStore>,
+    every: Duration,
+) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        let live: HashSet<String> = store.keys().await.into_iter().collect();
+        for key in session::prune(&mut *sessions.write().await, &live) {
+            tracing::info!("Session {} disconnected", key);
+        }
+    }
+}
+
+/// Whether the user accepted an elicitation and ticked its confirm box.
+fn confirmed(result: &ElicitResult) -> bool {
+    result.action == ElicitResultAction::Accept
+        && matches!(
+            result.content.as_ref().and_then(|c| c.get("confirm")),
+            Some(ElicitResultContentValue::Boolean(true))
+        )
+}
 
 pub struct BinaryAnalysisHandler {
     pub state: Arc<RwLock<ServerState>>,
+    pub sessions: Arc<RwLock<HashMap<String, SessionState>>>,
+    /// Tool groups given to new sessions
+    pub default_groups: RwLock<ToolGroups>,
+    /// Notifies sessions and logs when the state changes
+    pub publisher: Publisher,
+    /// Server policy: whether tools may send sampling/createMessage to clients
+    pub sampling_allowed: bool,
+    /// Runs the tools that don't need a client, and keeps the undo history
+    pub executor: Arc<Executor>,
+    /// Record of every handled tool call, if enabled
+    pub journal: Option<Arc<Journal>>,
 }
 
 impl BinaryAnalysisHandler {
-    pub async fn new() -> Self {
-        let state = Arc::new(RwLock::new(ServerState::new()));
-        
-        println!("\n🔬 Binary Analysis MCP Server Starting...");
-        {
-            // let s = state.blocking_read();
-            let s = state.read().await;
-            s.display();
-        } // Drop the read lock here before moving state
-        
-        Self { state }
+    pub async fn new(
+        default_groups: ToolGroups,
+        debug_state: bool,
+        sampling_allowed: bool,
+        output_dir: Option<PathBuf>,
+        journal: Option<Journal>,
+    ) -> Self {
+        let executor = Arc::new(Executor::new(output_dir));
+        let state = executor.state.clone();
+        let sessions = Arc::new(RwLock::new(HashMap::new()));
+
+        tracing::info!("🔬 Binary Analysis MCP Server Starting...");
+        if debug_state {
+            state.read().await.display();
+        }
+
+        Self {
+            publisher: Publisher { state: state.clone(), sessions: sessions.clone(), debug_state },
+            state,
+            sessions,
+            default_groups: RwLock::new(default_groups),
+            sampling_allowed,
+            executor,
+            journal: journal.map(Arc::new),
+        }
+    }
+
+    /// A console running tool commands against this handler's state, for the
+    /// terminal UI.
+    #[cfg(feature = "tui")]
+    pub fn console(&self) -> crate::tui::Console {
+        crate::tui::Console {
+            executor: self.executor.clone(),
+            journal: self.journal.clone(),
+            publisher: self.publisher.clone(),
+        }
+    }
+
+    /// Registers the calling session on first use and returns its key.
+    async fn ensure_session(&self, runtime: &Arc<dyn McpServer>) -> String {
+        let key = session_key(runtime);
+        if !self.sessions.read().await.contains_key(&key) {
+            let groups = self.default_groups.read().await.clone();
+            self.sessions.write().await
+                .entry(key.clone())
+                .or_insert_with(|| SessionState::new(runtime.clone(), groups));
+        }
+        key
+    }
+
+    /// Returns the tool groups of the calling session.
+    async fn session_groups(&self, runtime: &Arc<dyn McpServer>) -> ToolGroups {
+        let key = self.ensure_session(runtime).await;
+        self.sessions.read().await
+            .get(&key)
+            .map(|session| session.tool_groups.clone())
+            .unwrap_or_else(ToolGroups::all)
+    }
+
+    /// Runs a long-running tool as a cancellable job of the calling session,
+    /// forwarding its progress as notifications/progress when a token is given.
+    ///
+    /// The SDK drops `_meta.progressToken` and the request id before calling the
+    /// handler, so the token comes from the tool's `progress_token` argument and a
+    /// cancellation notification stops all of the session's running jobs.
+    async fn run_job<F, Fut>(
+        &self,
+        runtime: &Arc<dyn McpServer>,
+        progress_token: Option<String>,
+        work: F,
+    ) -> std::result::Result<CallToolResult, CallToolError>
+    where
+        F: FnOnce(JobContext) -> Fut,
+        Fut: Future<Output = std::result::Result<CallToolResult, CallToolError>>,
+    {
+        let key = self.ensure_session(runtime).await;
+        let (job_id, cancelled) = match self.sessions.write().await.get_mut(&key) {
+            Some(session) => session.jobs.start(),
+            None => (0, Default::default()),
+        };
+
+        let progress = progress_token.map(|token| {
+            let token = match token.parse::<i64>() {
+                Ok(n) => ProgressToken::Integer(n),
+                Err(_) => ProgressToken::String(token),
+            };
+            let (tx, mut rx) = mpsc::unbounded_channel::<Progress>();
+            let runtime = runtime.clone();
+            tokio::spawn(async move {
+                while let Some((processed, total)) = rx.recv().await {
+                    let notification = ProgressNotification::new(ProgressNotificationParams {
+                        message: None,
+                        progress: processed as f64,
+                        progress_token: token.clone(),
+                        total: Some(total as f64),
+                    });
+                    if let Err(e) = runtime.send_notification(notification.into()).await {
+                        tracing::warn!("Failed to send progress notification: {}", e);
+                        break;
+                    }
+                }
+            });
+            tx
+        });
+
+        let result = work(JobContext::new(cancelled, progress))
+            .await
+            .unwrap_or_else(CallToolResult::from);
+
+        if let Some(session) = self.sessions.write().await.get_mut(&key) {
+            session.jobs.finish(job_id);
+        }
+        Ok(result)
+    }
+
+    /// Asks the user to confirm a destructive operation through elicitation.
+    /// Clients without elicitation support are not asked and the operation proceeds.
+    async fn confirm(&self, runtime: &Arc<dyn McpServer>, message: String) -> bool {
+        let supports_elicitation = runtime
+            .client_info()
+            .map(|info| info.capabilities.elicitation.is_some())
+            .unwrap_or(false);
+        if !supports_elicitation {
+            return true;
+        }
+
+        let schema = ElicitRequestedSchema::new(
+            HashMap::from([(
+                "confirm".to_string(),
+                PrimitiveSchemaDefinition::BooleanSchema(BooleanSchema::new(
+                    Some(false),
+                    Some("Proceed with the operation".to_string()),
+                    Some("Confirm".to_string()),
+                )),
+            )]),
+            vec!["confirm".to_string()],
+        );
+
+        match runtime.elicit_input(format!("{} Continue?", message), schema).await {
+            Ok(result) => confirmed(&result),
+            Err(e) => {
+                tracing::warn!("Elicitation failed, not proceeding: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Asks the client's model a question and returns its answer as a
+    /// model-generated analysis note, for the caller to store.
+    async fn sample_note<F>(
+        &self,
+        runtime: &Arc<dyn McpServer>,
+        build: F,
+    ) -> std::result::Result<(Note, CallToolResult), CallToolError>
+    where
+        F: FnOnce(&ServerState) -> std::result::Result<SamplingRequest, CallToolError>,
+    {
+        let request = build(&*self.state.read().await)?;
+        if !self.sampling_allowed {
+            return Err(CallToolError::from_message("Sampling is disabled by server policy"));
+        }
+        if !runtime.client_supports_sampling().unwrap_or(false) {
+            return Err(CallToolError::from_message("The client does not support sampling"));
+        }
+
+        let subject = request.subject.clone();
+        let note = Note {
+            offset: request.offset,
+            length: request.length,
+            bookmark: request.bookmark.clone(),
+            tags: vec![sampling::MODEL_NOTE_TAG.to_string()],
+            author: session_key(runtime),
+            ..Default::default()
+        };
+        let result = runtime.create_message(request.into_params()).await
+            .map_err(|e| CallToolError::from_message(format!("Sampling request failed: {}", e)))?;
+        let answer = sampling::answer_text(&result)
+            .ok_or_else(|| CallToolError::from_message("The model did not return a text answer"))?;
+
+        let text = format!("✅ Model-generated note added for {} ({}):\n{}", subject, result.model, answer);
+        let note = Note { text: sampling::model_note_text(&subject, &result.model, &answer), ..note };
+        Ok((note, CallToolResult::text_content(vec![TextContent::from(text)])))
+    }
+
+    /// Directories path completion may look into: the client's `file://` roots
+    /// when it exposes any, otherwise the server's working directory.
+    async fn allowed_roots(runtime: &Arc<dyn McpServer>) -> Vec<PathBuf> {
+        if runtime.client_supports_root_list().unwrap_or(false) {
+            match runtime.list_roots(None).await {
+                Ok(result) => {
+                    let roots: Vec<PathBuf> = result
+                        .roots
+                        .iter()
+                        .filter_map(|root| root.uri.strip_prefix("file://"))
+                        .map(PathBuf::from)
+                        .collect();
+                    if !roots.is_empty() {
+                        return roots;
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to list client roots: {}", e),
+            }
+        }
+        std::env::current_dir().into_iter().collect()
+    }
+
+    async fn notify_tool_list_changed(runtime: &Arc<dyn McpServer>) {
+        if let Err(e) = runtime.send_tool_list_changed(None).await {
+            tracing::warn!("Failed to send tools/list_changed: {}", e);
+        }
+    }
+
+    async fn set_tool_group(
+        &self,
+        tool: crate::tools::SetToolGroup,
+        runtime: &Arc<dyn McpServer>,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        if tool.enabled && !self.sampling_allowed && tool.parse_group()? == ToolGroup::Sampling {
+            return Err(CallToolError::from_message("Sampling is disabled by server policy"));
+        }
+
+        let mut groups = self.session_groups(runtime).await;
+        let before = groups.clone();
+        let result = tool.call_tool(&mut groups).await?;
+
+        if tool.all_sessions.unwrap_or(false) {
+            let group = tool.parse_group()?;
+            let _ = self.default_groups.write().await.set(group, tool.enabled);
+
+            let mut sessions = self.sessions.write().await;
+            for session in sessions.values_mut() {
+                if session.tool_groups.set(group, tool.enabled).unwrap_or(false) {
+                    Self::notify_tool_list_changed(&session.runtime).await;
+                }
+            }
+        } else if groups != before {
+            if let Some(session) = self.sessions.write().await.get_mut(&session_key(runtime)) {
+                session.tool_groups = groups;
+            }
+            Self::notify_tool_list_changed(runtime).await;
+        }
+
+        Ok(result)
     }
 }
 
 #[async_trait]
 impl ServerHandler for BinaryAnalysisHandler {
     async fn on_initialized(&self, runtime: Arc<dyn McpServer>) {
+        self.session_groups(&runtime).await;
         let _ = runtime.stderr_message(
             "✅ Binary Analysis Server initialized. Ready for reverse engineering.".to_string()
         ).await;
     }
 
+    async fn handle_set_level_request(
+        &self,
+        request: SetLevelRequest,
+        runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<EmptyResult, RpcError> {
+        let key = self.ensure_session(&runtime).await;
+        if let Some(session) = self.sessions.write().await.get_mut(&key) {
+            session.log_level = request.params.level;
+        }
+        tracing::info!(session = %key, level = %request.params.level, "Log level changed");
+        Ok(EmptyResult::default())
+    }
+
     async fn handle_list_tools_request(
         &self,
         _request: ListToolsRequest,
-        _runtime: Arc<dyn McpServer>,
+        runtime: Arc<dyn McpServer>,
     ) -> std::result::Result<ListToolsResult, RpcError> {
+        let groups = self.session_groups(&runtime).await;
+
         Ok(ListToolsResult {
             meta: None,
             next_cursor: None,
-            tools: BinaryTools::tools(),
+            tools: crate::tools::tool_list()
+                .into_iter()
+                .filter(|tool| groups.is_tool_enabled(&tool.name))
+                .collect(),
         })
     }
 
+    async fn handle_list_resources_request(
+        &self,
+        _request: ListResourcesRequest,
+        _runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<ListResourcesResult, RpcError> {
+        let s = self.state.read().await;
+
+        Ok(ListResourcesResult {
+            meta: None,
+            next_cursor: None,
+            resources: resources::list_resources(&s),
+        })
+    }
+
+    async fn handle_list_resource_templates_request(
+        &self,
+        _request: ListResourceTemplatesRequest,
+        _runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<ListResourceTemplatesResult, RpcError> {
+        Ok(ListResourceTemplatesResult {
+            meta: None,
+            next_cursor: None,
+            resource_templates: resources::resource_templates(),
+        })
+    }
+
+    async fn handle_read_resource_request(
+        &self,
+        request: ReadResourceRequest,
+        _runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<ReadResourceResult, RpcError> {
+        let s = self.state.read().await;
+
+        Ok(ReadResourceResult {
+            contents: resources::read_resource(&s, &request.params.uri)?,
+            meta: None,
+        })
+    }
+
+    async fn handle_subscribe_request(
+        &self,
+        request: SubscribeRequest,
+        runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<EmptyResult, RpcError> {
+        resources::BinaryUri::parse(&request.params.uri)?;
+
+        let key = self.ensure_session(&runtime).await;
+        if let Some(session) = self.sessions.write().await.get_mut(&key) {
+            session.subscriptions.insert(request.params.uri);
+        }
+        Ok(EmptyResult::default())
+    }
+
+    async fn handle_unsubscribe_request(
+        &self,
+        request: UnsubscribeRequest,
+        runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<EmptyResult, RpcError> {
+        let key = self.ensure_session(&runtime).await;
+        if let Some(session) = self.sessions.write().await.get_mut(&key) {
+            session.subscriptions.remove(&request.params.uri);
+        }
+        Ok(EmptyResult::default())
+    }
+
+    async fn handle_list_prompts_request(
+        &self,
+        _request: ListPromptsRequest,
+        _runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<ListPromptsResult, RpcError> {
+        Ok(ListPromptsResult {
+            meta: None,
+            next_cursor: None,
+            prompts: prompts::list_prompts(),
+        })
+    }
+
+    async fn handle_get_prompt_request(
+        &self,
+        request: GetPromptRequest,
+        _runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<GetPromptResult, RpcError> {
+        prompts::get_prompt(
+            &request.params.name,
+            &request.params.arguments.unwrap_or_default(),
+        )
+    }
+
+    async fn handle_complete_request(
+        &self,
+        request: CompleteRequest,
+        runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<CompleteResult, RpcError> {
+        let params = request.params;
+        let arguments = match &params.ref_ {
+            CompleteRequestParamsRef::PromptReference(prompt) => {
+                prompts::prompt_arguments(&prompt.name)
+                    .map(|args| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+                    .ok_or_else(|| {
+                        RpcError::invalid_params()
+                            .with_message(format!("Unknown prompt '{}'", prompt.name))
+                    })?
+            }
+            CompleteRequestParamsRef::ResourceTemplateReference(template) => {
+                resources::template_arguments(&template.uri).ok_or_else(|| {
+                    RpcError::invalid_params()
+                        .with_message(format!("Unknown resource template '{}'", template.uri))
+                })?
+            }
+        };
+        if !arguments.contains(&params.argument.name) {
+            return Err(RpcError::invalid_params()
+                .with_message(format!("Unknown argument '{}'", params.argument.name)));
+        }
+
+        let roots = if params.argument.name == "path" {
+            Self::allowed_roots(&runtime).await
+        } else {
+            Vec::new()
+        };
+        let state = self.state.read().await;
+        Ok(completion::complete_argument(
+            &state,
+            &roots,
+            &params.argument.name,
+            &params.argument.value,
+        ))
+    }
+
+    /// Jobs can't be matched to the cancelled request, whose id the SDK keeps
+    /// from the handler, so every job of the session is stopped; the tool
+    /// descriptions say so.
+    async fn handle_cancelled_notification(
+        &self,
+        notification: CancelledNotification,
+        runtime: Arc<dyn McpServer>,
+    ) -> std::result::Result<(), RpcError> {
+        let key = self.ensure_session(&runtime).await;
+        if let Some(session) = self.sessions.write().await.get_mut(&key) {
+            let cancelled = session.jobs.cancel_all();
+            tracing::info!(
+                "Request {:?} cancelled ({}); stopped {} running job(s)",
+                notification.params.request_id,
+                notification.params.reason.as_deref().unwrap_or("no reason given"),
+                cancelled
+            );
+        }
+        Ok(())
+    }
+
     async fn handle_call_tool_request(
         &self,
         request: CallToolRequest,
-        _runtime: Arc<dyn McpServer>,
+        runtime: Arc<dyn McpServer>,
     ) -> std::result::Result<CallToolResult, CallToolError> {
+        let groups = self.session_groups(&runtime).await;
+        if let Some(group) = ToolGroup::of_tool(&request.params.name).filter(|g| !groups.contains(*g)) {
+            return Err(CallToolError::from_message(format!(
+                "Tool '{}' belongs to the disabled tool group '{}'",
+                request.params.name,
+                group.name()
+            )));
+        }
+
+        let arguments = request.params.arguments.clone();
         let tool_params: BinaryTools =
             BinaryTools::try_from(request.params).map_err(CallToolError::new)?;
-        
-        match tool_params {
-            BinaryTools::LoadBinary(tool) => tool.call_tool(&self.state).await,
-            BinaryTools::ReadBytes(tool) => tool.call_tool(&self.state).await,
-            BinaryTools::SearchPattern(tool) => tool.call_tool(&self.state).await,
-            BinaryTools::ExtractSegment(tool) => tool.call_tool(&self.state).await,
-            BinaryTools::AddBookmark(tool) => tool.call_tool(&self.state).await,
-            BinaryTools::ReadString(tool) => tool.call_tool(&self.state).await,
-            BinaryTools::ReadInteger(tool) => tool.call_tool(&self.state).await,
-            BinaryTools::CalculateHash(tool) => tool.call_tool(&self.state).await,
-            BinaryTools::GetInfo(tool) => tool.call_tool(&self.state).await,
-            BinaryTools::AddNote(tool) => tool.call_tool(&self.state).await,
-            BinaryTools::SetOutput(tool) => tool.call_tool(&self.state).await,
-        }
-    }
-}
\ No newline at end of file
+
+        let pending_loss = match &tool_params {
+            BinaryTools::LoadBinary(tool) => tool.pending_loss(&*self.state.read().await),
+            BinaryTools::LoadBytes(tool) => tool.pending_loss(&*self.state.read().await),
+            BinaryTools::LoadHex(tool) => tool.pending_loss(&*self.state.read().await),
+            BinaryTools::SetOutput(tool) => tool.pending_loss(&*self.state.read().await),
+            _ => None,
+        };
+        if let Some(message) = pending_loss {
+            if !self.confirm(&runtime, message).await {
+                return Ok(CallToolResult::text_content(vec![TextContent::from(format!(
+                    "❎ {} cancelled; nothing was changed",
+                    tool_params.tool_name()
+                ))]));
+            }
+        }
+
+        let tool_name = tool_params.tool_name();
+        // Sampling waits on the client, so only the note it produces is added in the turn below
+        let sampled = match &tool_params {
+            BinaryTools::SummarizeRegion(tool) => {
+                Some(self.sample_note(&runtime, |s| tool.sampling_request(s)).await)
+            }
+            BinaryTools::ProposeBookmarkName(tool) => {
+                Some(self.sample_note(&runtime, |s| tool.sampling_request(s)).await)
+            }
+            BinaryTools::ClassifyStrings(tool) => {
+                Some(self.sample_note(&runtime, |s| tool.sampling_request(s)).await)
+            }
+            _ => None,
+        }
+        // CallToolError isn't Send, so settle it into an error result before awaiting again
+        .map(|sampled| match sampled {
+            Ok((note, result)) => (Some(note), result),
+            Err(e) => (None, CallToolResult::from(e)),
+        });
+
+        // A call that may change the state holds the turn until its changes are
+        // recorded and journaled, so no other call's changes mix with them
+        let mut call = self.executor.begin(&tool_params).await;
+        let result = match (sampled, tool_params) {
+            (Some((note, result)), _) => {
+                if let Some(note) = note {
+                    self.state.write().await.add_note(note);
+                }
+                Ok(result)
+            }
+            (None, BinaryTools::ListToolGroups(tool)) => tool.call_tool(&groups).await,
+            (None, BinaryTools::SetToolGroup(tool)) => self.set_tool_group(tool, &runtime).await,
+            (None, tool @ (BinaryTools::SearchPattern(_)
+            | BinaryTools::CalculateHash(_)
+            | BinaryTools::CalculateEntropy(_))) => {
+                let token = match &tool {
+                    BinaryTools::SearchPattern(t) => t.progress_token.clone(),
+                    BinaryTools::CalculateHash(t) => t.progress_token.clone(),
+                    BinaryTools::CalculateEntropy(t) => t.progress_token.clone(),
+                    _ => None,
+                };
+                let author = session_key(&runtime);
+                self.run_job(&runtime, token, |job| self.executor.run(tool, &author, job)).await
+            }
+            (None, tool) => {
+                self.executor.run(tool, &session_key(&runtime), JobContext::default()).await
+            }
+        }
+        .unwrap_or_else(CallToolResult::from);
+
+        let changes = self.executor.finish(&mut call).await;
+        if let Some(journal) = &self.journal {
+            let s = self.state.read().await;
+            journal.record(&s, &changes, &session_key(&runtime), &tool_name, arguments, &result);
+        }
+        drop(call);
+        self.publisher.publish(changes).await;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elicited(action: ElicitResultAction, confirm: Option<ElicitResultContentValue>) -> ElicitResult {
+        ElicitResult {
+            action,
+            content: confirm.map(|value| HashMap::from([("confirm".to_string(), value)])),
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn only_a_ticked_acceptance_confirms() {
+        use ElicitResultContentValue::{Boolean, String as Text};
+
+        assert!(confirmed(&elicited(ElicitResultAction::Accept, Some(Boolean(true)))));
+        assert!(!confirmed(&elicited(ElicitResultAction::Accept, Some(Boolean(false)))));
+        assert!(!confirmed(&elicited(ElicitResultAction::Accept, Some(Text("true".to_string())))));
+        assert!(!confirmed(&elicited(ElicitResultAction::Accept, None)));
+        assert!(!confirmed(&elicited(ElicitResultAction::Decline, Some(Boolean(true)))));
+        assert!(!confirmed(&elicited(ElicitResultAction::Cancel, Some(Boolean(true)))));
+    }
+}
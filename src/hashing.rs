@@ -0,0 +1,273 @@
+// ============================================================================
+// src/hashing.rs
+// ============================================================================
+use crate::pe;
+use crate::ssdeep::{self, Ssdeep};
+use sha2::digest::DynDigest;
+use tlsh2::{TlshDefault, TlshDefaultBuilder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Sha3_256,
+    Sha3_512,
+    Blake2b,
+    Blake2s,
+    Blake3,
+    Crc32,
+    Ssdeep,
+    Tlsh,
+    Imphash,
+    Authentihash,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 14] = [
+        Algorithm::Md5, Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512,
+        Algorithm::Sha3_256, Algorithm::Sha3_512, Algorithm::Blake2b, Algorithm::Blake2s,
+        Algorithm::Blake3, Algorithm::Crc32, Algorithm::Ssdeep, Algorithm::Tlsh,
+        Algorithm::Imphash, Algorithm::Authentihash,
+    ];
+
+    /// Parses an algorithm name, ignoring case, '-' and '_' ('SHA-1', 'sha3_256').
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let key: String = name.chars()
+            .filter(|c| *c != '-' && *c != '_')
+            .collect::<String>()
+            .to_ascii_lowercase();
+        let key = match key.as_str() {
+            "sha3" => "sha3256",
+            "blake2" | "blake2b512" => "blake2b",
+            "blake2s256" => "blake2s",
+            other => other,
+        };
+        Self::ALL.into_iter()
+            .find(|a| a.name().replace('-', "") == key)
+            .ok_or_else(|| format!(
+                "Unknown hash algorithm '{}', expected one of: {}",
+                name,
+                Self::ALL.map(Algorithm::name).join(", ")
+            ))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Sha3_256 => "sha3-256",
+            Algorithm::Sha3_512 => "sha3-512",
+            Algorithm::Blake2b => "blake2b",
+            Algorithm::Blake2s => "blake2s",
+            Algorithm::Blake3 => "blake3",
+            Algorithm::Crc32 => "crc32",
+            Algorithm::Ssdeep => "ssdeep",
+            Algorithm::Tlsh => "tlsh",
+            Algorithm::Imphash => "imphash",
+            Algorithm::Authentihash => "authentihash",
+        }
+    }
+
+    /// Display name, e.g. 'SHA-256'.
+    pub fn label(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha1 => "SHA-1",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha512 => "SHA-512",
+            Algorithm::Sha3_256 => "SHA3-256",
+            Algorithm::Sha3_512 => "SHA3-512",
+            Algorithm::Blake2b => "BLAKE2b-512",
+            Algorithm::Blake2s => "BLAKE2s-256",
+            Algorithm::Blake3 => "BLAKE3",
+            Algorithm::Crc32 => "CRC32",
+            Algorithm::Ssdeep => "ssdeep",
+            Algorithm::Tlsh => "TLSH",
+            Algorithm::Imphash => "imphash",
+            Algorithm::Authentihash => "authentihash",
+        }
+    }
+
+    /// Hasher to be fed `total` bytes in chunks, or `None` for the PE digests.
+    pub fn hasher(self, total: usize) -> Option<Hasher> {
+        let digest: Box<dyn DynDigest + Send> = match self {
+            Algorithm::Md5 => Box::new(md5::Md5::default()),
+            Algorithm::Sha1 => Box::new(sha1::Sha1::default()),
+            Algorithm::Sha256 => Box::new(sha2::Sha256::default()),
+            Algorithm::Sha512 => Box::new(sha2::Sha512::default()),
+            Algorithm::Sha3_256 => Box::new(sha3::Sha3_256::default()),
+            Algorithm::Sha3_512 => Box::new(sha3::Sha3_512::default()),
+            Algorithm::Blake2b => Box::new(blake2::Blake2b512::default()),
+            Algorithm::Blake2s => Box::new(blake2::Blake2s256::default()),
+            Algorithm::Blake3 => return Some(Hasher::Blake3(Box::default())),
+            Algorithm::Crc32 => return Some(Hasher::Crc32(crc32fast::Hasher::new())),
+            Algorithm::Ssdeep => return Some(Hasher::Ssdeep(Box::new(Ssdeep::new(total)))),
+            Algorithm::Tlsh => return Some(Hasher::Tlsh(Box::default())),
+            Algorithm::Imphash | Algorithm::Authentihash => return None,
+        };
+        Some(Hasher::Digest(digest))
+    }
+
+    /// Digest of a whole PE image, for the algorithms that need one.
+    pub fn pe_digest(self, data: &[u8]) -> Result<String, String> {
+        match self {
+            Algorithm::Imphash => pe::imphash(data),
+            Algorithm::Authentihash => pe::authentihash(data),
+            _ => Err(format!("{} is not a PE digest", self.label())),
+        }
+    }
+}
+
+/// A running hash of one algorithm.
+pub enum Hasher {
+    Digest(Box<dyn DynDigest + Send>),
+    Blake3(Box<blake3::Hasher>),
+    Crc32(crc32fast::Hasher),
+    Ssdeep(Box<Ssdeep>),
+    Tlsh(Box<TlshDefaultBuilder>),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Digest(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Hasher::Crc32(hasher) => hasher.update(data),
+            Hasher::Ssdeep(hasher) => hasher.update(data),
+            Hasher::Tlsh(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Lowercase hex digest; CRC32 is written big-endian, as usually shown.
+    /// ssdeep and TLSH digests use their own text forms, and TLSH fails on
+    /// data too short or too uniform to characterize.
+    pub fn finish(self) -> Result<String, String> {
+        match self {
+            Hasher::Digest(hasher) => Ok(hex::encode(hasher.finalize())),
+            Hasher::Blake3(hasher) => Ok(hasher.finalize().to_hex().to_string()),
+            Hasher::Crc32(hasher) => Ok(format!("{:08x}", hasher.finalize())),
+            Hasher::Ssdeep(hasher) => hasher.finish(),
+            Hasher::Tlsh(hasher) => hasher.build()
+                .map(|tlsh| String::from_utf8_lossy(&tlsh.hash()).into_owned())
+                .ok_or_else(|| format!("TLSH needs at least {} bytes with some variety", TLSH_MIN_LENGTH)),
+        }
+    }
+}
+
+/// Shortest data TLSH will digest.
+const TLSH_MIN_LENGTH: usize = 50;
+
+/// Digest of `data` for ssdeep or TLSH.
+pub fn fuzzy_digest(algorithm: Algorithm, data: &[u8]) -> Result<String, String> {
+    let mut hasher = algorithm.hasher(data.len())
+        .filter(|_| matches!(algorithm, Algorithm::Ssdeep | Algorithm::Tlsh))
+        .ok_or_else(|| format!("{} is not a similarity digest", algorithm.label()))?;
+    hasher.update(data);
+    hasher.finish()
+}
+
+/// Recognizes an ssdeep ('blocksize:hash:hash') or TLSH ('T1' and 70 hex
+/// digits, the prefix being optional) digest.
+pub fn fuzzy_kind(digest: &str) -> Option<Algorithm> {
+    let digest = digest.trim();
+    let hex = digest.strip_prefix("T1").unwrap_or(digest);
+    if hex.len() == 70 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(Algorithm::Tlsh);
+    }
+    let mut parts = digest.splitn(3, ':');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(size), Some(_), Some(_)) if size.parse::<u32>().is_ok() => Some(Algorithm::Ssdeep),
+        _ => None,
+    }
+}
+
+/// ssdeep match score, from 0 (unrelated) to 100 (identical or nearly).
+pub fn ssdeep_score(a: &str, b: &str) -> Result<u32, String> {
+    ssdeep::compare(a, b)
+}
+
+/// TLSH distance: 0 for identical data, growing as the data differs.
+pub fn tlsh_distance(a: &str, b: &str) -> Result<i32, String> {
+    let parse = |digest: &str| {
+        let digest = digest.trim().to_ascii_uppercase();
+        let digest = if digest.starts_with("T1") { digest } else { format!("T1{}", digest) };
+        digest.parse::<TlshDefault>().map_err(|_| format!("Invalid TLSH digest '{}'", digest))
+    };
+    Ok(parse(a)?.diff(&parse(b)?, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn algorithm_names_parse_loosely() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(Algorithm::parse(algorithm.name()), Ok(algorithm));
+            assert_eq!(Algorithm::parse(algorithm.label()), Ok(algorithm));
+        }
+        assert_eq!(Algorithm::parse("SHA_1"), Ok(Algorithm::Sha1));
+        assert_eq!(Algorithm::parse("sha3"), Ok(Algorithm::Sha3_256));
+        assert_eq!(Algorithm::parse("SHA3_512"), Ok(Algorithm::Sha3_512));
+        assert_eq!(Algorithm::parse("blake2"), Ok(Algorithm::Blake2b));
+        assert_eq!(Algorithm::parse("BLAKE2s256"), Ok(Algorithm::Blake2s));
+        assert!(Algorithm::parse("sha").unwrap_err().starts_with("Unknown hash algorithm 'sha', expected one of: md5,"));
+        assert!(Algorithm::parse("").is_err());
+    }
+
+    #[test]
+    fn digests_match_known_vectors() {
+        let digest = |algorithm: Algorithm, data: &[u8]| {
+            let mut hasher = algorithm.hasher(data.len()).unwrap();
+            for chunk in data.chunks(2) {
+                hasher.update(chunk);
+            }
+            hasher.finish().unwrap()
+        };
+        assert_eq!(digest(Algorithm::Md5, b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(digest(Algorithm::Sha1, b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            digest(Algorithm::Sha256, b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            digest(Algorithm::Sha3_256, b"abc"),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+        assert_eq!(
+            digest(Algorithm::Blake3, b""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(digest(Algorithm::Crc32, b"123456789"), "cbf43926");
+
+        assert!(Algorithm::Tlsh.hasher(4).unwrap().finish().is_err());
+        assert!(Algorithm::Imphash.hasher(4).is_none());
+        assert_eq!(Algorithm::Sha1.pe_digest(b"MZ"), Err("SHA-1 is not a PE digest".to_string()));
+        assert!(Algorithm::Authentihash.pe_digest(b"MZ").is_err());
+        assert_eq!(fuzzy_digest(Algorithm::Md5, b"abc"), Err("MD5 is not a similarity digest".to_string()));
+    }
+
+    #[test]
+    fn fuzzy_digests_are_recognized() {
+        let tlsh = "T1".to_string() + &"A".repeat(70);
+        assert_eq!(fuzzy_kind(&tlsh), Some(Algorithm::Tlsh));
+        assert_eq!(fuzzy_kind(&format!(" {} ", &tlsh[2..])), Some(Algorithm::Tlsh));
+        assert_eq!(fuzzy_kind(&tlsh[..71]), None);
+        assert_eq!(fuzzy_kind("3:AXGBicFlgVNhBGcL6wCrFQEv:AXGHsNhxLsr2C"), Some(Algorithm::Ssdeep));
+        assert_eq!(fuzzy_kind("3:AXGB"), None);
+        assert_eq!(fuzzy_kind("x:AXGB:AXGB"), None);
+        assert_eq!(fuzzy_kind(""), None);
+
+        let data: Vec<u8> = (0..4096u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        let digest = fuzzy_digest(Algorithm::Tlsh, &data).unwrap();
+        assert_eq!(fuzzy_kind(&digest), Some(Algorithm::Tlsh));
+        assert_eq!(tlsh_distance(&digest, &digest[2..].to_ascii_lowercase()), Ok(0));
+        assert_eq!(fuzzy_kind(&fuzzy_digest(Algorithm::Ssdeep, &data).unwrap()), Some(Algorithm::Ssdeep));
+    }
+}
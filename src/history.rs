@@ -0,0 +1,290 @@
+// ============================================================================
+// src/history.rs
+// ============================================================================
+use crate::state::{format_timestamp, unix_time, BinarySegment, Bookmark, Note, ServerState, StateChange};
+use crate::tools::BinaryTools;
+use std::collections::{HashMap, VecDeque};
+
+/// Entries kept for undo at most.
+const MAX_ENTRIES: usize = 200;
+
+/// Approximate bytes kept in undo and redo snapshots at most. The oldest
+/// entries are dropped first; the newest entry is always kept.
+const MAX_BYTES: usize = 256 * 1024 * 1024;
+
+/// Parts of the state a tool may modify, which its snapshot captures. Tools
+/// without any are not recorded: they either don't modify the analysis state
+/// or are history tools themselves.
+pub fn touches(tool_name: &str) -> &'static [StateChange] {
+    use StateChange::*;
+    match tool_name {
+        "load_binary" | "load_bytes" | "load_hex" => &[Buffer, Bookmarks, Segments, Notes],
+        "apply_patch" => &[Buffer, Bookmarks, Segments, Notes],
+        "extract_segment" | "remove_segment" | "relabel_segment" | "calculate_entropy" => &[Segments],
+        "add_bookmark" => &[Bookmarks],
+        "rename_bookmark" | "remove_bookmark" => &[Bookmarks, Notes],
+        "add_note" | "update_note" | "delete_note" | "summarize_region" | "propose_bookmark_name"
+            | "classify_strings" => &[Notes],
+        "set_output" => &[Output],
+        _ => &[],
+    }
+}
+
+/// What a particular call may modify. A `load_bytes` chunk that doesn't end
+/// its upload only stores the bytes, so it captures and records nothing.
+pub fn call_touches(tool: &BinaryTools) -> &'static [StateChange] {
+    match tool {
+        BinaryTools::LoadBytes(load) if !load.loads() => &[],
+        tool => touches(&tool.tool_name()),
+    }
+}
+
+/// Copies of the parts of the state an operation touched.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    buffer: Option<(Vec<u8>, Option<String>)>,
+    bookmarks: Option<HashMap<String, Bookmark>>,
+    segments: Option<Vec<BinarySegment>>,
+    notes: Option<Vec<Note>>,
+    output: Option<String>,
+}
+
+impl Snapshot {
+    /// Captures what a call could modify, before it runs.
+    pub fn before(state: &ServerState, tool: &BinaryTools) -> Self {
+        Self::capture(state, call_touches(tool))
+    }
+
+    fn capture(state: &ServerState, parts: &[StateChange]) -> Self {
+        let mut snapshot = Snapshot::default();
+        for part in parts {
+            match part {
+                StateChange::Buffer => {
+                    snapshot.buffer = Some((state.buffer.clone(), state.file_loaded.clone()))
+                }
+                StateChange::Bookmarks => snapshot.bookmarks = Some(state.bookmarks.clone()),
+                StateChange::Segments => snapshot.segments = Some(state.segments.clone()),
+                StateChange::Notes => snapshot.notes = Some(state.analysis_notes.clone()),
+                StateChange::Output => snapshot.output = Some(state.output.clone()),
+            }
+        }
+        snapshot
+    }
+
+    /// Keeps only the parts that actually changed. Returns `None` if a changed
+    /// part wasn't captured, in which case the operation can't be undone.
+    fn retain(mut self, changes: &[StateChange]) -> Option<Self> {
+        let keep = |part| changes.contains(&part);
+        self.buffer = self.buffer.filter(|_| keep(StateChange::Buffer));
+        self.bookmarks = self.bookmarks.filter(|_| keep(StateChange::Bookmarks));
+        self.segments = self.segments.filter(|_| keep(StateChange::Segments));
+        self.notes = self.notes.filter(|_| keep(StateChange::Notes));
+        self.output = self.output.filter(|_| keep(StateChange::Output));
+        (self.changes().len() == changes.len()).then_some(self)
+    }
+
+    /// The parts of the state this snapshot holds.
+    fn changes(&self) -> Vec<StateChange> {
+        [
+            (self.buffer.is_some(), StateChange::Buffer),
+            (self.bookmarks.is_some(), StateChange::Bookmarks),
+            (self.segments.is_some(), StateChange::Segments),
+            (self.notes.is_some(), StateChange::Notes),
+            (self.output.is_some(), StateChange::Output),
+        ]
+        .into_iter()
+        .filter_map(|(held, change)| held.then_some(change))
+        .collect()
+    }
+
+    /// Approximate heap size, used to bound the history.
+    fn size(&self) -> usize {
+        self.buffer.as_ref().map_or(0, |(data, _)| data.len())
+            + self.bookmarks.as_ref().map_or(0, |b| b.len() * 128)
+            + self.segments.as_ref().map_or(0, |s| s.iter().map(|seg| seg.data.len() + 64).sum())
+            + self.notes.as_ref().map_or(0, |n| n.iter().map(|note| note.text.len() + 128).sum())
+            + self.output.as_ref().map_or(0, |o| o.len())
+    }
+
+    /// Puts the captured parts back into the state and returns a snapshot of
+    /// the values they replaced.
+    fn restore(self, state: &mut ServerState) -> Snapshot {
+        let current = Self::capture(state, &self.changes());
+        if let Some((buffer, file_loaded)) = self.buffer {
+            state.buffer = buffer;
+            state.file_loaded = file_loaded;
+        }
+        if let Some(bookmarks) = self.bookmarks {
+            state.bookmarks = bookmarks;
+        }
+        if let Some(segments) = self.segments {
+            state.segments = segments;
+        }
+        if let Some(notes) = self.notes {
+            state.analysis_notes = notes;
+        }
+        if let Some(output) = self.output {
+            state.output = output;
+        }
+        for change in current.changes() {
+            state.mark_changed(change);
+        }
+        current
+    }
+}
+
+/// One recorded operation and the state needed to reverse it.
+struct Entry {
+    tool: String,
+    time: u64,
+    /// Undo stack: values before the operation; redo stack: values after it
+    snapshot: Snapshot,
+}
+
+impl Entry {
+    fn describe(&self) -> String {
+        let parts = self.snapshot.changes()
+            .iter()
+            .map(|c| format!("{:?}", c).to_lowercase())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{} at {} ({})", self.tool, format_timestamp(self.time), parts)
+    }
+}
+
+/// Undo and redo stacks of recorded operations.
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+}
+
+impl History {
+    /// Records a finished operation given the snapshot taken before it and the
+    /// parts of the state it changed. A new operation clears the redo stack.
+    pub fn record(&mut self, tool: &str, before: Snapshot, changes: &[StateChange]) {
+        if changes.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        match before.retain(changes) {
+            Some(snapshot) => self.undo.push_back(Entry {
+                tool: tool.to_string(),
+                time: unix_time(),
+                snapshot,
+            }),
+            None => {
+                // A part we didn't capture changed; older entries can't be replayed across it
+                tracing::warn!("{} changed state that wasn't captured; clearing undo history", tool);
+                self.undo.clear();
+            }
+        }
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.undo.len() > MAX_ENTRIES {
+            self.undo.pop_front();
+        }
+        let mut size: usize = self.undo.iter().map(|e| e.snapshot.size()).sum();
+        while self.undo.len() > 1 && size > MAX_BYTES {
+            if let Some(entry) = self.undo.pop_front() {
+                size -= entry.snapshot.size();
+            }
+        }
+    }
+
+    /// Reverts up to `steps` operations, returning descriptions of those undone.
+    pub fn undo(&mut self, state: &mut ServerState, steps: usize) -> Vec<String> {
+        let mut undone = Vec::new();
+        for _ in 0..steps {
+            let Some(entry) = self.undo.pop_back() else { break };
+            undone.push(entry.describe());
+            let after = entry.snapshot.restore(state);
+            self.redo.push(Entry { snapshot: after, ..entry });
+        }
+        undone
+    }
+
+    /// Re-applies up to `steps` undone operations, returning their descriptions.
+    pub fn redo(&mut self, state: &mut ServerState, steps: usize) -> Vec<String> {
+        let mut redone = Vec::new();
+        for _ in 0..steps {
+            let Some(entry) = self.redo.pop() else { break };
+            redone.push(entry.describe());
+            let before = entry.snapshot.restore(state);
+            self.undo.push_back(Entry { snapshot: before, ..entry });
+        }
+        redone
+    }
+
+    /// Undoable operations, oldest first, followed by the redoable ones.
+    pub fn list(&self) -> (Vec<String>, Vec<String>) {
+        (
+            self.undo.iter().map(Entry::describe).collect(),
+            self.redo.iter().rev().map(Entry::describe).collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer_snapshot(len: usize) -> Snapshot {
+        Snapshot { buffer: Some((vec![0; len], None)), ..Default::default() }
+    }
+
+    #[test]
+    fn undo_and_redo_swap_the_changed_parts() {
+        let mut state = ServerState::new();
+        let mut history = History::default();
+        state.output = "first".to_string();
+        let before = Snapshot::capture(&state, &[StateChange::Output, StateChange::Notes]);
+        state.output = "second".to_string();
+        history.record("set_output", before, &[StateChange::Output]);
+
+        assert_eq!(history.undo(&mut state, 5).len(), 1);
+        assert_eq!(state.output, "first");
+        assert_eq!(state.take_changes(), [StateChange::Output]);
+        assert_eq!(history.redo(&mut state, 1).len(), 1);
+        assert_eq!(state.output, "second");
+        let (undo, redo) = history.list();
+        assert_eq!((undo.len(), redo.len()), (1, 0));
+        assert!(undo[0].starts_with("set_output at ") && undo[0].ends_with("(output)"));
+    }
+
+    #[test]
+    fn uncaptured_changes_clear_the_history() {
+        let state = ServerState::new();
+        let snapshot = Snapshot::capture(&state, &[StateChange::Segments, StateChange::Notes]);
+        assert_eq!(snapshot.clone().retain(&[StateChange::Notes]).unwrap().changes(), [StateChange::Notes]);
+        assert!(snapshot.clone().retain(&[StateChange::Segments, StateChange::Buffer]).is_none());
+
+        let mut history = History::default();
+        history.record("add_note", snapshot.clone(), &[StateChange::Notes]);
+        history.record("load_bytes", snapshot, &[StateChange::Buffer]);
+        assert!(history.list().0.is_empty());
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted() {
+        let mut history = History::default();
+        for _ in 0..MAX_ENTRIES + 5 {
+            let before = Snapshot { output: Some(String::new()), ..Default::default() };
+            history.record("set_output", before, &[StateChange::Output]);
+        }
+        assert_eq!(history.undo.len(), MAX_ENTRIES);
+
+        let mut history = History::default();
+        for _ in 0..3 {
+            history.record("load_bytes", buffer_snapshot(MAX_BYTES / 3), &[StateChange::Buffer]);
+        }
+        assert_eq!(history.undo.len(), 3);
+        history.record("load_bytes", buffer_snapshot(MAX_BYTES / 2), &[StateChange::Buffer]);
+        assert_eq!(history.undo.len(), 2);
+        // The newest entry is kept even on its own over the limit
+        history.record("load_bytes", buffer_snapshot(MAX_BYTES + 1), &[StateChange::Buffer]);
+        assert_eq!(history.undo.len(), 1);
+    }
+}
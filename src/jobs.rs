@@ -0,0 +1,99 @@
+// ============================================================================
+// src/jobs.rs
+// ============================================================================
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Number of bytes processed between cancellation checks and progress reports.
+pub const PROGRESS_STEP: usize = 1024 * 1024;
+
+/// A (processed, total) progress update.
+pub type Progress = (u64, u64);
+
+/// Cancellation flag and progress channel handed to long-running tool work.
+#[derive(Clone, Default)]
+pub struct JobContext {
+    cancelled: Arc<AtomicBool>,
+    progress: Option<mpsc::UnboundedSender<Progress>>,
+}
+
+impl JobContext {
+    pub fn new(
+        cancelled: Arc<AtomicBool>,
+        progress: Option<mpsc::UnboundedSender<Progress>>,
+    ) -> Self {
+        Self { cancelled, progress }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self, processed: u64, total: u64) {
+        if let Some(progress) = &self.progress {
+            let _ = progress.send((processed, total));
+        }
+    }
+}
+
+/// Flags of the jobs currently running for a session.
+#[derive(Default)]
+pub struct JobRegistry {
+    next_id: u64,
+    running: std::collections::HashMap<u64, Arc<AtomicBool>>,
+}
+
+impl JobRegistry {
+    pub fn start(&mut self) -> (u64, Arc<AtomicBool>) {
+        self.next_id += 1;
+        let flag = Arc::new(AtomicBool::new(false));
+        self.running.insert(self.next_id, flag.clone());
+        (self.next_id, flag)
+    }
+
+    pub fn finish(&mut self, id: u64) {
+        self.running.remove(&id);
+    }
+
+    /// Cancels every running job, returning how many were flagged.
+    pub fn cancel_all(&mut self) -> usize {
+        for flag in self.running.values() {
+            flag.store(true, Ordering::Relaxed);
+        }
+        self.running.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelling_flags_only_running_jobs() {
+        let mut jobs = JobRegistry::default();
+        let (first, first_flag) = jobs.start();
+        let (second, second_flag) = jobs.start();
+        assert_ne!(first, second);
+        jobs.finish(first);
+
+        assert_eq!(jobs.cancel_all(), 1);
+        assert!(!first_flag.load(Ordering::Relaxed));
+        assert!(JobContext::new(second_flag, None).is_cancelled());
+        jobs.finish(second);
+        assert_eq!(jobs.cancel_all(), 0);
+    }
+
+    #[test]
+    fn progress_reaches_the_channel() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let job = JobContext::new(Arc::new(AtomicBool::new(false)), Some(sender));
+        job.report(1, 4);
+        job.report(4, 4);
+        assert_eq!(receiver.try_recv(), Ok((1, 4)));
+        assert_eq!(receiver.try_recv(), Ok((4, 4)));
+
+        // Reports without a progress token go nowhere
+        JobContext::default().report(1, 1);
+    }
+}
@@ -0,0 +1,353 @@
+// ============================================================================
+// src/journal.rs
+// ============================================================================
+use crate::executor::Executor;
+use crate::history;
+use crate::state::{unix_time, ServerState, StateChange};
+use crate::tools::BinaryTools;
+use rust_mcp_sdk::schema::{CallToolRequestParams, CallToolResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// One handled tools/call request, as a line of the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Unix seconds
+    pub time: u64,
+    pub session: String,
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Map<String, Value>>,
+    pub result: CallToolResult,
+    /// SHA-256 of the buffer after the call
+    pub buffer_sha256: String,
+}
+
+/// SHA-256 of the buffer, recomputed only when the buffer changed.
+#[derive(Default)]
+pub struct BufferHash(Option<String>);
+
+impl BufferHash {
+    pub fn update(&mut self, state: &ServerState, changes: &[StateChange]) -> String {
+        if self.0.is_none() || changes.contains(&StateChange::Buffer) {
+            self.0 = Some(hex::encode(Sha256::digest(&state.buffer)));
+        }
+        self.0.clone().unwrap_or_default()
+    }
+}
+
+/// Append-only JSONL record of every tool call the server handles.
+pub struct Journal {
+    file: Mutex<File>,
+    hash: Mutex<BufferHash>,
+}
+
+impl Journal {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file: Mutex::new(file), hash: Mutex::new(BufferHash::default()) })
+    }
+
+    /// Appends a call given the state changes it made, so the buffer hash is
+    /// refreshed when it changed the buffer. Must run before the call's turn
+    /// ends, so the hash is of the buffer the call left.
+    pub fn record(
+        &self,
+        state: &ServerState,
+        changes: &[StateChange],
+        session: &str,
+        tool: &str,
+        arguments: Option<Map<String, Value>>,
+        result: &CallToolResult,
+    ) {
+        let buffer_sha256 = match self.hash.lock() {
+            Ok(mut hash) => hash.update(state, changes),
+            Err(_) => return,
+        };
+        let entry = JournalEntry {
+            time: unix_time(),
+            session: session.to_string(),
+            tool: tool.to_string(),
+            arguments,
+            result: result.clone(),
+            buffer_sha256,
+        };
+        let written = serde_json::to_string(&entry)
+            .map_err(std::io::Error::other)
+            .and_then(|line| match self.file.lock() {
+                Ok(mut file) => writeln!(file, "{}", line).and_then(|_| file.flush()),
+                Err(_) => Err(std::io::Error::other("journal lock poisoned")),
+            });
+        if let Err(e) = written {
+            tracing::warn!("Failed to write journal entry for {}: {}", tool, e);
+        }
+    }
+}
+
+/// Reads a journal, skipping blank lines.
+pub fn read_journal(path: &Path) -> Result<Vec<JournalEntry>, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Cannot open journal '{}': {}", path.display(), e))?;
+    let mut entries = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Cannot read journal line {}: {}", i + 1, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .map_err(|e| format!("Invalid journal line {}: {}", i + 1, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Outcome of replaying a journal.
+#[derive(Debug, Default)]
+pub struct ReplaySummary {
+    pub steps: usize,
+    pub divergences: usize,
+    pub skipped: usize,
+}
+
+/// Re-executes a journal against a fresh state, printing every divergence
+/// from the recorded results and buffer hashes. With `file`, load_binary
+/// calls load it instead of the recorded path; results depend on the file
+/// from then on, so only whether each call failed is compared.
+pub async fn replay(
+    entries: Vec<JournalEntry>,
+    file: Option<&Path>,
+    executor: &Executor,
+) -> ReplaySummary {
+    let mut summary = ReplaySummary::default();
+    let mut hash = BufferHash::default();
+    let mut buffer_diverged = false;
+    let mut substituted = false;
+
+    for (i, entry) in entries.into_iter().enumerate() {
+        let step = i + 1;
+        summary.steps += 1;
+
+        if Executor::needs_client(&entry.tool) {
+            println!("⏭️  step {} {}: skipped, needs an MCP client", step, entry.tool);
+            summary.skipped += 1;
+            // The note it added used up an id, which later note calls refer to
+            let added_note = history::touches(&entry.tool).contains(&StateChange::Notes)
+                && !entry.result.is_error.unwrap_or(false);
+            if added_note {
+                executor.state.write().await.skip_note_id();
+            }
+            continue;
+        }
+
+        let mut arguments = entry.arguments.clone();
+        if let (Some(file), "load_binary") = (file, entry.tool.as_str()) {
+            arguments.get_or_insert_with(Map::new).insert(
+                "path".to_string(),
+                Value::String(file.display().to_string()),
+            );
+            if !substituted {
+                println!(
+                    "🔀 step {} {}: loading '{}' instead; results are compared by outcome from here on",
+                    step, entry.tool, file.display()
+                );
+            }
+            substituted = true;
+        }
+        let params = CallToolRequestParams { name: entry.tool.clone(), arguments };
+        let (result, changes) = match BinaryTools::try_from(params) {
+            Ok(tool) => executor.call(tool, &entry.session).await,
+            Err(e) => {
+                println!("❌ step {} {}: arguments no longer parse: {}", step, entry.tool, e);
+                summary.divergences += 1;
+                continue;
+            }
+        };
+
+        let buffer_sha256 = hash.update(&*executor.state.read().await, &changes);
+
+        // A substituted file changes names, sizes and hashes; only outcomes must match
+        let expected = comparable(&entry.result, substituted);
+        let actual = comparable(&result, substituted);
+        if expected != actual {
+            summary.divergences += 1;
+            println!("❌ step {} {}: result differs", step, entry.tool);
+            println!("   expected: {}", expected);
+            println!("   actual:   {}", actual);
+        }
+
+        if substituted {
+            continue;
+        }
+        match (buffer_sha256 == entry.buffer_sha256, buffer_diverged) {
+            (false, false) => {
+                summary.divergences += 1;
+                buffer_diverged = true;
+                println!(
+                    "❌ step {} {}: buffer differs from here on (expected sha256 {}, got {})",
+                    step, entry.tool, entry.buffer_sha256, buffer_sha256
+                );
+            }
+            (true, true) => {
+                buffer_diverged = false;
+                println!("✅ step {} {}: buffer matches the journal again", step, entry.tool);
+            }
+            _ => {}
+        }
+    }
+
+    summary
+}
+
+/// The parts of a result a replay must reproduce, with timestamps masked.
+fn comparable(result: &CallToolResult, outcome_only: bool) -> Value {
+    let is_error = result.is_error.unwrap_or(false);
+    if outcome_only {
+        return serde_json::json!({ "is_error": is_error });
+    }
+    let mut value = serde_json::json!({
+        "is_error": is_error,
+        "content": result.content,
+        "structured": result.structured_content,
+    });
+    mask_timestamps(&mut value);
+    value
+}
+
+/// Replaces RFC 3339 timestamps (`YYYY-MM-DDTHH:MM:SSZ`) in every string.
+fn mask_timestamps(value: &mut Value) {
+    match value {
+        Value::String(text) => {
+            const PATTERN: &[u8] = b"dddd-dd-ddTdd:dd:ddZ";
+            let bytes = text.as_bytes();
+            let mut masked = String::with_capacity(text.len());
+            let mut i = 0;
+            while i < bytes.len() {
+                let matches = bytes.len() - i >= PATTERN.len()
+                    && PATTERN.iter().zip(&bytes[i..]).all(|(&p, &b)| match p {
+                        b'd' => b.is_ascii_digit(),
+                        _ => p == b,
+                    });
+                if matches {
+                    masked.push_str("<time>");
+                    i += PATTERN.len();
+                } else {
+                    let ch = text[i..].chars().next().unwrap_or_default();
+                    masked.push(ch);
+                    i += ch.len_utf8().max(1);
+                }
+            }
+            *text = masked;
+        }
+        Value::Array(items) => items.iter_mut().for_each(mask_timestamps),
+        Value::Object(map) => map.values_mut().for_each(mask_timestamps),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Note;
+    use rust_mcp_sdk::schema::TextContent;
+    use serde_json::json;
+
+    /// Journals `steps` as the server would. `summarize_region` stands for a
+    /// sampled call: it adds a note without running the tool.
+    async fn journal_of(steps: &[(&str, Value)]) -> Vec<JournalEntry> {
+        let executor = Executor::new(None);
+        let mut hash = BufferHash::default();
+        let mut entries = Vec::new();
+        for (tool, arguments) in steps {
+            let arguments = arguments.as_object().cloned();
+            let (result, changes) = if *tool == "summarize_region" {
+                let mut s = executor.state.write().await;
+                s.add_note(Note { text: "sampled".to_string(), ..Default::default() });
+                (CallToolResult::text_content(vec![TextContent::from("✅")]), s.take_changes())
+            } else {
+                let params = CallToolRequestParams { name: tool.to_string(), arguments: arguments.clone() };
+                executor.call(BinaryTools::try_from(params).unwrap(), "test").await
+            };
+            let buffer_sha256 = hash.update(&*executor.state.read().await, &changes);
+            entries.push(JournalEntry {
+                time: 0,
+                session: "test".to_string(),
+                tool: tool.to_string(),
+                arguments,
+                result,
+                buffer_sha256,
+            });
+        }
+        entries
+    }
+
+    fn temp_file(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("journal-test-{}-{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn replay_reproduces_a_journal() {
+        let entries = journal_of(&[
+            ("load_hex", json!({ "hex": "4d5a9000" })),
+            ("summarize_region", json!({})),
+            ("add_note", json!({ "note": "header", "offset": 0 })),
+            ("update_note", json!({ "id": 2, "note": "MZ header" })),
+            ("list_notes", json!({})),
+            ("read_bytes", json!({ "offset": 0, "length": 2 })),
+        ])
+        .await;
+        let summary = replay(entries, None, &Executor::new(None)).await;
+        // Note ids still line up; only list_notes misses the sampled note
+        assert_eq!((summary.steps, summary.skipped, summary.divergences), (6, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn a_substituted_file_is_compared_by_outcome() {
+        let original = temp_file("original", b"MZ original file");
+        let substitute = temp_file("substitute", b"MZ another, longer file");
+        let entries = journal_of(&[
+            ("load_binary", json!({ "path": original.display().to_string() })),
+            ("get_info", json!({})),
+            ("calculate_hash", json!({})),
+            ("read_bytes", json!({ "offset": 0, "length": 2 })),
+            ("read_bytes", json!({ "offset": 1000, "length": 2 })),
+        ])
+        .await;
+
+        let summary = replay(entries.clone(), Some(&substitute), &Executor::new(None)).await;
+        assert_eq!(summary.divergences, 0);
+        let summary = replay(entries, None, &Executor::new(None)).await;
+        assert_eq!(summary.divergences, 0);
+
+        let short = temp_file("short", b"M");
+        let entries = journal_of(&[
+            ("load_binary", json!({ "path": original.display().to_string() })),
+            ("read_bytes", json!({ "offset": 0, "length": 2 })),
+        ])
+        .await;
+        let summary = replay(entries, Some(&short), &Executor::new(None)).await;
+        assert_eq!(summary.divergences, 1);
+        for path in [original, substitute, short] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn timestamps_are_masked_everywhere() {
+        let mut value = json!({
+            "text": "added 2026-10-18T15:59:34Z, edited 2026-10-18T16:00:00Z ✎",
+            "list": ["2026-10-18T15:59:34Z", "2026-10-18 15:59:34", 42],
+        });
+        mask_timestamps(&mut value);
+        assert_eq!(value, json!({
+            "text": "added <time>, edited <time> ✎",
+            "list": ["<time>", "2026-10-18 15:59:34", 42],
+        }));
+    }
+}
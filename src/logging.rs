@@ -0,0 +1,104 @@
+// ============================================================================
+// src/logging.rs
+// ============================================================================
+use crate::state::{ServerState, StateChange};
+use rust_mcp_sdk::schema::LoggingLevel;
+use serde_json::{json, Value};
+
+/// Logger name used in notifications/message.
+pub const LOGGER: &str = "binary-analysis";
+
+/// Level used for sessions that never called logging/setLevel.
+pub const DEFAULT_LEVEL: LoggingLevel = LoggingLevel::Info;
+
+/// RFC 5424 severity, lowest first. `LoggingLevel`'s derived `Ord` is
+/// alphabetical, so it can't be compared directly.
+fn severity(level: LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
+
+/// Whether a message at `level` passes a session's minimum level.
+pub fn is_enabled(minimum: LoggingLevel, level: LoggingLevel) -> bool {
+    severity(level) >= severity(minimum)
+}
+
+/// Structured description of a state change, sent as the `data` of
+/// notifications/message and recorded as tracing fields.
+pub fn change_event(state: &ServerState, change: StateChange) -> Value {
+    match change {
+        StateChange::Buffer => json!({
+            "change": "buffer",
+            "file": state.file_loaded,
+            "size": state.buffer.len(),
+        }),
+        StateChange::Bookmarks => json!({
+            "change": "bookmarks",
+            "count": state.bookmarks.len(),
+        }),
+        StateChange::Segments => json!({
+            "change": "segments",
+            "count": state.segments.len(),
+        }),
+        StateChange::Notes => json!({
+            "change": "notes",
+            "count": state.analysis_notes.len(),
+            "latest": state.analysis_notes.last().map(|note| note.id),
+        }),
+        StateChange::Output => json!({
+            "change": "output",
+            "length": state.output.len(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVELS: [LoggingLevel; 8] = [
+        LoggingLevel::Debug,
+        LoggingLevel::Info,
+        LoggingLevel::Notice,
+        LoggingLevel::Warning,
+        LoggingLevel::Error,
+        LoggingLevel::Critical,
+        LoggingLevel::Alert,
+        LoggingLevel::Emergency,
+    ];
+
+    #[test]
+    fn levels_compare_by_severity() {
+        for (i, &level) in LEVELS.iter().enumerate() {
+            assert_eq!(severity(level) as usize, i);
+            for (j, &minimum) in LEVELS.iter().enumerate() {
+                assert_eq!(is_enabled(minimum, level), i >= j, "{:?} at minimum {:?}", level, minimum);
+            }
+        }
+        // Alphabetically 'error' sorts before 'info'
+        assert!(is_enabled(DEFAULT_LEVEL, LoggingLevel::Error));
+        assert!(!is_enabled(DEFAULT_LEVEL, LoggingLevel::Debug));
+    }
+
+    #[test]
+    fn change_events_describe_the_new_state() {
+        let mut state = ServerState::new();
+        state.replace_buffer(vec![0; 8], "a.bin".to_string());
+        assert_eq!(
+            change_event(&state, StateChange::Buffer),
+            json!({ "change": "buffer", "file": "a.bin", "size": 8 })
+        );
+        assert_eq!(
+            change_event(&state, StateChange::Notes),
+            json!({ "change": "notes", "count": 0, "latest": null })
+        );
+    }
+}
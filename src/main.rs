@@ -1,38 +1,229 @@
+mod cli;
+mod completion;
+mod diff;
+mod entropy;
+mod executor;
+mod groups;
+mod hashing;
 mod handler;
+mod history;
+mod jobs;
+mod journal;
+mod logging;
+mod patch;
+mod pe;
+mod prompts;
+mod resources;
+mod sampling;
+mod session;
+mod ssdeep;
 mod tools;
 mod state;
+#[cfg(feature = "tui")]
+mod tui;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use groups::{ToolGroup, ToolGroups};
+use executor::Executor;
 use handler::BinaryAnalysisHandler;
+use journal::Journal;
 use rust_mcp_sdk::event_store::InMemoryEventStore;
 use rust_mcp_sdk::mcp_server::{hyper_server, HyperServerOptions};
 use rust_mcp_sdk::schema::{
-    Implementation, InitializeResult, ServerCapabilities, ServerCapabilitiesTools,
-    LATEST_PROTOCOL_VERSION,
+    Implementation, InitializeResult, ServerCapabilities, ServerCapabilitiesPrompts,
+    ServerCapabilitiesResources, ServerCapabilitiesTools, LATEST_PROTOCOL_VERSION,
 };
-use rust_mcp_sdk::{error::SdkResult, mcp_server::ServerHandler};
+use rust_mcp_sdk::error::SdkResult;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+const INSTRUCTIONS: &str = "\
+Binary analysis server for reverse engineering. All tools share one analysis state: a single \
+loaded buffer plus bookmarks, extracted segments, analysis notes and a final output text.
+
+Workflow: load a file with load_binary (a path on the server), load_bytes (base64 from the \
+client, chunked with upload_id for large files) or load_hex (pasted hex dumps), inspect it \
+with get_info, read_bytes, hexdump (xxd / hexdump -C layout, paginated, with bookmark, \
+segment and note markers), read_string and \
+read_integer (1/2/4/8 bytes, 'little' or 'big' endian), and locate structures with \
+search_pattern (hex patterns such as '4D5A'). Record what you learn with add_bookmark \
+(named offsets or ranges with a type, tags and a comment; query them with list_bookmarks and \
+bookmarks_at, change them with rename_bookmark and remove_bookmark), extract_segment \
+(labelled byte ranges; manage them with list_segments, relabel_segment and remove_segment), \
+add_note (optionally anchored to an offset, range or bookmark; list_notes filters them by \
+range, tag, author or text, update_note and delete_note edit them), and finish with set_output. \
+calculate_hash returns the SHA-256 of the buffer or a range, or any list of MD5, SHA-1, \
+SHA-2, SHA3, BLAKE2/3, CRC32, ssdeep, TLSH, imphash and authentihash digests; \
+compare_hashes scores ssdeep and TLSH similarity against files, segments or known digests. \
+calculate_entropy profiles entropy in sliding windows and flags encrypted, compressed or \
+padding regions. search_pattern, calculate_hash and calculate_entropy report progress when \
+given a progress_token and stop when the request is cancelled. Offsets are byte offsets into \
+the loaded file. Loading a new file discards bookmarks and segments; notes are kept, but \
+detached from their offsets and bookmarks. export_range and export_segment \
+return carved bytes as embedded resources; save_bytes writes them under the server's output \
+directory. \
+undo, redo and list_history revert or re-apply any change to the analysis state. \
+The editing group compares and patches buffers: diff_buffers aligns the buffer with another \
+file or a segment and lists the changed regions; create_patch and apply_patch produce and \
+apply IPS, BPS and bsdiff patches; create_patch returns the patch as an embedded resource \
+or writes it to the output directory.
+
+Tools are organized in groups (core, annotation, editing, crypto, sampling); use \
+list_tool_groups and set_tool_group to change which are offered. The buffer, segments, \
+bookmarks, notes and output are also available as binary:// resources, and the prompts \
+describe standard triage workflows.
+summarize_region, propose_bookmark_name and classify_strings ask your own model through \
+sampling and store its answer as a note tagged model-generated.";
+
+/// Keep-alive ping period, also used to notice disconnected sessions
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Parser)]
 #[command(name = "binary-analysis-mcp")]
 #[command(about = "MCP server for binary file analysis and reverse engineering")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(short, long, default_value = "8080")]
     port: u16,
+    /// Comma separated tool groups enabled for new sessions (core is always enabled)
+    #[arg(long, default_value = "core,annotation,editing,crypto,sampling")]
+    tool_groups: String,
+    /// Print the full analysis state to stderr after every change
+    #[arg(long)]
+    debug_state: bool,
+    /// Never send sampling/createMessage requests to clients
+    #[arg(long)]
+    no_sampling: bool,
+    /// Directory save_bytes and create_patch may write files into
+    #[arg(long, global = true)]
+    output_dir: Option<PathBuf>,
+    /// Append every handled tool call, its result and the buffer hash to this JSONL file
+    #[arg(long)]
+    journal: Option<PathBuf>,
+    /// Show the live analysis state in a terminal UI that also accepts tool
+    /// commands; quitting it stops the server
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    tui: bool,
+}
+
+/// Without a subcommand the MCP server is started.
+#[derive(Subcommand)]
+enum Command {
+    /// Re-execute a journal written with --journal and report divergences
+    Replay {
+        /// Journal to replay
+        journal: PathBuf,
+        /// Load this file wherever the journal called load_binary
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Run one tool without an MCP client and print its result
+    Run {
+        /// Tool name, e.g. get_info
+        tool: String,
+        /// Tool arguments as a JSON object
+        #[arg(long)]
+        args: Option<String>,
+        /// Load this file before running the tool
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Print the full result as JSON instead of its text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run a JSONL script of {"tool": ..., "arguments": {...}} lines without an MCP client
+    Batch {
+        /// Script to run; journals written with --journal work too
+        script: PathBuf,
+        /// Load this file before the first step
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Print one JSON result per line instead of text
+        #[arg(long)]
+        json: bool,
+        /// Continue after a failed step instead of stopping
+        #[arg(long)]
+        keep_going: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() -> SdkResult<()> {
+    let args = Args::parse();
+
+    // The terminal UI owns the terminal, so logs go to its Log view instead
+    #[cfg(feature = "tui")]
+    let (use_tui, logs) = (args.tui, tui::LogBuffer::default());
+    #[cfg(not(feature = "tui"))]
+    let use_tui = false;
+    let writer = BoxMakeWriter::new(std::io::stderr);
+    #[cfg(feature = "tui")]
+    let writer = if use_tui { BoxMakeWriter::new(logs.clone()) } else { writer };
+
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(!use_tui))
         .init();
 
-    let args = Args::parse();
+    match args.command {
+        Some(Command::Replay { journal, file }) => {
+            let entries = journal::read_journal(&journal).unwrap_or_else(|e| exit_with(&e));
+            let executor = Executor::new(args.output_dir);
+            let summary = journal::replay(entries, file.as_deref(), &executor).await;
+            println!(
+                "Replayed {} step(s): {} divergence(s), {} skipped",
+                summary.steps, summary.divergences, summary.skipped
+            );
+            std::process::exit(if summary.divergences > 0 { 1 } else { 0 });
+        }
+        Some(Command::Run { tool, args: tool_args, file, json }) => {
+            let arguments = cli::parse_arguments(tool_args.as_deref())
+                .unwrap_or_else(|e| exit_with(&e));
+            let executor = Executor::new(args.output_dir);
+            if let Some(file) = file {
+                let loaded = cli::preload(&executor, &file).await;
+                if cli::is_error(&loaded) {
+                    cli::print_result("load_binary", &loaded, json);
+                    std::process::exit(1);
+                }
+            }
+            let result = cli::call(&executor, &tool, arguments).await;
+            cli::print_result(&tool, &result, json);
+            std::process::exit(if cli::is_error(&result) { 1 } else { 0 });
+        }
+        Some(Command::Batch { script, file, json, keep_going }) => {
+            let steps = cli::read_script(&script).unwrap_or_else(|e| exit_with(&e));
+            let executor = Executor::new(args.output_dir);
+            if let Some(file) = file {
+                let loaded = cli::preload(&executor, &file).await;
+                cli::print_result("load_binary", &loaded, json);
+                if cli::is_error(&loaded) {
+                    std::process::exit(1);
+                }
+            }
+            let failed = cli::batch(&executor, steps, json, keep_going).await;
+            std::process::exit(if failed > 0 { 1 } else { 0 });
+        }
+        None => {}
+    }
+
+    let mut default_groups = ToolGroups::from_list(&args.tool_groups)
+        .unwrap_or_else(|e| exit_with(&format!("Invalid --tool-groups: {}", e)));
+    if args.no_sampling {
+        let _ = default_groups.set(ToolGroup::Sampling, false);
+    }
+
+    let journal = args.journal.as_deref()
+        .map(Journal::open)
+        .transpose()
+        .unwrap_or_else(|e| exit_with(&format!("Cannot open journal: {}", e)));
 
     let server_details = InitializeResult {
         server_info: Implementation {
@@ -41,29 +232,83 @@ async fn main() -> SdkResult<()> {
             title: Some("Binary Analysis MCP Server".to_string()),
         },
         capabilities: ServerCapabilities {
-            tools: Some(ServerCapabilitiesTools { list_changed: None }),
+            tools: Some(ServerCapabilitiesTools { list_changed: Some(true) }),
+            prompts: Some(ServerCapabilitiesPrompts { list_changed: None }),
+            resources: Some(ServerCapabilitiesResources {
+                list_changed: Some(true),
+                subscribe: Some(true),
+            }),
+            completions: Some(serde_json::Map::new()),
+            logging: Some(serde_json::Map::new()),
             ..Default::default()
         },
         meta: None,
-        instructions: Some("server instructions...".to_string()),
+        instructions: Some(INSTRUCTIONS.to_string()),
         protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
     };
 
-    let handler = BinaryAnalysisHandler::new().await;
+    let handler = BinaryAnalysisHandler::new(
+        default_groups,
+        args.debug_state && !use_tui,
+        !args.no_sampling,
+        args.output_dir,
+        journal,
+    )
+    .await;
+    #[cfg(feature = "tui")]
+    let console = use_tui.then(|| handler.console());
+    let sessions = handler.sessions.clone();
 
     let server = hyper_server::create_server(
         server_details,
         handler,
         HyperServerOptions {
             host: "127.0.0.1".to_string(),
-            // port: args.port,
-            ping_interval: Duration::from_secs(5),
+            port: args.port,
+            ping_interval: PING_INTERVAL,
             event_store: Some(Arc::new(InMemoryEventStore::default())), 
             ..Default::default()
         },
     );
+    tokio::spawn(handler::prune_sessions(
+        sessions,
+        server.state().session_store.clone(),
+        PING_INTERVAL,
+    ));
+
+    #[cfg(feature = "tui")]
+    if let Some(console) = console {
+        tokio::spawn(async move {
+            if let Err(e) = server.start().await {
+                tracing::error!("Server stopped: {}", e);
+            }
+        });
+        match tokio::task::spawn_blocking(move || tui::run(console, logs)).await {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(e)) => exit_with(&format!("Terminal UI failed: {}", e)),
+            Err(e) => exit_with(&format!("Terminal UI failed: {}", e)),
+        }
+    }
 
     server.start().await?;
 
     Ok(())
 }
+
+/// Reports a command-line usage or input error and exits.
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instructions_mention_every_tool() {
+        for tool in tools::BinaryTools::tools() {
+            assert!(INSTRUCTIONS.contains(&tool.name), "INSTRUCTIONS don't mention {}", tool.name);
+        }
+    }
+}
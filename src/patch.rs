@@ -0,0 +1,653 @@
+// ============================================================================
+// src/patch.rs
+// ============================================================================
+use crate::diff::{self, Edit};
+use std::io::{Read, Write};
+
+/// Largest buffer a patch may produce.
+pub const MAX_TARGET_SIZE: usize = 1024 * 1024 * 1024;
+
+/// IPS offsets are 24 bits wide.
+const IPS_MAX_SIZE: usize = 1 << 24;
+/// An IPS record can't start here; it would read as the EOF marker.
+const IPS_EOF_OFFSET: usize = 0x454F46;
+const IPS_MAX_RECORD: usize = 0xFFFF;
+/// Equal bytes that end an IPS record; shorter runs are cheaper to include.
+const IPS_RECORD_GAP: usize = 6;
+/// Runs of one byte at least this long become RLE records.
+const IPS_MIN_RLE: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+    Bsdiff,
+}
+
+impl PatchFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "ips" => Ok(PatchFormat::Ips),
+            "bps" => Ok(PatchFormat::Bps),
+            "bsdiff" => Ok(PatchFormat::Bsdiff),
+            _ => Err(format!("Unknown patch format '{}', expected 'ips', 'bps' or 'bsdiff'", name)),
+        }
+    }
+
+    /// Recognizes a patch by its magic bytes.
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(b"PATCH") {
+            Some(PatchFormat::Ips)
+        } else if patch.starts_with(b"BPS1") {
+            Some(PatchFormat::Bps)
+        } else if patch.starts_with(b"BSDIFF40") {
+            Some(PatchFormat::Bsdiff)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "IPS",
+            PatchFormat::Bps => "BPS",
+            PatchFormat::Bsdiff => "bsdiff",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "ips",
+            PatchFormat::Bps => "bps",
+            PatchFormat::Bsdiff => "bsdiff",
+        }
+    }
+
+    /// Whether the format carries checksums of its input and output.
+    pub fn has_checksums(self) -> bool {
+        self == PatchFormat::Bps
+    }
+}
+
+/// Builds a patch turning `old` into `new`.
+pub fn create(format: PatchFormat, old: &[u8], new: &[u8]) -> Result<Vec<u8>, String> {
+    match format {
+        PatchFormat::Ips => create_ips(old, new),
+        PatchFormat::Bps => Ok(create_bps(old, new)),
+        PatchFormat::Bsdiff => create_bsdiff(old, new),
+    }
+}
+
+/// Applies a patch to `old`, validating whatever checksums the format carries.
+pub fn apply(format: PatchFormat, old: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    match format {
+        PatchFormat::Ips => apply_ips(old, patch),
+        PatchFormat::Bps => apply_bps(old, patch),
+        PatchFormat::Bsdiff => apply_bsdiff(old, patch),
+    }
+}
+
+/// Bounds-checked reader over a patch.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.data.len())
+            .ok_or_else(|| format!("Patch is truncated at byte {}", self.pos))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn be(&mut self, n: usize) -> Result<usize, String> {
+        Ok(self.take(n)?.iter().fold(0, |acc, &b| acc << 8 | b as usize))
+    }
+}
+
+fn check_size(size: usize) -> Result<usize, String> {
+    if size > MAX_TARGET_SIZE {
+        return Err(format!("Patched buffer would be {} bytes, more than the {} byte limit", size, MAX_TARGET_SIZE));
+    }
+    Ok(size)
+}
+
+// ---------------------------------------------------------------------------
+// IPS: offset/length records overwriting the source in place
+// ---------------------------------------------------------------------------
+
+fn create_ips(old: &[u8], new: &[u8]) -> Result<Vec<u8>, String> {
+    if new.len() > IPS_MAX_SIZE {
+        return Err(format!("IPS patches address at most {} bytes; use bps or bsdiff", IPS_MAX_SIZE));
+    }
+    let differs = |i: usize| i >= old.len() || old[i] != new[i];
+    let mut patch = b"PATCH".to_vec();
+    let mut i = 0;
+    while i < new.len() {
+        if !differs(i) {
+            i += 1;
+            continue;
+        }
+        // A record may not start at the offset spelling "EOF"; start it a byte earlier
+        let start = if i == IPS_EOF_OFFSET { i - 1 } else { i };
+        let mut end = i + 1;
+        let mut equal = 0;
+        let mut j = end;
+        while j < new.len() && j - start < IPS_MAX_RECORD && equal < IPS_RECORD_GAP {
+            if differs(j) {
+                equal = 0;
+                end = j + 1;
+            } else {
+                equal += 1;
+            }
+            j += 1;
+        }
+        let data = &new[start..end];
+        patch.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+        if data.len() >= IPS_MIN_RLE && data.iter().all(|&b| b == data[0]) {
+            patch.extend_from_slice(&[0, 0]);
+            patch.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            patch.push(data[0]);
+        } else {
+            patch.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            patch.extend_from_slice(data);
+        }
+        i = end;
+    }
+    patch.extend_from_slice(b"EOF");
+    if new.len() < old.len() {
+        // The truncated size is 24 bits wide too
+        if new.len() >= IPS_MAX_SIZE {
+            return Err(format!("IPS patches truncate to at most {} bytes; use bps or bsdiff", IPS_MAX_SIZE - 1));
+        }
+        patch.extend_from_slice(&(new.len() as u32).to_be_bytes()[1..]);
+    }
+    Ok(patch)
+}
+
+fn apply_ips(old: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let mut cursor = Cursor { data: patch, pos: 0 };
+    if cursor.take(5)? != b"PATCH" {
+        return Err("Not an IPS patch".to_string());
+    }
+    let mut out = old.to_vec();
+    loop {
+        if cursor.data[cursor.pos..].starts_with(b"EOF") {
+            cursor.pos += 3;
+            // The truncation extension: a 24-bit final size after EOF
+            if cursor.data.len() - cursor.pos >= 3 {
+                out.truncate(cursor.be(3)?);
+            }
+            return Ok(out);
+        }
+        let offset = cursor.be(3)?;
+        let (length, fill) = match cursor.be(2)? {
+            0 => (cursor.be(2)?, Some(cursor.take(1)?[0])),
+            n => (n, None),
+        };
+        let end = check_size(offset + length)?;
+        if out.len() < end {
+            out.resize(end, 0);
+        }
+        match fill {
+            Some(byte) => out[offset..end].fill(byte),
+            None => out[offset..end].copy_from_slice(cursor.take(length)?),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// BPS: source/target copy actions with CRC32s of source, target and patch
+// ---------------------------------------------------------------------------
+
+const BPS_SOURCE_READ: u64 = 0;
+const BPS_TARGET_READ: u64 = 1;
+const BPS_SOURCE_COPY: u64 = 2;
+const BPS_TARGET_COPY: u64 = 3;
+
+fn write_bps_number(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let low = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(0x80 | low);
+            return;
+        }
+        out.push(low);
+        value -= 1;
+    }
+}
+
+fn read_bps_number(cursor: &mut Cursor) -> Result<u64, String> {
+    let (mut value, mut shift) = (0u64, 1u64);
+    loop {
+        let byte = cursor.take(1)?[0];
+        value = (byte as u64 & 0x7F)
+            .checked_mul(shift)
+            .and_then(|part| value.checked_add(part))
+            .ok_or("BPS number overflows")?;
+        if byte & 0x80 != 0 {
+            return Ok(value);
+        }
+        shift = shift.checked_shl(7).filter(|&s| s != 0).ok_or("BPS number overflows")?;
+        value = value.checked_add(shift).ok_or("BPS number overflows")?;
+    }
+}
+
+fn write_bps_offset(out: &mut Vec<u8>, delta: i64) {
+    write_bps_number(out, (delta.unsigned_abs() << 1) | (delta < 0) as u64);
+}
+
+fn read_bps_offset(cursor: &mut Cursor) -> Result<i64, String> {
+    let value = read_bps_number(cursor)?;
+    let magnitude = (value >> 1) as i64;
+    Ok(if value & 1 != 0 { -magnitude } else { magnitude })
+}
+
+fn create_bps(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut patch = b"BPS1".to_vec();
+    write_bps_number(&mut patch, old.len() as u64);
+    write_bps_number(&mut patch, new.len() as u64);
+    write_bps_number(&mut patch, 0);
+
+    let action = |patch: &mut Vec<u8>, kind: u64, length: usize| {
+        write_bps_number(patch, ((length as u64 - 1) << 2) | kind)
+    };
+    let (mut source_at, mut target_at, mut source_relative) = (0usize, 0usize, 0usize);
+    for edit in diff::diff(old, new) {
+        match edit {
+            Edit::Equal(n) if source_at == target_at => action(&mut patch, BPS_SOURCE_READ, n),
+            Edit::Equal(n) => {
+                action(&mut patch, BPS_SOURCE_COPY, n);
+                write_bps_offset(&mut patch, source_at as i64 - source_relative as i64);
+                source_relative = source_at + n;
+            }
+            Edit::Delete(_) => {}
+            Edit::Insert(n) => {
+                action(&mut patch, BPS_TARGET_READ, n);
+                patch.extend_from_slice(&new[target_at..target_at + n]);
+            }
+        }
+        match edit {
+            Edit::Equal(n) => {
+                source_at += n;
+                target_at += n;
+            }
+            Edit::Delete(n) => source_at += n,
+            Edit::Insert(n) => target_at += n,
+        }
+    }
+
+    patch.extend_from_slice(&crc32fast::hash(old).to_le_bytes());
+    patch.extend_from_slice(&crc32fast::hash(new).to_le_bytes());
+    let patch_crc = crc32fast::hash(&patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+    patch
+}
+
+fn apply_bps(old: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.len() < 4 + 3 + 12 || !patch.starts_with(b"BPS1") {
+        return Err("Not a BPS patch".to_string());
+    }
+    let (body, footer) = patch.split_at(patch.len() - 12);
+    let crc = |at: usize| u32::from_le_bytes([footer[at], footer[at + 1], footer[at + 2], footer[at + 3]]);
+    if crc32fast::hash(&patch[..patch.len() - 4]) != crc(8) {
+        return Err("BPS patch checksum mismatch; the patch is corrupt".to_string());
+    }
+
+    let mut cursor = Cursor { data: body, pos: 4 };
+    let source_size = read_bps_number(&mut cursor)? as usize;
+    let target_size = check_size(read_bps_number(&mut cursor)? as usize)?;
+    let metadata_size = read_bps_number(&mut cursor)? as usize;
+    cursor.take(metadata_size)?;
+    if source_size != old.len() {
+        return Err(format!("BPS patch expects a {} byte source, the buffer has {}", source_size, old.len()));
+    }
+    if crc32fast::hash(old) != crc(0) {
+        return Err("BPS source checksum mismatch; the patch is for a different file".to_string());
+    }
+
+    let mut out: Vec<u8> = Vec::with_capacity(target_size);
+    let (mut source_relative, mut target_relative) = (0i64, 0i64);
+    let out_of_bounds = || "BPS action reads outside its source".to_string();
+    while cursor.pos < body.len() {
+        let data = read_bps_number(&mut cursor)?;
+        let length = (data >> 2) as usize + 1;
+        if out.len() + length > target_size {
+            return Err("BPS actions write past the target size".to_string());
+        }
+        match data & 3 {
+            BPS_SOURCE_READ => {
+                let at = out.len();
+                out.extend_from_slice(old.get(at..at + length).ok_or_else(out_of_bounds)?);
+            }
+            BPS_TARGET_READ => out.extend_from_slice(cursor.take(length)?),
+            BPS_SOURCE_COPY => {
+                source_relative += read_bps_offset(&mut cursor)?;
+                let at = usize::try_from(source_relative).map_err(|_| out_of_bounds())?;
+                out.extend_from_slice(old.get(at..at + length).ok_or_else(out_of_bounds)?);
+                source_relative += length as i64;
+            }
+            BPS_TARGET_COPY => {
+                target_relative += read_bps_offset(&mut cursor)?;
+                // Byte by byte: the copy may overlap the bytes it produces
+                for _ in 0..length {
+                    let at = usize::try_from(target_relative).ok()
+                        .filter(|&at| at < out.len())
+                        .ok_or_else(out_of_bounds)?;
+                    out.push(out[at]);
+                    target_relative += 1;
+                }
+            }
+            _ => unreachable!("BPS actions are two bits"),
+        }
+    }
+
+    if out.len() != target_size {
+        return Err(format!("BPS patch produced {} bytes, expected {}", out.len(), target_size));
+    }
+    if crc32fast::hash(&out) != crc(4) {
+        return Err("BPS target checksum mismatch".to_string());
+    }
+    Ok(out)
+}
+
+// ---------------------------------------------------------------------------
+// bsdiff: BSDIFF40 control, diff and extra blocks, each bzip2 compressed
+// ---------------------------------------------------------------------------
+
+fn write_offset(out: &mut Vec<u8>, value: i64) {
+    let mut bytes = value.unsigned_abs().to_le_bytes();
+    if value < 0 {
+        bytes[7] |= 0x80;
+    }
+    out.extend_from_slice(&bytes);
+}
+
+fn read_offset(bytes: &[u8]) -> i64 {
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&bytes[..8]);
+    let negative = raw[7] & 0x80 != 0;
+    raw[7] &= 0x7F;
+    let magnitude = i64::from_le_bytes(raw);
+    if negative { -magnitude } else { magnitude }
+}
+
+fn bzip2(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::best());
+    encoder.write_all(data)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| format!("bzip2 compression failed: {}", e))
+}
+
+fn bunzip2(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    bzip2::read::BzDecoder::new(data)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| format!("Corrupt bsdiff block: {}", e))?;
+    if out.len() > limit {
+        return Err("bsdiff block is larger than the patched buffer".to_string());
+    }
+    Ok(out)
+}
+
+fn create_bsdiff(old: &[u8], new: &[u8]) -> Result<Vec<u8>, String> {
+    // Each control triple adds diff bytes onto the old data, copies extra
+    // bytes, then seeks in the old data. Equal runs become zero diff bytes.
+    let (mut control, mut diff_block, mut extra) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut add, mut copy, mut seek) = (0usize, 0usize, 0i64);
+    let mut target_at = 0;
+    for edit in diff::diff(old, new) {
+        match edit {
+            Edit::Equal(n) => {
+                if copy > 0 || seek != 0 {
+                    write_offset(&mut control, add as i64);
+                    write_offset(&mut control, copy as i64);
+                    write_offset(&mut control, seek);
+                    (add, copy, seek) = (0, 0, 0);
+                }
+                add += n;
+                diff_block.resize(diff_block.len() + n, 0);
+                target_at += n;
+            }
+            Edit::Insert(n) => {
+                extra.extend_from_slice(&new[target_at..target_at + n]);
+                copy += n;
+                target_at += n;
+            }
+            Edit::Delete(n) => seek += n as i64,
+        }
+    }
+    if add > 0 || copy > 0 || seek != 0 {
+        write_offset(&mut control, add as i64);
+        write_offset(&mut control, copy as i64);
+        write_offset(&mut control, seek);
+    }
+
+    let (control, diff_block, extra) = (bzip2(&control)?, bzip2(&diff_block)?, bzip2(&extra)?);
+    let mut patch = b"BSDIFF40".to_vec();
+    write_offset(&mut patch, control.len() as i64);
+    write_offset(&mut patch, diff_block.len() as i64);
+    write_offset(&mut patch, new.len() as i64);
+    patch.extend_from_slice(&control);
+    patch.extend_from_slice(&diff_block);
+    patch.extend_from_slice(&extra);
+    Ok(patch)
+}
+
+fn apply_bsdiff(old: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.len() < 32 || !patch.starts_with(b"BSDIFF40") {
+        return Err("Not a bsdiff patch".to_string());
+    }
+    let size = |at: usize| usize::try_from(read_offset(&patch[at..]))
+        .map_err(|_| "Corrupt bsdiff header".to_string());
+    let (control_len, diff_len, new_size) = (size(8)?, size(16)?, check_size(size(24)?)?);
+    let control_end = 32usize.checked_add(control_len).filter(|&end| end <= patch.len());
+    let diff_end = control_end.and_then(|end| end.checked_add(diff_len)).filter(|&end| end <= patch.len());
+    let (Some(control_end), Some(diff_end)) = (control_end, diff_end) else {
+        return Err("bsdiff patch is truncated".to_string());
+    };
+    let control = bunzip2(&patch[32..control_end], new_size.saturating_mul(24).max(24))?;
+    let diff_block = bunzip2(&patch[control_end..diff_end], new_size)?;
+    let extra = bunzip2(&patch[diff_end..], new_size)?;
+
+    let corrupt = || "Corrupt bsdiff patch: control data points outside its blocks".to_string();
+    let mut out = vec![0u8; new_size];
+    let (mut old_at, mut new_at, mut diff_at, mut extra_at) = (0i64, 0usize, 0usize, 0usize);
+    for triple in control.chunks_exact(24) {
+        if new_at >= new_size {
+            break;
+        }
+        let add = usize::try_from(read_offset(triple)).map_err(|_| corrupt())?;
+        let copy = usize::try_from(read_offset(&triple[8..])).map_err(|_| corrupt())?;
+        let seek = read_offset(&triple[16..]);
+
+        if new_at + add > new_size || diff_at + add > diff_block.len() {
+            return Err(corrupt());
+        }
+        for i in 0..add {
+            let old_byte = old_at.checked_add(i as i64)
+                .and_then(|at| usize::try_from(at).ok())
+                .and_then(|at| old.get(at))
+                .copied()
+                .unwrap_or(0);
+            out[new_at + i] = diff_block[diff_at + i].wrapping_add(old_byte);
+        }
+        new_at += add;
+        diff_at += add;
+        old_at = old_at.checked_add(add as i64).ok_or_else(corrupt)?;
+
+        if new_at + copy > new_size || extra_at + copy > extra.len() {
+            return Err(corrupt());
+        }
+        out[new_at..new_at + copy].copy_from_slice(&extra[extra_at..extra_at + copy]);
+        new_at += copy;
+        extra_at += copy;
+        old_at = old_at.checked_add(seek).ok_or_else(corrupt)?;
+    }
+    if new_at != new_size {
+        return Err(format!("bsdiff patch produced {} bytes, expected {}", new_at, new_size));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [PatchFormat; 3] = [PatchFormat::Ips, PatchFormat::Bps, PatchFormat::Bsdiff];
+
+    /// Deterministic bytes that look random (a 64-bit LCG's high byte).
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    fn round_trip(format: PatchFormat, old: &[u8], new: &[u8]) -> Vec<u8> {
+        let patch = create(format, old, new).unwrap();
+        assert_eq!(PatchFormat::detect(&patch), Some(format));
+        assert_eq!(apply(format, old, &patch).unwrap(), new, "{} round trip", format.name());
+        patch
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let old = noise(10_000, 1);
+        let mut edited = old.clone();
+        edited[10] ^= 0xFF;
+        edited[5000..5100].fill(0xCC);
+        let mut shifted = old.clone();
+        shifted.splice(3000..3000, noise(500, 2));
+        shifted.drain(8000..8200);
+        let mut grown = old.clone();
+        grown.extend(noise(300, 3));
+        let cases: [(&[u8], &[u8]); 8] = [
+            (b"", b""),
+            (b"", b"new data"),
+            (b"old data", b""),
+            (&old, &old),
+            (&old, &edited),
+            (&old, &shifted),
+            (&old, &grown),
+            (&old, &old[..4321]),
+        ];
+        for format in FORMATS {
+            for (old, new) in cases {
+                round_trip(format, old, new);
+            }
+        }
+    }
+
+    #[test]
+    fn ips_truncates_after_eof() {
+        let old = noise(1000, 1);
+        let patch = round_trip(PatchFormat::Ips, &old, &old[..700]);
+        assert_eq!(patch, b"PATCHEOF\x00\x02\xBC");
+        // Without the size, IPS keeps the original length
+        assert_eq!(apply_ips(&old, b"PATCHEOF").unwrap(), old);
+    }
+
+    #[test]
+    fn ips_records_avoid_the_eof_offset() {
+        let old = vec![0u8; IPS_EOF_OFFSET + 16];
+        let mut new = old.clone();
+        new[IPS_EOF_OFFSET] = 1;
+        let patch = round_trip(PatchFormat::Ips, &old, &new);
+        // The record starts a byte early, so its offset doesn't spell "EOF"
+        assert_eq!(&patch[5..10], &[0x45, 0x4F, 0x45, 0x00, 0x02]);
+        assert_eq!(&patch[10..12], &[0x00, 0x01]);
+        assert!(patch.ends_with(b"EOF"));
+    }
+
+    #[test]
+    fn ips_rle_records() {
+        let old = vec![0u8; 100];
+        let mut new = old.clone();
+        new[10..60].fill(0xAB);
+        let patch = round_trip(PatchFormat::Ips, &old, &new);
+        assert_eq!(patch, b"PATCH\x00\x00\x0A\x00\x00\x00\x32\xABEOF");
+    }
+
+    #[test]
+    fn checksums_and_truncation_are_detected() {
+        let old = noise(2000, 1);
+        let mut new = old.clone();
+        new[100] ^= 1;
+
+        let bps = create(PatchFormat::Bps, &old, &new).unwrap();
+        let mut other = old.clone();
+        other[1999] ^= 1;
+        assert!(apply(PatchFormat::Bps, &other, &bps).unwrap_err().contains("source checksum"));
+        assert!(apply(PatchFormat::Bps, &old[..1000], &bps).is_err());
+        let mut corrupt = bps.clone();
+        corrupt[8] ^= 1;
+        assert!(apply(PatchFormat::Bps, &old, &corrupt).unwrap_err().contains("corrupt"));
+
+        for format in FORMATS {
+            let patch = create(format, &old, &new).unwrap();
+            assert!(apply(format, &old, &patch[..patch.len() / 2]).is_err(), "{} truncated", format.name());
+        }
+    }
+
+    /// A bsdiff patch from raw control triples and blocks.
+    fn bsdiff_patch(triples: &[(i64, i64, i64)], diff_block: &[u8], extra: &[u8], new_size: i64) -> Vec<u8> {
+        let mut control = Vec::new();
+        for &(add, copy, seek) in triples {
+            write_offset(&mut control, add);
+            write_offset(&mut control, copy);
+            write_offset(&mut control, seek);
+        }
+        let (control, diff_block, extra) = (bzip2(&control).unwrap(), bzip2(diff_block).unwrap(), bzip2(extra).unwrap());
+        let mut patch = b"BSDIFF40".to_vec();
+        write_offset(&mut patch, control.len() as i64);
+        write_offset(&mut patch, diff_block.len() as i64);
+        write_offset(&mut patch, new_size);
+        patch.extend_from_slice(&control);
+        patch.extend_from_slice(&diff_block);
+        patch.extend_from_slice(&extra);
+        patch
+    }
+
+    #[test]
+    fn malformed_bsdiff_is_rejected() {
+        let old = b"old data";
+        let valid = bsdiff_patch(&[(2, 1, -1), (1, 0, 0)], &[0, 0, 0], b"X", 4);
+        assert_eq!(apply_bsdiff(old, &valid).unwrap(), b"olXl");
+
+        // Seeks that overflow the old position
+        let seek = 1i64 << 62;
+        let patch = bsdiff_patch(&[(0, 1, seek), (0, 1, seek), (1, 0, 0)], &[0], b"ab", 3);
+        assert!(apply_bsdiff(old, &patch).unwrap_err().contains("Corrupt"));
+        // Reads past the diff and extra blocks
+        assert!(apply_bsdiff(old, &bsdiff_patch(&[(4, 0, 0)], &[0; 2], b"", 4)).is_err());
+        assert!(apply_bsdiff(old, &bsdiff_patch(&[(0, 4, 0)], b"", b"ab", 4)).is_err());
+        // Negative lengths, and control data ending early
+        assert!(apply_bsdiff(old, &bsdiff_patch(&[(-1, 0, 0)], b"", b"", 1)).is_err());
+        assert!(apply_bsdiff(old, &bsdiff_patch(&[(1, 0, 0)], &[0], b"", 2)).is_err());
+        assert!(apply_bsdiff(old, &valid[..31]).is_err());
+    }
+
+    #[test]
+    fn bps_numbers_round_trip() {
+        for value in [0, 1, 127, 128, 16511, 16512, u32::MAX as u64, 1 << 40] {
+            let mut out = Vec::new();
+            write_bps_number(&mut out, value);
+            let mut cursor = Cursor { data: &out, pos: 0 };
+            assert_eq!(read_bps_number(&mut cursor), Ok(value));
+            assert_eq!(cursor.pos, out.len());
+        }
+        for delta in [0, 5, -5, i32::MIN as i64] {
+            let mut out = Vec::new();
+            write_bps_offset(&mut out, delta);
+            assert_eq!(read_bps_offset(&mut Cursor { data: &out, pos: 0 }), Ok(delta));
+        }
+    }
+}
@@ -0,0 +1,883 @@
+// ============================================================================
+// src/pe.rs
+// ============================================================================
+use std::ops::Range;
+
+/// Import descriptors read before giving up on a malformed table.
+const MAX_IMPORT_DLLS: usize = 4096;
+/// Thunks read per descriptor before giving up on a malformed table.
+const MAX_IMPORT_THUNKS: usize = 65536;
+/// Longest DLL or function name read.
+const MAX_NAME: usize = 512;
+
+const IMPORT_DIRECTORY: usize = 1;
+const SECURITY_DIRECTORY: usize = 4;
+
+struct Section {
+    virtual_address: usize,
+    virtual_size: usize,
+    raw_offset: usize,
+    raw_size: usize,
+}
+
+/// The parts of a PE image needed for imphash and authentihash.
+pub struct Pe<'a> {
+    data: &'a [u8],
+    is64: bool,
+    /// File offset of the optional header
+    optional_header: usize,
+    /// File offset of the data directories
+    directories: usize,
+    directory_count: usize,
+    size_of_headers: usize,
+    sections: Vec<Section>,
+}
+
+fn u16_at(data: &[u8], at: usize) -> Result<u16, String> {
+    data.get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| format!("PE is truncated at byte {}", at))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Result<u32, String> {
+    data.get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| format!("PE is truncated at byte {}", at))
+}
+
+fn u64_at(data: &[u8], at: usize) -> Result<u64, String> {
+    Ok(u32_at(data, at)? as u64 | (u32_at(data, at + 4)? as u64) << 32)
+}
+
+impl<'a> Pe<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, String> {
+        if !data.starts_with(b"MZ") {
+            return Err("Not a PE file: no MZ header".to_string());
+        }
+        let pe_offset = u32_at(data, 0x3C)? as usize;
+        if data.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0".as_slice()) {
+            return Err(format!("Not a PE file: no PE signature at 0x{:X}", pe_offset));
+        }
+        let coff = pe_offset + 4;
+        let section_count = u16_at(data, coff + 2)? as usize;
+        let optional_size = u16_at(data, coff + 16)? as usize;
+        let optional_header = coff + 20;
+        let is64 = match u16_at(data, optional_header)? {
+            0x10B => false,
+            0x20B => true,
+            magic => return Err(format!("Unknown optional header magic 0x{:04X}", magic)),
+        };
+        let size_of_headers = u32_at(data, optional_header + 60)? as usize;
+        let (count_at, directories) = if is64 {
+            (optional_header + 108, optional_header + 112)
+        } else {
+            (optional_header + 92, optional_header + 96)
+        };
+        let directory_count = (u32_at(data, count_at)? as usize).min(16);
+
+        let table = optional_header + optional_size;
+        let sections = (0..section_count)
+            .map(|i| {
+                let at = table + i * 40;
+                Ok(Section {
+                    virtual_size: u32_at(data, at + 8)? as usize,
+                    virtual_address: u32_at(data, at + 12)? as usize,
+                    raw_size: u32_at(data, at + 16)? as usize,
+                    raw_offset: u32_at(data, at + 20)? as usize,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Pe { data, is64, optional_header, directories, directory_count, size_of_headers, sections })
+    }
+
+    /// Address and size of a data directory, if present and non-empty.
+    fn directory(&self, index: usize) -> Result<Option<(usize, usize)>, String> {
+        if index >= self.directory_count {
+            return Ok(None);
+        }
+        let at = self.directories + index * 8;
+        let address = u32_at(self.data, at)? as usize;
+        let size = u32_at(self.data, at + 4)? as usize;
+        Ok((address != 0 && size != 0).then_some((address, size)))
+    }
+
+    fn rva_to_offset(&self, rva: usize) -> Result<usize, String> {
+        // SizeOfHeaders comes from the file and may reach past its end
+        if rva < self.size_of_headers && rva < self.data.len() {
+            return Ok(rva);
+        }
+        self.sections.iter()
+            .find(|s| rva >= s.virtual_address && rva < s.virtual_address + s.virtual_size.max(s.raw_size))
+            .map(|s| rva - s.virtual_address + s.raw_offset)
+            .filter(|&offset| offset < self.data.len())
+            .ok_or_else(|| format!("RVA 0x{:X} is outside the file", rva))
+    }
+
+    fn name_at(&self, rva: usize) -> Result<String, String> {
+        let start = self.rva_to_offset(rva)?;
+        let bytes = &self.data[start..self.data.len().min(start + MAX_NAME)];
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+
+    /// Imports as (dll, function) pairs in table order; functions imported by
+    /// ordinal are named as pefile names them, see `ordinal_name`.
+    pub fn imports(&self) -> Result<Vec<(String, String)>, String> {
+        let Some((table, _)) = self.directory(IMPORT_DIRECTORY)? else {
+            return Ok(Vec::new());
+        };
+        let table = self.rva_to_offset(table)?;
+        let (thunk_size, ordinal_flag) = if self.is64 { (8, 1u64 << 63) } else { (4, 1u64 << 31) };
+        let mut imports = Vec::new();
+
+        for i in 0..MAX_IMPORT_DLLS {
+            let at = table + i * 20;
+            let lookup = u32_at(self.data, at)? as usize;
+            let name = u32_at(self.data, at + 12)? as usize;
+            let address = u32_at(self.data, at + 16)? as usize;
+            if name == 0 && lookup == 0 && address == 0 {
+                break;
+            }
+            let dll = self.name_at(name)?;
+            let thunks = self.rva_to_offset(if lookup != 0 { lookup } else { address })?;
+            for j in 0..MAX_IMPORT_THUNKS {
+                let at = thunks + j * thunk_size;
+                let thunk = if self.is64 { u64_at(self.data, at)? } else { u32_at(self.data, at)? as u64 };
+                if thunk == 0 {
+                    break;
+                }
+                let function = if thunk & ordinal_flag != 0 {
+                    ordinal_name(&dll, (thunk & 0xFFFF) as u16)
+                } else {
+                    self.name_at((thunk & 0x7FFF_FFFF) as usize + 2)?
+                };
+                imports.push((dll.clone(), function));
+            }
+        }
+        Ok(imports)
+    }
+
+    /// Byte ranges covered by the Authenticode hash: the headers without the
+    /// checksum and the certificate table entry, the sections in file order,
+    /// then any trailing data up to the certificate table.
+    pub fn authenticode_ranges(&self) -> Result<Vec<Range<usize>>, String> {
+        let len = self.data.len();
+        let checksum = self.optional_header + 64;
+        let security_entry = self.directories + SECURITY_DIRECTORY * 8;
+        let headers_end = self.size_of_headers.min(len);
+        if security_entry + 8 > headers_end {
+            return Err("PE headers are too short for an Authenticode hash".to_string());
+        }
+        let mut ranges = vec![0..checksum, checksum + 4..security_entry, security_entry + 8..headers_end];
+
+        let mut sections: Vec<&Section> = self.sections.iter().filter(|s| s.raw_size > 0).collect();
+        sections.sort_by_key(|s| s.raw_offset);
+        let mut hashed_end = headers_end;
+        for section in sections {
+            let start = section.raw_offset.min(len);
+            let end = (section.raw_offset + section.raw_size).min(len);
+            ranges.push(start..end);
+            hashed_end = hashed_end.max(end);
+        }
+
+        let certificates = match self.directory(SECURITY_DIRECTORY)? {
+            Some((offset, _)) if offset >= hashed_end => offset.min(len),
+            _ => len,
+        };
+        if certificates > hashed_end {
+            ranges.push(hashed_end..certificates);
+        }
+        Ok(ranges)
+    }
+}
+
+/// Name of a function imported by ordinal: looked up for the few DLLs pefile
+/// knows the exports of (ws2_32, wsock32, oleaut32), `ord<N>` otherwise.
+fn ordinal_name(dll: &str, ordinal: u16) -> String {
+    let table = match dll.to_ascii_lowercase().as_str() {
+        "ws2_32.dll" | "wsock32.dll" => WS2_32_ORDINALS,
+        "oleaut32.dll" => OLEAUT32_ORDINALS,
+        _ => &[],
+    };
+    table.binary_search_by_key(&ordinal, |&(n, _)| n)
+        .map(|i| table[i].1.to_string())
+        .unwrap_or_else(|_| format!("ord{}", ordinal))
+}
+
+/// Imphash: MD5 of the comma-separated, lowercased `dll.function` imports,
+/// with '.dll', '.ocx' and '.sys' dropped from DLL names, matching pefile.
+pub fn imphash(data: &[u8]) -> Result<String, String> {
+    use md5::{Digest, Md5};
+    let imports = Pe::parse(data)?.imports()?;
+    if imports.is_empty() {
+        return Err("PE has no imports".to_string());
+    }
+    let list = imports.iter()
+        .map(|(dll, function)| {
+            let dll = dll.to_lowercase();
+            let stem = ["dll", "ocx", "sys"].iter()
+                .find_map(|ext| dll.strip_suffix(&format!(".{}", ext)))
+                .unwrap_or(&dll);
+            format!("{}.{}", stem, function.to_lowercase())
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    Ok(hex::encode(Md5::digest(list.as_bytes())))
+}
+
+/// SHA-256 Authenticode hash of a PE image, as signed in its certificate.
+pub fn authentihash(data: &[u8]) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    for range in Pe::parse(data)?.authenticode_ranges()? {
+        hasher.update(&data[range]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Names of the ws2_32.dll and wsock32.dll exports, by ordinal, as pefile
+/// resolves them.
+const WS2_32_ORDINALS: &[(u16, &str)] = &[
+    (1, "accept"),
+    (2, "bind"),
+    (3, "closesocket"),
+    (4, "connect"),
+    (5, "getpeername"),
+    (6, "getsockname"),
+    (7, "getsockopt"),
+    (8, "htonl"),
+    (9, "htons"),
+    (10, "ioctlsocket"),
+    (11, "inet_addr"),
+    (12, "inet_ntoa"),
+    (13, "listen"),
+    (14, "ntohl"),
+    (15, "ntohs"),
+    (16, "recv"),
+    (17, "recvfrom"),
+    (18, "select"),
+    (19, "send"),
+    (20, "sendto"),
+    (21, "setsockopt"),
+    (22, "shutdown"),
+    (23, "socket"),
+    (24, "GetAddrInfoW"),
+    (25, "GetNameInfoW"),
+    (26, "WSApSetPostRoutine"),
+    (27, "FreeAddrInfoW"),
+    (28, "WPUCompleteOverlappedRequest"),
+    (29, "WSAAccept"),
+    (30, "WSAAddressToStringA"),
+    (31, "WSAAddressToStringW"),
+    (32, "WSACloseEvent"),
+    (33, "WSAConnect"),
+    (34, "WSACreateEvent"),
+    (35, "WSADuplicateSocketA"),
+    (36, "WSADuplicateSocketW"),
+    (37, "WSAEnumNameSpaceProvidersA"),
+    (38, "WSAEnumNameSpaceProvidersW"),
+    (39, "WSAEnumNetworkEvents"),
+    (40, "WSAEnumProtocolsA"),
+    (41, "WSAEnumProtocolsW"),
+    (42, "WSAEventSelect"),
+    (43, "WSAGetOverlappedResult"),
+    (44, "WSAGetQOSByName"),
+    (45, "WSAGetServiceClassInfoA"),
+    (46, "WSAGetServiceClassInfoW"),
+    (47, "WSAGetServiceClassNameByClassIdA"),
+    (48, "WSAGetServiceClassNameByClassIdW"),
+    (49, "WSAHtonl"),
+    (50, "WSAHtons"),
+    (51, "gethostbyaddr"),
+    (52, "gethostbyname"),
+    (53, "getprotobyname"),
+    (54, "getprotobynumber"),
+    (55, "getservbyname"),
+    (56, "getservbyport"),
+    (57, "gethostname"),
+    (58, "WSAInstallServiceClassA"),
+    (59, "WSAInstallServiceClassW"),
+    (60, "WSAIoctl"),
+    (61, "WSAJoinLeaf"),
+    (62, "WSALookupServiceBeginA"),
+    (63, "WSALookupServiceBeginW"),
+    (64, "WSALookupServiceEnd"),
+    (65, "WSALookupServiceNextA"),
+    (66, "WSALookupServiceNextW"),
+    (67, "WSANSPIoctl"),
+    (68, "WSANtohl"),
+    (69, "WSANtohs"),
+    (70, "WSAProviderConfigChange"),
+    (71, "WSARecv"),
+    (72, "WSARecvDisconnect"),
+    (73, "WSARecvFrom"),
+    (74, "WSARemoveServiceClass"),
+    (75, "WSAResetEvent"),
+    (76, "WSASend"),
+    (77, "WSASendDisconnect"),
+    (78, "WSASendTo"),
+    (79, "WSASetEvent"),
+    (80, "WSASetServiceA"),
+    (81, "WSASetServiceW"),
+    (82, "WSASocketA"),
+    (83, "WSASocketW"),
+    (84, "WSAStringToAddressA"),
+    (85, "WSAStringToAddressW"),
+    (86, "WSAWaitForMultipleEvents"),
+    (87, "WSCDeinstallProvider"),
+    (88, "WSCEnableNSProvider"),
+    (89, "WSCEnumProtocols"),
+    (90, "WSCGetProviderPath"),
+    (91, "WSCInstallNameSpace"),
+    (92, "WSCInstallProvider"),
+    (93, "WSCUnInstallNameSpace"),
+    (94, "WSCUpdateProvider"),
+    (95, "WSCWriteNameSpaceOrder"),
+    (96, "WSCWriteProviderOrder"),
+    (97, "freeaddrinfo"),
+    (98, "getaddrinfo"),
+    (99, "getnameinfo"),
+    (101, "WSAAsyncSelect"),
+    (102, "WSAAsyncGetHostByAddr"),
+    (103, "WSAAsyncGetHostByName"),
+    (104, "WSAAsyncGetProtoByNumber"),
+    (105, "WSAAsyncGetProtoByName"),
+    (106, "WSAAsyncGetServByPort"),
+    (107, "WSAAsyncGetServByName"),
+    (108, "WSACancelAsyncRequest"),
+    (109, "WSASetBlockingHook"),
+    (110, "WSAUnhookBlockingHook"),
+    (111, "WSAGetLastError"),
+    (112, "WSASetLastError"),
+    (113, "WSACancelBlockingCall"),
+    (114, "WSAIsBlocking"),
+    (115, "WSAStartup"),
+    (116, "WSACleanup"),
+    (151, "__WSAFDIsSet"),
+    (500, "WEP"),
+];
+
+/// Names of the oleaut32.dll exports, by ordinal, as pefile resolves them.
+const OLEAUT32_ORDINALS: &[(u16, &str)] = &[
+    (2, "SysAllocString"),
+    (3, "SysReAllocString"),
+    (4, "SysAllocStringLen"),
+    (5, "SysReAllocStringLen"),
+    (6, "SysFreeString"),
+    (7, "SysStringLen"),
+    (8, "VariantInit"),
+    (9, "VariantClear"),
+    (10, "VariantCopy"),
+    (11, "VariantCopyInd"),
+    (12, "VariantChangeType"),
+    (13, "VariantTimeToDosDateTime"),
+    (14, "DosDateTimeToVariantTime"),
+    (15, "SafeArrayCreate"),
+    (16, "SafeArrayDestroy"),
+    (17, "SafeArrayGetDim"),
+    (18, "SafeArrayGetElemsize"),
+    (19, "SafeArrayGetUBound"),
+    (20, "SafeArrayGetLBound"),
+    (21, "SafeArrayLock"),
+    (22, "SafeArrayUnlock"),
+    (23, "SafeArrayAccessData"),
+    (24, "SafeArrayUnaccessData"),
+    (25, "SafeArrayGetElement"),
+    (26, "SafeArrayPutElement"),
+    (27, "SafeArrayCopy"),
+    (28, "DispGetParam"),
+    (29, "DispGetIDsOfNames"),
+    (30, "DispInvoke"),
+    (31, "CreateDispTypeInfo"),
+    (32, "CreateStdDispatch"),
+    (33, "RegisterActiveObject"),
+    (34, "RevokeActiveObject"),
+    (35, "GetActiveObject"),
+    (36, "SafeArrayAllocDescriptor"),
+    (37, "SafeArrayAllocData"),
+    (38, "SafeArrayDestroyDescriptor"),
+    (39, "SafeArrayDestroyData"),
+    (40, "SafeArrayRedim"),
+    (41, "SafeArrayAllocDescriptorEx"),
+    (42, "SafeArrayCreateEx"),
+    (43, "SafeArrayCreateVectorEx"),
+    (44, "SafeArraySetRecordInfo"),
+    (45, "SafeArrayGetRecordInfo"),
+    (46, "VarParseNumFromStr"),
+    (47, "VarNumFromParseNum"),
+    (48, "VarI2FromUI1"),
+    (49, "VarI2FromI4"),
+    (50, "VarI2FromR4"),
+    (51, "VarI2FromR8"),
+    (52, "VarI2FromCy"),
+    (53, "VarI2FromDate"),
+    (54, "VarI2FromStr"),
+    (55, "VarI2FromDisp"),
+    (56, "VarI2FromBool"),
+    (57, "SafeArraySetIID"),
+    (58, "VarI4FromUI1"),
+    (59, "VarI4FromI2"),
+    (60, "VarI4FromR4"),
+    (61, "VarI4FromR8"),
+    (62, "VarI4FromCy"),
+    (63, "VarI4FromDate"),
+    (64, "VarI4FromStr"),
+    (65, "VarI4FromDisp"),
+    (66, "VarI4FromBool"),
+    (67, "SafeArrayGetIID"),
+    (68, "VarR4FromUI1"),
+    (69, "VarR4FromI2"),
+    (70, "VarR4FromI4"),
+    (71, "VarR4FromR8"),
+    (72, "VarR4FromCy"),
+    (73, "VarR4FromDate"),
+    (74, "VarR4FromStr"),
+    (75, "VarR4FromDisp"),
+    (76, "VarR4FromBool"),
+    (77, "SafeArrayGetVartype"),
+    (78, "VarR8FromUI1"),
+    (79, "VarR8FromI2"),
+    (80, "VarR8FromI4"),
+    (81, "VarR8FromR4"),
+    (82, "VarR8FromCy"),
+    (83, "VarR8FromDate"),
+    (84, "VarR8FromStr"),
+    (85, "VarR8FromDisp"),
+    (86, "VarR8FromBool"),
+    (87, "VarFormat"),
+    (88, "VarDateFromUI1"),
+    (89, "VarDateFromI2"),
+    (90, "VarDateFromI4"),
+    (91, "VarDateFromR4"),
+    (92, "VarDateFromR8"),
+    (93, "VarDateFromCy"),
+    (94, "VarDateFromStr"),
+    (95, "VarDateFromDisp"),
+    (96, "VarDateFromBool"),
+    (97, "VarFormatDateTime"),
+    (98, "VarCyFromUI1"),
+    (99, "VarCyFromI2"),
+    (100, "VarCyFromI4"),
+    (101, "VarCyFromR4"),
+    (102, "VarCyFromR8"),
+    (103, "VarCyFromDate"),
+    (104, "VarCyFromStr"),
+    (105, "VarCyFromDisp"),
+    (106, "VarCyFromBool"),
+    (107, "VarFormatNumber"),
+    (108, "VarBstrFromUI1"),
+    (109, "VarBstrFromI2"),
+    (110, "VarBstrFromI4"),
+    (111, "VarBstrFromR4"),
+    (112, "VarBstrFromR8"),
+    (113, "VarBstrFromCy"),
+    (114, "VarBstrFromDate"),
+    (115, "VarBstrFromDisp"),
+    (116, "VarBstrFromBool"),
+    (117, "VarFormatPercent"),
+    (118, "VarBoolFromUI1"),
+    (119, "VarBoolFromI2"),
+    (120, "VarBoolFromI4"),
+    (121, "VarBoolFromR4"),
+    (122, "VarBoolFromR8"),
+    (123, "VarBoolFromDate"),
+    (124, "VarBoolFromCy"),
+    (125, "VarBoolFromStr"),
+    (126, "VarBoolFromDisp"),
+    (127, "VarFormatCurrency"),
+    (128, "VarWeekdayName"),
+    (129, "VarMonthName"),
+    (130, "VarUI1FromI2"),
+    (131, "VarUI1FromI4"),
+    (132, "VarUI1FromR4"),
+    (133, "VarUI1FromR8"),
+    (134, "VarUI1FromCy"),
+    (135, "VarUI1FromDate"),
+    (136, "VarUI1FromStr"),
+    (137, "VarUI1FromDisp"),
+    (138, "VarUI1FromBool"),
+    (139, "VarFormatFromTokens"),
+    (140, "VarTokenizeFormatString"),
+    (141, "VarAdd"),
+    (142, "VarAnd"),
+    (143, "VarDiv"),
+    (144, "DllCanUnloadNow"),
+    (145, "DllGetClassObject"),
+    (146, "DispCallFunc"),
+    (147, "VariantChangeTypeEx"),
+    (148, "SafeArrayPtrOfIndex"),
+    (149, "SysStringByteLen"),
+    (150, "SysAllocStringByteLen"),
+    (151, "DllRegisterServer"),
+    (152, "VarEqv"),
+    (153, "VarIdiv"),
+    (154, "VarImp"),
+    (155, "VarMod"),
+    (156, "VarMul"),
+    (157, "VarOr"),
+    (158, "VarPow"),
+    (159, "VarSub"),
+    (160, "CreateTypeLib"),
+    (161, "LoadTypeLib"),
+    (162, "LoadRegTypeLib"),
+    (163, "RegisterTypeLib"),
+    (164, "QueryPathOfRegTypeLib"),
+    (165, "LHashValOfNameSys"),
+    (166, "LHashValOfNameSysA"),
+    (167, "VarXor"),
+    (168, "VarAbs"),
+    (169, "VarFix"),
+    (170, "OaBuildVersion"),
+    (171, "ClearCustData"),
+    (172, "VarInt"),
+    (173, "VarNeg"),
+    (174, "VarNot"),
+    (175, "VarRound"),
+    (176, "VarCmp"),
+    (177, "VarDecAdd"),
+    (178, "VarDecDiv"),
+    (179, "VarDecMul"),
+    (180, "CreateTypeLib2"),
+    (181, "VarDecSub"),
+    (182, "VarDecAbs"),
+    (183, "LoadTypeLibEx"),
+    (184, "SystemTimeToVariantTime"),
+    (185, "VariantTimeToSystemTime"),
+    (186, "UnRegisterTypeLib"),
+    (187, "VarDecFix"),
+    (188, "VarDecInt"),
+    (189, "VarDecNeg"),
+    (190, "VarDecFromUI1"),
+    (191, "VarDecFromI2"),
+    (192, "VarDecFromI4"),
+    (193, "VarDecFromR4"),
+    (194, "VarDecFromR8"),
+    (195, "VarDecFromDate"),
+    (196, "VarDecFromCy"),
+    (197, "VarDecFromStr"),
+    (198, "VarDecFromDisp"),
+    (199, "VarDecFromBool"),
+    (200, "GetErrorInfo"),
+    (201, "SetErrorInfo"),
+    (202, "CreateErrorInfo"),
+    (203, "VarDecRound"),
+    (204, "VarDecCmp"),
+    (205, "VarI2FromI1"),
+    (206, "VarI2FromUI2"),
+    (207, "VarI2FromUI4"),
+    (208, "VarI2FromDec"),
+    (209, "VarI4FromI1"),
+    (210, "VarI4FromUI2"),
+    (211, "VarI4FromUI4"),
+    (212, "VarI4FromDec"),
+    (213, "VarR4FromI1"),
+    (214, "VarR4FromUI2"),
+    (215, "VarR4FromUI4"),
+    (216, "VarR4FromDec"),
+    (217, "VarR8FromI1"),
+    (218, "VarR8FromUI2"),
+    (219, "VarR8FromUI4"),
+    (220, "VarR8FromDec"),
+    (221, "VarDateFromI1"),
+    (222, "VarDateFromUI2"),
+    (223, "VarDateFromUI4"),
+    (224, "VarDateFromDec"),
+    (225, "VarCyFromI1"),
+    (226, "VarCyFromUI2"),
+    (227, "VarCyFromUI4"),
+    (228, "VarCyFromDec"),
+    (229, "VarBstrFromI1"),
+    (230, "VarBstrFromUI2"),
+    (231, "VarBstrFromUI4"),
+    (232, "VarBstrFromDec"),
+    (233, "VarBoolFromI1"),
+    (234, "VarBoolFromUI2"),
+    (235, "VarBoolFromUI4"),
+    (236, "VarBoolFromDec"),
+    (237, "VarUI1FromI1"),
+    (238, "VarUI1FromUI2"),
+    (239, "VarUI1FromUI4"),
+    (240, "VarUI1FromDec"),
+    (241, "VarDecFromI1"),
+    (242, "VarDecFromUI2"),
+    (243, "VarDecFromUI4"),
+    (244, "VarI1FromUI1"),
+    (245, "VarI1FromI2"),
+    (246, "VarI1FromI4"),
+    (247, "VarI1FromR4"),
+    (248, "VarI1FromR8"),
+    (249, "VarI1FromDate"),
+    (250, "VarI1FromCy"),
+    (251, "VarI1FromStr"),
+    (252, "VarI1FromDisp"),
+    (253, "VarI1FromBool"),
+    (254, "VarI1FromUI2"),
+    (255, "VarI1FromUI4"),
+    (256, "VarI1FromDec"),
+    (257, "VarUI2FromUI1"),
+    (258, "VarUI2FromI2"),
+    (259, "VarUI2FromI4"),
+    (260, "VarUI2FromR4"),
+    (261, "VarUI2FromR8"),
+    (262, "VarUI2FromDate"),
+    (263, "VarUI2FromCy"),
+    (264, "VarUI2FromStr"),
+    (265, "VarUI2FromDisp"),
+    (266, "VarUI2FromBool"),
+    (267, "VarUI2FromI1"),
+    (268, "VarUI2FromUI4"),
+    (269, "VarUI2FromDec"),
+    (270, "VarUI4FromUI1"),
+    (271, "VarUI4FromI2"),
+    (272, "VarUI4FromI4"),
+    (273, "VarUI4FromR4"),
+    (274, "VarUI4FromR8"),
+    (275, "VarUI4FromDate"),
+    (276, "VarUI4FromCy"),
+    (277, "VarUI4FromStr"),
+    (278, "VarUI4FromDisp"),
+    (279, "VarUI4FromBool"),
+    (280, "VarUI4FromI1"),
+    (281, "VarUI4FromUI2"),
+    (282, "VarUI4FromDec"),
+    (283, "BSTR_UserSize"),
+    (284, "BSTR_UserMarshal"),
+    (285, "BSTR_UserUnmarshal"),
+    (286, "BSTR_UserFree"),
+    (287, "VARIANT_UserSize"),
+    (288, "VARIANT_UserMarshal"),
+    (289, "VARIANT_UserUnmarshal"),
+    (290, "VARIANT_UserFree"),
+    (291, "LPSAFEARRAY_UserSize"),
+    (292, "LPSAFEARRAY_UserMarshal"),
+    (293, "LPSAFEARRAY_UserUnmarshal"),
+    (294, "LPSAFEARRAY_UserFree"),
+    (295, "LPSAFEARRAY_Size"),
+    (296, "LPSAFEARRAY_Marshal"),
+    (297, "LPSAFEARRAY_Unmarshal"),
+    (298, "VarDecCmpR8"),
+    (299, "VarCyAdd"),
+    (300, "DllUnregisterServer"),
+    (301, "OACreateTypeLib2"),
+    (303, "VarCyMul"),
+    (304, "VarCyMulI4"),
+    (305, "VarCySub"),
+    (306, "VarCyAbs"),
+    (307, "VarCyFix"),
+    (308, "VarCyInt"),
+    (309, "VarCyNeg"),
+    (310, "VarCyRound"),
+    (311, "VarCyCmp"),
+    (312, "VarCyCmpR8"),
+    (313, "VarBstrCat"),
+    (314, "VarBstrCmp"),
+    (315, "VarR8Pow"),
+    (316, "VarR4CmpR8"),
+    (317, "VarR8Round"),
+    (318, "VarCat"),
+    (319, "VarDateFromUdateEx"),
+    (322, "GetRecordInfoFromGuids"),
+    (323, "GetRecordInfoFromTypeInfo"),
+    (325, "SetVarConversionLocaleSetting"),
+    (326, "GetVarConversionLocaleSetting"),
+    (327, "SetOaNoCache"),
+    (329, "VarCyMulI8"),
+    (330, "VarDateFromUdate"),
+    (331, "VarUdateFromDate"),
+    (332, "GetAltMonthNames"),
+    (333, "VarI8FromUI1"),
+    (334, "VarI8FromI2"),
+    (335, "VarI8FromR4"),
+    (336, "VarI8FromR8"),
+    (337, "VarI8FromCy"),
+    (338, "VarI8FromDate"),
+    (339, "VarI8FromStr"),
+    (340, "VarI8FromDisp"),
+    (341, "VarI8FromBool"),
+    (342, "VarI8FromI1"),
+    (343, "VarI8FromUI2"),
+    (344, "VarI8FromUI4"),
+    (345, "VarI8FromDec"),
+    (346, "VarI2FromI8"),
+    (347, "VarI2FromUI8"),
+    (348, "VarI4FromI8"),
+    (349, "VarI4FromUI8"),
+    (360, "VarR4FromI8"),
+    (361, "VarR4FromUI8"),
+    (362, "VarR8FromI8"),
+    (363, "VarR8FromUI8"),
+    (364, "VarDateFromI8"),
+    (365, "VarDateFromUI8"),
+    (366, "VarCyFromI8"),
+    (367, "VarCyFromUI8"),
+    (368, "VarBstrFromI8"),
+    (369, "VarBstrFromUI8"),
+    (370, "VarBoolFromI8"),
+    (371, "VarBoolFromUI8"),
+    (372, "VarUI1FromI8"),
+    (373, "VarUI1FromUI8"),
+    (374, "VarDecFromI8"),
+    (375, "VarDecFromUI8"),
+    (376, "VarI1FromI8"),
+    (377, "VarI1FromUI8"),
+    (378, "VarUI2FromI8"),
+    (379, "VarUI2FromUI8"),
+    (401, "OleLoadPictureEx"),
+    (402, "OleLoadPictureFileEx"),
+    (411, "SafeArrayCreateVector"),
+    (412, "SafeArrayCopyData"),
+    (413, "VectorFromBstr"),
+    (414, "BstrFromVector"),
+    (415, "OleIconToCursor"),
+    (416, "OleCreatePropertyFrameIndirect"),
+    (417, "OleCreatePropertyFrame"),
+    (418, "OleLoadPicture"),
+    (419, "OleCreatePictureIndirect"),
+    (420, "OleCreateFontIndirect"),
+    (421, "OleTranslateColor"),
+    (422, "OleLoadPictureFile"),
+    (423, "OleSavePictureFile"),
+    (424, "OleLoadPicturePath"),
+    (425, "VarUI4FromI8"),
+    (426, "VarUI4FromUI8"),
+    (427, "VarI8FromUI8"),
+    (428, "VarUI8FromI8"),
+    (429, "VarUI8FromUI1"),
+    (430, "VarUI8FromI2"),
+    (431, "VarUI8FromR4"),
+    (432, "VarUI8FromR8"),
+    (433, "VarUI8FromCy"),
+    (434, "VarUI8FromDate"),
+    (435, "VarUI8FromStr"),
+    (436, "VarUI8FromDisp"),
+    (437, "VarUI8FromBool"),
+    (438, "VarUI8FromI1"),
+    (439, "VarUI8FromUI2"),
+    (440, "VarUI8FromUI4"),
+    (441, "VarUI8FromDec"),
+    (442, "RegisterTypeLibForUser"),
+    (443, "UnRegisterTypeLibForUser"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A PE32 image with one section at RVA 0x1000 holding an import table
+    /// for `dlls`, each a DLL name and its thunks (a name, or an ordinal).
+    fn image(dlls: &[(&str, &[Result<&str, u16>])]) -> Vec<u8> {
+        let mut data = vec![0u8; 0x1000];
+        let put16 = |data: &mut Vec<u8>, at: usize, v: u16| data[at..at + 2].copy_from_slice(&v.to_le_bytes());
+        let put32 = |data: &mut Vec<u8>, at: usize, v: u32| data[at..at + 4].copy_from_slice(&v.to_le_bytes());
+        data[..2].copy_from_slice(b"MZ");
+        put32(&mut data, 0x3C, 0x40);
+        data[0x40..0x44].copy_from_slice(b"PE\0\0");
+        put16(&mut data, 0x44, 0x14C);
+        put16(&mut data, 0x46, 1);
+        put16(&mut data, 0x54, 0xE0);
+        let optional = 0x58;
+        put16(&mut data, optional, 0x10B);
+        put32(&mut data, optional + 60, 0x200);
+        put32(&mut data, optional + 92, 16);
+        put32(&mut data, optional + 104, 0x1000);
+        put32(&mut data, optional + 108, 0x100);
+        let section = optional + 0xE0;
+        put32(&mut data, section + 8, 0xE00);
+        put32(&mut data, section + 12, 0x1000);
+        put32(&mut data, section + 16, 0xE00);
+        put32(&mut data, section + 20, 0x200);
+
+        // Descriptors, then thunk arrays, then names; offsets are RVA - 0xE00
+        let mut thunks = 0x1000 + (dlls.len() + 1) * 20;
+        let mut names = 0x1400;
+        for (i, (dll, functions)) in dlls.iter().enumerate() {
+            let descriptor = 0x200 + i * 20;
+            put32(&mut data, descriptor, thunks as u32);
+            put32(&mut data, descriptor + 12, names as u32);
+            data[names - 0xE00..names - 0xE00 + dll.len()].copy_from_slice(dll.as_bytes());
+            names += dll.len() + 1;
+            for function in functions.iter() {
+                let thunk = match function {
+                    Ok(name) => {
+                        data[names - 0xE00 + 2..names - 0xE00 + 2 + name.len()].copy_from_slice(name.as_bytes());
+                        let rva = names as u32;
+                        names += name.len() + 3;
+                        rva
+                    }
+                    Err(ordinal) => 0x8000_0000 | *ordinal as u32,
+                };
+                put32(&mut data, thunks - 0xE00, thunk);
+                thunks += 4;
+            }
+            thunks += 4;
+        }
+        data
+    }
+
+    #[test]
+    fn imphash_resolves_ordinals_like_pefile() {
+        let data = image(&[
+            ("KERNEL32.dll", &[Ok("ExitProcess")]),
+            ("WS2_32.dll", &[Err(115), Err(999)]),
+            ("OLEAUT32.dll", &[Err(2)]),
+            ("MSVCRT.dll", &[Err(10)]),
+        ]);
+        let imports = Pe::parse(&data).unwrap().imports().unwrap();
+        let functions: Vec<&str> = imports.iter().map(|(_, f)| f.as_str()).collect();
+        assert_eq!(functions, ["ExitProcess", "WSAStartup", "ord999", "SysAllocString", "ord10"]);
+        // pefile: "kernel32.exitprocess,ws2_32.wsastartup,ws2_32.ord999,oleaut32.sysallocstring,msvcrt.ord10"
+        assert_eq!(imphash(&data).unwrap(), "c65aaba9d1363b586dd574c0e3179ea1");
+    }
+
+    #[test]
+    fn imphash_needs_imports() {
+        assert_eq!(imphash(&image(&[])), Err("PE has no imports".to_string()));
+        assert!(imphash(b"not a PE").is_err());
+    }
+
+    #[test]
+    fn lying_size_of_headers_is_an_error() {
+        let mut data = image(&[("KERNEL32.dll", &[Ok("ExitProcess")])]);
+        // Headers claimed to reach far past EOF, the import directory given as
+        // its file offset inside them, and a DLL name RVA inside them too but
+        // outside the file and every section
+        data[0x58 + 60..0x58 + 64].copy_from_slice(&0x10_0000u32.to_le_bytes());
+        data[0x58 + 104..0x58 + 108].copy_from_slice(&0x200u32.to_le_bytes());
+        data[0x200 + 12..0x200 + 16].copy_from_slice(&0x3000u32.to_le_bytes());
+        assert_eq!(imphash(&data), Err("RVA 0x3000 is outside the file".to_string()));
+        data.truncate(0x180);
+        assert!(imphash(&data).is_err());
+    }
+
+    #[test]
+    fn authentihash_skips_the_checksum_and_certificates() {
+        use sha2::{Digest, Sha256};
+        let mut data = image(&[("KERNEL32.dll", &[Ok("ExitProcess")])]);
+        // CheckSum at optional header + 64, the security entry at + 96 + 4 * 8
+        let (checksum, security) = (0x58 + 64, 0x58 + 96 + 32);
+        let mut signed = data[..checksum].to_vec();
+        signed.extend(&data[checksum + 4..security]);
+        signed.extend(&data[security + 8..]);
+        let expected = hex::encode(Sha256::digest(&signed));
+        assert_eq!(authentihash(&data).unwrap(), expected);
+
+        // Signing fills in the checksum and security entry and appends the certificates
+        data[checksum..checksum + 4].copy_from_slice(&0x1234u32.to_le_bytes());
+        data[security..security + 4].copy_from_slice(&0x1000u32.to_le_bytes());
+        data[security + 4..security + 8].copy_from_slice(&8u32.to_le_bytes());
+        data.extend(b"CERTDATA");
+        assert_eq!(authentihash(&data).unwrap(), expected);
+
+        data[0x300] ^= 1;
+        assert_ne!(authentihash(&data).unwrap(), expected);
+        assert!(authentihash(&data[..0xC0]).is_err());
+    }
+
+    #[test]
+    fn ordinal_tables_are_sorted() {
+        for table in [WS2_32_ORDINALS, OLEAUT32_ORDINALS] {
+            assert!(table.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+    }
+}
@@ -0,0 +1,284 @@
+// ============================================================================
+// src/prompts.rs
+// ============================================================================
+use rust_mcp_sdk::schema::{
+    GetPromptResult, Prompt, PromptArgument, PromptMessage, Role, RpcError, TextContent,
+};
+use std::collections::HashMap;
+
+struct PromptSpec {
+    name: &'static str,
+    title: &'static str,
+    description: &'static str,
+    /// (name, description, required)
+    arguments: &'static [(&'static str, &'static str, bool)],
+}
+
+const PATH_ARG: (&str, &str, bool) = (
+    "path",
+    "Path of the file to load; omit to work on the currently loaded buffer",
+    false,
+);
+
+const PROMPTS: [PromptSpec; 5] = [
+    PromptSpec {
+        name: "triage_unknown_binary",
+        title: "Triage unknown binary",
+        description: "Identify the format of an unknown file, map its main structures and summarize it",
+        arguments: &[PATH_ARG],
+    },
+    PromptSpec {
+        name: "find_config_block",
+        title: "Find configuration block",
+        description: "Locate an embedded configuration block (keys, URLs, magic markers) and decode its fields",
+        arguments: &[
+            PATH_ARG,
+            ("marker", "Known marker of the block, as hex (e.g. 'DEADBEEF') or plain text", false),
+        ],
+    },
+    PromptSpec {
+        name: "analyze_pe_imports",
+        title: "Analyze PE imports",
+        description: "Walk the PE headers and section table to recover the import table",
+        arguments: &[PATH_ARG],
+    },
+    PromptSpec {
+        name: "document_firmware_header",
+        title: "Document firmware header",
+        description: "Decode and document the fields of a firmware image header",
+        arguments: &[
+            PATH_ARG,
+            ("header_size", "Number of header bytes to examine (default 256)", false),
+        ],
+    },
+    PromptSpec {
+        name: "explain_region",
+        title: "Explain region",
+        description: "Explain the bytes at a bookmark or inside an extracted segment",
+        arguments: &[
+            ("bookmark", "Name of the bookmark to start from", false),
+            ("segment", "Label of the extracted segment to explain", false),
+        ],
+    },
+];
+
+pub fn list_prompts() -> Vec<Prompt> {
+    PROMPTS
+        .iter()
+        .map(|spec| Prompt {
+            arguments: spec
+                .arguments
+                .iter()
+                .map(|(name, description, required)| PromptArgument {
+                    description: Some(description.to_string()),
+                    name: name.to_string(),
+                    required: Some(*required),
+                    title: None,
+                })
+                .collect(),
+            description: Some(spec.description.to_string()),
+            meta: None,
+            name: spec.name.to_string(),
+            title: Some(spec.title.to_string()),
+        })
+        .collect()
+}
+
+/// Argument names of a prompt, or `None` if there is no such prompt.
+pub fn prompt_arguments(name: &str) -> Option<Vec<&'static str>> {
+    PROMPTS
+        .iter()
+        .find(|spec| spec.name == name)
+        .map(|spec| spec.arguments.iter().map(|(arg, _, _)| *arg).collect())
+}
+
+pub fn get_prompt(
+    name: &str,
+    arguments: &HashMap<String, String>,
+) -> Result<GetPromptResult, RpcError> {
+    let spec = PROMPTS.iter().find(|spec| spec.name == name).ok_or_else(|| {
+        RpcError::invalid_params().with_message(format!("Unknown prompt '{}'", name))
+    })?;
+
+    for (arg, _, required) in spec.arguments {
+        if *required && !arguments.contains_key(*arg) {
+            return Err(RpcError::invalid_params()
+                .with_message(format!("Missing required argument '{}'", arg)));
+        }
+    }
+
+    let arg = |key: &str| arguments.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+    let load_step = match arg("path") {
+        Some(path) => format!("Call load_binary with path '{}'.", path),
+        None => "Use the buffer that is already loaded (check it with get_info).".to_string(),
+    };
+
+    let text = match spec.name {
+        "triage_unknown_binary" => format!(
+            "Triage an unknown binary with the binary analysis tools.\n\n\
+             1. {load_step}\n\
+             2. Call get_info and calculate_hash to record size and SHA-256.\n\
+             3. Call read_bytes at offset 0 with length 64 and identify the magic number.\n\
+             4. Use search_pattern for common signatures: 4D5A (MZ/PE), 7F454C46 (ELF), \
+             504B0304 (ZIP), 1F8B08 (gzip), 89504E47 (PNG), 27051956 (U-Boot uImage).\n\
+             5. For every structure you identify, call add_bookmark at its start and \
+             extract_segment for embedded payloads, with a descriptive label.\n\
+             6. Inspect interesting offsets with read_string and read_integer.\n\
+             7. Record each finding with add_note.\n\
+             8. Finish with set_output: format, architecture if known, embedded content, \
+             and anything suspicious."
+        ),
+        "find_config_block" => {
+            let marker_step = match arg("marker") {
+                Some(marker) => format!(
+                    "Search for the marker '{marker}' with search_pattern (hex-encode it first \
+                     if it is plain text)."
+                ),
+                None => "Search for likely markers with search_pattern: hex-encoded fragments of \
+                         'http', 'key', 'cfg', 'config', as well as long runs of 00 or FF that \
+                         often pad configuration blocks."
+                    .to_string(),
+            };
+            format!(
+                "Find and decode an embedded configuration block.\n\n\
+                 1. {load_step}\n\
+                 2. {marker_step}\n\
+                 3. Around each candidate, call read_bytes (256 bytes) and read_string to see \
+                 whether the data looks like key/value pairs, URLs, IP addresses or length-prefixed \
+                 fields.\n\
+                 4. Decode numeric fields with read_integer, trying both endiannesses where the \
+                 value is ambiguous.\n\
+                 5. Call add_bookmark for the block start and extract_segment for the whole \
+                 block labelled 'config'.\n\
+                 6. Record each decoded field with add_note, then summarize the configuration \
+                 with set_output."
+            )
+        }
+        "analyze_pe_imports" => format!(
+            "Recover the import table of a PE file.\n\n\
+             1. {load_step}\n\
+             2. Check the 'MZ' magic with read_bytes at offset 0, then read e_lfanew with \
+             read_integer at offset 0x3C (size 4, little endian).\n\
+             3. At e_lfanew, confirm the signature 50450000 ('PE\\0\\0'). The COFF header follows: \
+             read NumberOfSections (offset +6, size 2) and SizeOfOptionalHeader (offset +20, size 2).\n\
+             4. The optional header starts at e_lfanew + 24. Its magic (size 2) is 0x10B for PE32 \
+             or 0x20B for PE32+. The data directories start at +96 (PE32) or +112 (PE32+); the \
+             import directory is entry 1 (RVA and size, 4 bytes each).\n\
+             5. Read the section table (40 bytes per entry, after the optional header) and map the \
+             import RVA to a file offset: offset = RVA - VirtualAddress + PointerToRawData.\n\
+             6. Walk the IMAGE_IMPORT_DESCRIPTOR array (20 bytes each, terminated by zeros). For each \
+             descriptor, read the DLL name with read_string and walk the thunk array to list \
+             imported function names.\n\
+             7. Bookmark the headers, the section table and the import directory; add a note per \
+             DLL with its imported functions.\n\
+             8. Finish with set_output summarizing imports and any that suggest capabilities \
+             (networking, crypto, process injection, persistence)."
+        ),
+        "document_firmware_header" => {
+            let header_size = arg("header_size").unwrap_or("256");
+            format!(
+                "Document the header of a firmware image.\n\n\
+                 1. {load_step}\n\
+                 2. Call read_bytes at offset 0 with length {header_size} and identify magic \
+                 values and version strings.\n\
+                 3. Walk the header field by field with read_integer, trying both endiannesses. \
+                 Look for values matching the file size (see get_info), offsets that land inside \
+                 the file, load or entry addresses, timestamps and checksums.\n\
+                 4. Verify checksum candidates with calculate_hash over the ranges they cover.\n\
+                 5. Call add_bookmark for each field using its name (e.g. 'hdr_magic', \
+                 'hdr_image_size') and extract_segment for the header and each payload it describes.\n\
+                 6. Finish with set_output containing a table of offset, size, endianness, value \
+                 and meaning for every field."
+            )
+        }
+        "explain_region" => {
+            let target = match (arg("bookmark"), arg("segment")) {
+                (Some(bookmark), _) => format!(
+                    "Read the bookmarks resource (binary://bookmarks) to find the offset of '{bookmark}', \
+                     then call read_bytes there with length 256."
+                ),
+                (None, Some(segment)) => format!(
+                    "Find the segment labelled '{segment}' in resources/list and read it (or call \
+                     read_bytes at its offset and length)."
+                ),
+                (None, None) => "Pick the most interesting bookmark or segment from \
+                                 binary://bookmarks and resources/list and read its bytes."
+                    .to_string(),
+            };
+            format!(
+                "Explain a region of the loaded buffer.\n\n\
+                 1. {target}\n\
+                 2. Identify magic values, strings (read_string) and plausible integer fields \
+                 (read_integer, both endiannesses).\n\
+                 3. Relate the region to the surrounding structure: what points to it and what it \
+                 points to.\n\
+                 4. Record your explanation with add_note and bookmark any new fields you find."
+            )
+        }
+        _ => unreachable!("prompt specs and templates are kept in sync"),
+    };
+
+    Ok(GetPromptResult {
+        description: Some(spec.description.to_string()),
+        messages: vec![PromptMessage {
+            content: TextContent::new(text, None, None).into(),
+            role: Role::User,
+        }],
+        meta: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::BinaryTools;
+    use rust_mcp_sdk::schema::ContentBlock;
+
+    fn render(name: &str, arguments: &[(&str, &str)]) -> Result<String, RpcError> {
+        let arguments = arguments.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let result = get_prompt(name, &arguments)?;
+        match &result.messages[0].content {
+            ContentBlock::TextContent(text) => Ok(text.text.clone()),
+            other => panic!("expected text, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn prompts_only_mention_existing_tools() {
+        let tools: Vec<String> = BinaryTools::tools().into_iter().map(|t| t.name).collect();
+        // Field names the prompts use as examples; structure names are upper case
+        let fields = ["e_lfanew", "hdr_magic", "hdr_image_size"];
+
+        for prompt in list_prompts() {
+            let names = prompt_arguments(&prompt.name).unwrap();
+            let arguments: Vec<(&str, &str)> = names.iter().map(|name| (*name, "x")).collect();
+            for arguments in [&arguments[..], &[]] {
+                let text = render(&prompt.name, arguments).unwrap();
+                for word in text.split(|c: char| !c.is_ascii_alphanumeric() && c != '_') {
+                    if word.contains('_') && word == word.to_lowercase() && !fields.contains(&word) {
+                        assert!(tools.iter().any(|tool| tool == word), "{} mentions {}", prompt.name, word);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn arguments_shape_the_steps() {
+        let text = render("triage_unknown_binary", &[("path", " /tmp/a.bin ")]).unwrap();
+        assert!(text.contains("Call load_binary with path '/tmp/a.bin'."));
+        let text = render("triage_unknown_binary", &[("path", "  ")]).unwrap();
+        assert!(text.contains("Use the buffer that is already loaded"));
+
+        let text = render("find_config_block", &[("marker", "DEADBEEF")]).unwrap();
+        assert!(text.contains("Search for the marker 'DEADBEEF'"));
+        let text = render("document_firmware_header", &[]).unwrap();
+        assert!(text.contains("length 256"));
+        let text = render("explain_region", &[("segment", "cfg")]).unwrap();
+        assert!(text.contains("labelled 'cfg'"));
+
+        assert!(render("missing", &[]).unwrap_err().message.contains("Unknown prompt 'missing'"));
+        assert_eq!(prompt_arguments("explain_region").unwrap(), ["bookmark", "segment"]);
+        assert!(prompt_arguments("missing").is_none());
+    }
+}
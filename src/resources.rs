@@ -0,0 +1,405 @@
+// ============================================================================
+// src/resources.rs
+// ============================================================================
+use crate::state::{ServerState, StateChange};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rust_mcp_sdk::schema::{
+    BlobResourceContents, ReadResourceResultContentsItem, Resource, ResourceTemplate, RpcError,
+    TextResourceContents,
+};
+
+pub const SCHEME: &str = "binary://";
+pub const BOOKMARKS_URI: &str = "binary://bookmarks";
+pub const NOTES_URI: &str = "binary://notes";
+pub const OUTPUT_URI: &str = "binary://output";
+
+const OCTET_STREAM: &str = "application/octet-stream";
+const TEXT_PLAIN: &str = "text/plain";
+
+/// A parsed `binary://` URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryUri {
+    /// `binary://{buffer}/range/{offset}/{len}`
+    Range { buffer: String, offset: usize, length: usize },
+    /// `binary://segments/{index}`
+    Segment(usize),
+    /// `binary://bookmarks`
+    Bookmarks,
+    /// `binary://notes`
+    Notes,
+    /// `binary://output`
+    Output,
+}
+
+impl BinaryUri {
+    pub fn parse(uri: &str) -> Result<Self, RpcError> {
+        let path = uri
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| invalid_uri(uri, "expected the binary:// scheme"))?;
+        let parts: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+
+        match parts.as_slice() {
+            ["bookmarks"] => Ok(BinaryUri::Bookmarks),
+            ["notes"] => Ok(BinaryUri::Notes),
+            ["output"] => Ok(BinaryUri::Output),
+            ["segments", index] => Ok(BinaryUri::Segment(parse_number(uri, index)?)),
+            [buffer, "range", offset, length] => Ok(BinaryUri::Range {
+                buffer: buffer.to_string(),
+                offset: parse_number(uri, offset)?,
+                length: parse_number(uri, length)?,
+            }),
+            _ => Err(invalid_uri(uri, "unknown resource path")),
+        }
+    }
+
+    /// Whether the contents behind this URI depend on the given part of the state.
+    pub fn affected_by(&self, change: StateChange) -> bool {
+        matches!(
+            (self, change),
+            (BinaryUri::Range { .. }, StateChange::Buffer)
+                | (BinaryUri::Segment(_), StateChange::Segments)
+                | (BinaryUri::Bookmarks, StateChange::Bookmarks)
+                | (BinaryUri::Notes, StateChange::Notes)
+                | (BinaryUri::Output, StateChange::Output)
+        )
+    }
+}
+
+/// Whether a change adds or removes entries from `resources/list`.
+pub fn changes_resource_list(change: StateChange) -> bool {
+    matches!(change, StateChange::Buffer | StateChange::Segments)
+}
+
+/// Accepts decimal or `0x`-prefixed hexadecimal numbers.
+fn parse_number(uri: &str, text: &str) -> Result<usize, RpcError> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| invalid_uri(uri, &format!("'{}' is not a number", text)))
+}
+
+fn invalid_uri(uri: &str, reason: &str) -> RpcError {
+    RpcError::invalid_params().with_message(format!("Invalid resource URI '{}': {}", uri, reason))
+}
+
+/// MCP's "resource not found" error (-32002).
+fn not_found(uri: &str, reason: &str) -> RpcError {
+    RpcError {
+        code: -32002,
+        data: Some(serde_json::json!({ "uri": uri })),
+        message: format!("Resource not found: {}", reason),
+    }
+}
+
+pub fn range_uri(buffer: &str, offset: usize, length: usize) -> String {
+    format!("{}{}/range/{}/{}", SCHEME, buffer, offset, length)
+}
+
+pub fn segment_uri(index: usize) -> String {
+    format!("{}segments/{}", SCHEME, index)
+}
+
+pub fn resource_templates() -> Vec<ResourceTemplate> {
+    let template = |uri_template: &str, name: &str, description: &str, mime_type: &str| {
+        ResourceTemplate {
+            annotations: None,
+            description: Some(description.to_string()),
+            meta: None,
+            mime_type: Some(mime_type.to_string()),
+            name: name.to_string(),
+            title: None,
+            uri_template: uri_template.to_string(),
+        }
+    };
+
+    vec![
+        template(
+            "binary://{buffer}/range/{offset}/{len}",
+            "buffer_range",
+            "Raw bytes of a buffer range; {buffer} is the loaded file name or 'active', numbers may be decimal or 0x-prefixed hex",
+            OCTET_STREAM,
+        ),
+        template(
+            "binary://segments/{index}",
+            "segment",
+            "Raw bytes of an extracted segment",
+            OCTET_STREAM,
+        ),
+    ]
+}
+
+/// Variable names of one of our URI templates, or `None` if it isn't ours.
+pub fn template_arguments(uri_template: &str) -> Option<Vec<String>> {
+    resource_templates()
+        .iter()
+        .find(|template| template.uri_template == uri_template)
+        .map(|template| {
+            template
+                .uri_template
+                .split('{')
+                .skip(1)
+                .filter_map(|part| part.split_once('}').map(|(name, _)| name.to_string()))
+                .collect()
+        })
+}
+
+pub fn list_resources(state: &ServerState) -> Vec<Resource> {
+    let resource = |uri: String, name: String, description: String, mime_type: &str, size: Option<usize>| {
+        Resource {
+            annotations: None,
+            description: Some(description),
+            meta: None,
+            mime_type: Some(mime_type.to_string()),
+            name,
+            size: size.map(|s| s as i64),
+            title: None,
+            uri,
+        }
+    };
+
+    let mut resources = Vec::new();
+
+    if !state.buffer.is_empty() {
+        let name = state.buffer_name();
+        resources.push(resource(
+            range_uri(&name, 0, state.buffer.len()),
+            name.clone(),
+            format!("Entire loaded buffer '{}'", name),
+            OCTET_STREAM,
+            Some(state.buffer.len()),
+        ));
+    }
+
+    for (i, seg) in state.segments.iter().enumerate() {
+        resources.push(resource(
+            segment_uri(i),
+            seg.label.clone().unwrap_or_else(|| format!("segment_{}", i)),
+            format!("Segment {} extracted at 0x{:08X} ({} bytes)", i, seg.offset, seg.data.len()),
+            OCTET_STREAM,
+            Some(seg.data.len()),
+        ));
+    }
+
+    resources.push(resource(
+        BOOKMARKS_URI.to_string(),
+        "bookmarks".to_string(),
+        format!("Named bookmarks ({})", state.bookmarks.len()),
+        TEXT_PLAIN,
+        None,
+    ));
+    resources.push(resource(
+        NOTES_URI.to_string(),
+        "notes".to_string(),
+        format!("Analysis notes ({})", state.analysis_notes.len()),
+        TEXT_PLAIN,
+        None,
+    ));
+    resources.push(resource(
+        OUTPUT_URI.to_string(),
+        "output".to_string(),
+        "Final analysis output".to_string(),
+        TEXT_PLAIN,
+        Some(state.output.len()),
+    ));
+
+    resources
+}
+
+pub fn read_resource(
+    state: &ServerState,
+    uri: &str,
+) -> Result<Vec<ReadResourceResultContentsItem>, RpcError> {
+    let contents = match BinaryUri::parse(uri)? {
+        BinaryUri::Range { buffer, offset, length } => {
+            if buffer != "active" && buffer != state.buffer_name() {
+                return Err(not_found(uri, &format!("no buffer named '{}'", buffer)));
+            }
+            let end = offset
+                .checked_add(length)
+                .filter(|&end| end <= state.buffer.len())
+                .ok_or_else(|| invalid_uri(uri, "range exceeds buffer bounds"))?;
+            blob(uri, &state.buffer[offset..end])
+        }
+        BinaryUri::Segment(index) => {
+            let seg = state.segments.get(index)
+                .ok_or_else(|| not_found(uri, &format!("no segment with index {}", index)))?;
+            blob(uri, &seg.data)
+        }
+        BinaryUri::Bookmarks => text(
+            uri,
+            state.sorted_bookmarks()
+                .iter()
+                .map(|(name, bookmark)| bookmark.describe(name))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        BinaryUri::Notes => text(
+            uri,
+            state.analysis_notes
+                .iter()
+                .map(|note| note.describe())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        BinaryUri::Output => text(uri, state.output.clone()),
+    };
+
+    Ok(vec![contents])
+}
+
+fn blob(uri: &str, data: &[u8]) -> ReadResourceResultContentsItem {
+    ReadResourceResultContentsItem::BlobResourceContents(blob_contents(uri, data, OCTET_STREAM))
+}
+
+pub fn blob_contents(uri: &str, data: &[u8], mime_type: &str) -> BlobResourceContents {
+    BlobResourceContents {
+        blob: BASE64.encode(data),
+        meta: None,
+        mime_type: Some(mime_type.to_string()),
+        uri: uri.to_string(),
+    }
+}
+
+/// Guesses a MIME type from magic numbers, falling back to text/plain for
+/// printable UTF-8 and application/octet-stream otherwise.
+pub fn guess_mime_type(data: &[u8]) -> &'static str {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"MZ", "application/vnd.microsoft.portable-executable"),
+        (b"\x7FELF", "application/x-elf"),
+        (b"\xFE\xED\xFA\xCE", "application/x-mach-binary"),
+        (b"\xFE\xED\xFA\xCF", "application/x-mach-binary"),
+        (b"\xCE\xFA\xED\xFE", "application/x-mach-binary"),
+        (b"\xCF\xFA\xED\xFE", "application/x-mach-binary"),
+        (b"\xCA\xFE\xBA\xBE", "application/java-vm"),
+        (b"\0asm", "application/wasm"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1F\x8B", "application/gzip"),
+        (b"BZh", "application/x-bzip2"),
+        (b"\xFD7zXZ\0", "application/x-xz"),
+        (b"7z\xBC\xAF\x27\x1C", "application/x-7z-compressed"),
+        (b"\x28\xB5\x2F\xFD", "application/zstd"),
+        (b"%PDF-", "application/pdf"),
+        (b"\x89PNG\r\n\x1A\n", "image/png"),
+        (b"\xFF\xD8\xFF", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"-----BEGIN ", "application/x-pem-file"),
+    ];
+
+    if let Some((_, mime_type)) = MAGIC.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return mime_type;
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) if !data.is_empty()
+            && text.chars().all(|c| !c.is_control() || c.is_ascii_whitespace()) => TEXT_PLAIN,
+        _ => OCTET_STREAM,
+    }
+}
+
+fn text(uri: &str, text: String) -> ReadResourceResultContentsItem {
+    ReadResourceResultContentsItem::TextResourceContents(TextResourceContents {
+        meta: None,
+        mime_type: Some(TEXT_PLAIN.to_string()),
+        text,
+        uri: uri.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uris_parse() {
+        assert_eq!(BinaryUri::parse("binary://bookmarks").unwrap(), BinaryUri::Bookmarks);
+        assert_eq!(BinaryUri::parse("binary://notes/").unwrap(), BinaryUri::Notes);
+        assert_eq!(BinaryUri::parse("binary://output").unwrap(), BinaryUri::Output);
+        assert_eq!(BinaryUri::parse("binary://segments/0x10").unwrap(), BinaryUri::Segment(16));
+        assert_eq!(
+            BinaryUri::parse("binary://a.exe/range/0x40/32").unwrap(),
+            BinaryUri::Range { buffer: "a.exe".to_string(), offset: 64, length: 32 }
+        );
+        assert_eq!(BinaryUri::parse(&range_uri("active", 1, 2)).unwrap(), BinaryUri::Range {
+            buffer: "active".to_string(),
+            offset: 1,
+            length: 2,
+        });
+
+        for uri in [
+            "file:///etc/passwd",
+            "binary://",
+            "binary://segments",
+            "binary://segments/-1",
+            "binary://segments/0xZZ",
+            "binary://a.exe/range/0",
+            "binary://a.exe/range/1/2/3",
+            "binary://a.exe/range/1/99999999999999999999999",
+        ] {
+            let error = BinaryUri::parse(uri).unwrap_err();
+            assert!(error.message.starts_with(&format!("Invalid resource URI '{}'", uri)), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn ranges_are_read_within_bounds() {
+        let mut state = ServerState::new();
+        state.replace_buffer(b"0123456789".to_vec(), "a.bin".to_string());
+        let read = |uri: &str| read_resource(&state, uri);
+
+        match &read("binary://a.bin/range/2/3").unwrap()[0] {
+            ReadResourceResultContentsItem::BlobResourceContents(blob) => {
+                assert_eq!(BASE64.decode(&blob.blob).unwrap(), b"234");
+            }
+            other => panic!("expected a blob, got {:?}", other),
+        }
+        assert!(read("binary://active/range/0/10").is_ok());
+        assert!(read("binary://a.bin/range/8/3").unwrap_err().message.contains("exceeds buffer bounds"));
+        assert!(read(&range_uri("a.bin", usize::MAX, 2)).unwrap_err().message.contains("exceeds buffer bounds"));
+        assert_eq!(read("binary://b.bin/range/0/1").unwrap_err().code, -32002);
+        assert_eq!(read("binary://segments/0").unwrap_err().code, -32002);
+    }
+
+    #[test]
+    fn subscriptions_follow_the_state_they_show() {
+        let uris = ["binary://a/range/0/1", "binary://segments/0", BOOKMARKS_URI, NOTES_URI, OUTPUT_URI]
+            .map(|uri| BinaryUri::parse(uri).unwrap());
+        let affected = |change| uris.iter().map(|uri| uri.affected_by(change)).collect::<Vec<_>>();
+        assert_eq!(affected(StateChange::Buffer), [true, false, false, false, false]);
+        assert_eq!(affected(StateChange::Segments), [false, true, false, false, false]);
+        assert_eq!(affected(StateChange::Bookmarks), [false, false, true, false, false]);
+        assert_eq!(affected(StateChange::Notes), [false, false, false, true, false]);
+        assert_eq!(affected(StateChange::Output), [false, false, false, false, true]);
+
+        assert!(changes_resource_list(StateChange::Buffer));
+        assert!(changes_resource_list(StateChange::Segments));
+        assert!(!changes_resource_list(StateChange::Bookmarks));
+        assert!(!changes_resource_list(StateChange::Notes));
+        assert!(!changes_resource_list(StateChange::Output));
+
+        // A load rewrites the buffer and drops bookmarks and segments, but
+        // leaves the output alone
+        let mut state = ServerState::new();
+        state.replace_buffer(vec![0; 4], "a".to_string());
+        let changes = state.take_changes();
+        assert_eq!(
+            uris.iter().map(|uri| changes.iter().any(|c| uri.affected_by(*c))).collect::<Vec<_>>(),
+            [true, true, true, false, false]
+        );
+    }
+
+    #[test]
+    fn mime_types_come_from_magic_numbers() {
+        assert_eq!(guess_mime_type(b"MZ\x90\x00"), "application/vnd.microsoft.portable-executable");
+        assert_eq!(guess_mime_type(b"\x7FELF\x02"), "application/x-elf");
+        assert_eq!(guess_mime_type(b"\xCF\xFA\xED\xFE"), "application/x-mach-binary");
+        assert_eq!(guess_mime_type(b"PK\x03\x04rest"), "application/zip");
+        assert_eq!(guess_mime_type(b"\x89PNG\r\n\x1A\n"), "image/png");
+        assert_eq!(guess_mime_type(b"-----BEGIN CERTIFICATE-----"), "application/x-pem-file");
+        assert_eq!(guess_mime_type(b"key = value\r\n\ttabbed"), TEXT_PLAIN);
+        assert_eq!(guess_mime_type("ünïcode".as_bytes()), TEXT_PLAIN);
+        assert_eq!(guess_mime_type(b"text\x00with nul"), OCTET_STREAM);
+        assert_eq!(guess_mime_type(b"\xFF\xFE"), OCTET_STREAM);
+        assert_eq!(guess_mime_type(b"\x89PN"), OCTET_STREAM, "truncated magic");
+        assert_eq!(guess_mime_type(b""), OCTET_STREAM);
+    }
+}
@@ -0,0 +1,118 @@
+// ============================================================================
+// src/sampling.rs
+// ============================================================================
+use rust_mcp_sdk::schema::{
+    CreateMessageRequestParams, CreateMessageResult, CreateMessageResultContent, Role,
+    SamplingMessage, TextContent,
+};
+
+/// Tag of analysis notes written from a client model's answer.
+pub const MODEL_NOTE_TAG: &str = "model-generated";
+
+/// Bytes of a region shown to the model at most, as hex.
+pub const MAX_SAMPLED_BYTES: usize = 512;
+
+const SYSTEM_PROMPT: &str = "You are assisting a reverse engineer analysing a binary file. \
+Answer briefly and precisely, and say when the data is insufficient to be sure.";
+
+/// A question for the client's model, and what its answer will be recorded about.
+pub struct SamplingRequest {
+    /// What the answer is about, e.g. "region 0x00000000-0x00000040"
+    pub subject: String,
+    /// Anchor of the resulting note
+    pub offset: Option<usize>,
+    pub length: usize,
+    pub bookmark: Option<String>,
+    pub prompt: String,
+    pub max_tokens: i64,
+}
+
+impl SamplingRequest {
+    pub fn into_params(self) -> CreateMessageRequestParams {
+        CreateMessageRequestParams {
+            include_context: None,
+            max_tokens: self.max_tokens,
+            messages: vec![SamplingMessage {
+                content: TextContent::new(self.prompt, None, None).into(),
+                role: Role::User,
+            }],
+            metadata: None,
+            model_preferences: None,
+            stop_sequences: Vec::new(),
+            system_prompt: Some(SYSTEM_PROMPT.to_string()),
+            temperature: Some(0.2),
+        }
+    }
+}
+
+/// The text of a sampling answer; image and audio answers are not useful here.
+pub fn answer_text(result: &CreateMessageResult) -> Option<String> {
+    match &result.content {
+        CreateMessageResultContent::TextContent(text) => {
+            Some(text.text.trim().to_string()).filter(|t| !t.is_empty())
+        }
+        _ => None,
+    }
+}
+
+pub fn model_note_text(subject: &str, model: &str, answer: &str) -> String {
+    format!("{} ({}): {}", subject, model, answer)
+}
+
+/// Runs of printable ASCII at least `min_length` long, with their offsets
+/// relative to `data`.
+pub fn ascii_strings(data: &[u8], min_length: usize) -> Vec<(usize, String)> {
+    let mut strings = Vec::new();
+    let mut start = None;
+    for (i, &b) in data.iter().chain(std::iter::once(&0)).enumerate() {
+        let printable = b == b'\t' || (0x20..0x7F).contains(&b);
+        match (printable, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                if i - s >= min_length {
+                    strings.push((s, String::from_utf8_lossy(&data[s..i]).into_owned()));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    strings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_mcp_sdk::schema::ImageContent;
+
+    #[test]
+    fn strings_are_printable_runs() {
+        let data = b"\x00MZ\x00hello\tworld\x01abc\xFFlast";
+        assert_eq!(
+            ascii_strings(data, 4),
+            [(4, "hello\tworld".to_string()), (20, "last".to_string())]
+        );
+        assert_eq!(ascii_strings(data, 3).len(), 3);
+        assert_eq!(ascii_strings(b"abcd", 4), [(0, "abcd".to_string())]);
+        assert!(ascii_strings(b"abc", 4).is_empty());
+        assert!(ascii_strings(b"", 1).is_empty());
+        assert!(ascii_strings(b"\n\r\x7F", 1).is_empty());
+    }
+
+    #[test]
+    fn only_text_answers_are_kept() {
+        let answer = |content: CreateMessageResultContent| CreateMessageResult {
+            content,
+            meta: None,
+            model: "m".to_string(),
+            role: Role::Assistant,
+            stop_reason: None,
+        };
+        let text = |t: &str| answer(TextContent::new(t.to_string(), None, None).into());
+
+        assert_eq!(answer_text(&text("  a header \n")).as_deref(), Some("a header"));
+        assert_eq!(answer_text(&text(" \n ")), None);
+        let image = ImageContent::new(String::new(), "image/png".to_string(), None, None);
+        assert_eq!(answer_text(&answer(image.into())), None);
+    }
+}
@@ -0,0 +1,56 @@
+// ============================================================================
+// src/session.rs
+// ============================================================================
+use crate::groups::ToolGroups;
+use crate::jobs::JobRegistry;
+use crate::logging;
+use rust_mcp_sdk::schema::LoggingLevel;
+use rust_mcp_sdk::McpServer;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Key used for transports that don't carry a session id (e.g. stdio).
+pub const DEFAULT_SESSION: &str = "default";
+
+/// Per-client state kept by the handler, separate from the shared `ServerState`.
+pub struct SessionState {
+    pub runtime: Arc<dyn McpServer>,
+    pub tool_groups: ToolGroups,
+    /// Resource URIs the client asked to be notified about
+    pub subscriptions: HashSet<String>,
+    /// Long-running tool calls that can be cancelled
+    pub jobs: JobRegistry,
+    /// Minimum level of notifications/message sent to the client
+    pub log_level: LoggingLevel,
+}
+
+impl SessionState {
+    pub fn new(runtime: Arc<dyn McpServer>, tool_groups: ToolGroups) -> Self {
+        Self {
+            runtime,
+            tool_groups,
+            subscriptions: HashSet::new(),
+            jobs: JobRegistry::default(),
+            log_level: logging::DEFAULT_LEVEL,
+        }
+    }
+}
+
+pub fn session_key(runtime: &Arc<dyn McpServer>) -> String {
+    runtime.session_id().unwrap_or_else(|| DEFAULT_SESSION.to_string())
+}
+
+/// Drops the sessions the transport no longer knows, cancelling their jobs,
+/// and returns their keys. The session for id-less transports is kept.
+pub fn prune(sessions: &mut HashMap<String, SessionState>, live: &HashSet<String>) -> Vec<String> {
+    let gone: Vec<String> = sessions.keys()
+        .filter(|key| *key != DEFAULT_SESSION && !live.contains(*key))
+        .cloned()
+        .collect();
+    for key in &gone {
+        if let Some(mut session) = sessions.remove(key) {
+            session.jobs.cancel_all();
+        }
+    }
+    gone
+}
@@ -0,0 +1,389 @@
+// ============================================================================
+// src/ssdeep.rs
+// ============================================================================
+const ROLLING_WINDOW: usize = 7;
+const MIN_BLOCKSIZE: u64 = 3;
+const HASH_PRIME: u32 = 0x0100_0193;
+const HASH_INIT: u32 = 0x2802_1967;
+const SPAMSUM_LENGTH: usize = 64;
+const NUM_BLOCKHASHES: usize = 31;
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn block_size(index: usize) -> u64 {
+    MIN_BLOCKSIZE << index
+}
+
+fn sum_hash(c: u8, h: u32) -> u32 {
+    h.wrapping_mul(HASH_PRIME) ^ c as u32
+}
+
+/// Rolling hash over the last `ROLLING_WINDOW` bytes, deciding where pieces end.
+#[derive(Default)]
+struct Roll {
+    window: [u8; ROLLING_WINDOW],
+    h1: u32,
+    h2: u32,
+    h3: u32,
+    n: usize,
+}
+
+impl Roll {
+    fn update(&mut self, c: u8) {
+        self.h2 = self.h2.wrapping_sub(self.h1).wrapping_add(ROLLING_WINDOW as u32 * c as u32);
+        self.h1 = self.h1.wrapping_add(c as u32).wrapping_sub(self.window[self.n] as u32);
+        self.window[self.n] = c;
+        self.n = (self.n + 1) % ROLLING_WINDOW;
+        self.h3 = (self.h3 << 5) ^ c as u32;
+    }
+
+    fn sum(&self) -> u32 {
+        self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+    }
+}
+
+/// Digest for one block size. `digest[dlen]` holds the piece in progress
+/// once the digest is full.
+#[derive(Clone, Copy)]
+struct BlockHash {
+    h: u32,
+    half_h: u32,
+    digest: [u8; SPAMSUM_LENGTH],
+    half_digest: u8,
+    dlen: usize,
+}
+
+impl Default for BlockHash {
+    fn default() -> Self {
+        Self { h: HASH_INIT, half_h: HASH_INIT, digest: [0; SPAMSUM_LENGTH], half_digest: 0, dlen: 0 }
+    }
+}
+
+/// Running ssdeep (context triggered piecewise) hash. The total size must be
+/// known up front, as ssdeep picks the block sizes it keeps from it.
+pub struct Ssdeep {
+    roll: Roll,
+    blocks: [BlockHash; NUM_BLOCKHASHES],
+    start: usize,
+    end: usize,
+    total_size: u64,
+}
+
+impl Ssdeep {
+    pub fn new(total_size: usize) -> Self {
+        Self {
+            roll: Roll::default(),
+            blocks: [BlockHash::default(); NUM_BLOCKHASHES],
+            start: 0,
+            end: 1,
+            total_size: total_size as u64,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        data.iter().for_each(|&c| self.step(c));
+    }
+
+    fn step(&mut self, c: u8) {
+        self.roll.update(c);
+        let h = self.roll.sum() as u64;
+        for block in &mut self.blocks[self.start..self.end] {
+            block.h = sum_hash(c, block.h);
+            block.half_h = sum_hash(c, block.half_h);
+        }
+        // A fork extends the range, and the new block size is checked in this step too
+        let mut i = self.start;
+        while i < self.end {
+            if h % block_size(i) != block_size(i) - 1 {
+                break;
+            }
+            if self.blocks[i].dlen == 0 {
+                self.fork();
+            }
+            let block = &mut self.blocks[i];
+            block.digest[block.dlen] = B64[(block.h % 64) as usize];
+            block.half_digest = B64[(block.half_h % 64) as usize];
+            if block.dlen < SPAMSUM_LENGTH - 1 {
+                block.dlen += 1;
+                block.digest[block.dlen] = 0;
+                block.h = HASH_INIT;
+                if block.dlen < SPAMSUM_LENGTH / 2 {
+                    block.half_h = HASH_INIT;
+                    block.half_digest = 0;
+                }
+            } else {
+                self.reduce();
+            }
+            i += 1;
+        }
+    }
+
+    /// Starts the next block size, continuing from the current largest.
+    fn fork(&mut self) {
+        if self.end >= NUM_BLOCKHASHES {
+            return;
+        }
+        let last = self.blocks[self.end - 1];
+        self.blocks[self.end] = BlockHash { h: last.h, half_h: last.half_h, ..BlockHash::default() };
+        self.end += 1;
+    }
+
+    /// Drops the smallest block size once it can no longer be the one reported.
+    fn reduce(&mut self) {
+        if self.end - self.start < 2
+            || block_size(self.start) * SPAMSUM_LENGTH as u64 >= self.total_size
+            || self.blocks[self.start + 1].dlen < SPAMSUM_LENGTH / 2
+        {
+            return;
+        }
+        self.start += 1;
+    }
+
+    /// The digest, `blocksize:hash:hash`.
+    pub fn finish(&self) -> Result<String, String> {
+        let h = self.roll.sum();
+        let mut bi = self.start;
+        while block_size(bi) * (SPAMSUM_LENGTH as u64) < self.total_size {
+            bi += 1;
+            if bi >= NUM_BLOCKHASHES {
+                return Err("Data is too large for ssdeep".to_string());
+            }
+        }
+        bi = bi.min(self.end - 1);
+        while bi > self.start && self.blocks[bi].dlen < SPAMSUM_LENGTH / 2 {
+            bi -= 1;
+        }
+
+        let block = &self.blocks[bi];
+        let mut first = block.digest[..block.dlen].to_vec();
+        if h != 0 {
+            first.push(B64[(block.h % 64) as usize]);
+        } else if block.digest[block.dlen] != 0 {
+            first.push(block.digest[block.dlen]);
+        }
+
+        let mut second = Vec::new();
+        if bi < self.end - 1 {
+            let block = &self.blocks[bi + 1];
+            let len = block.dlen.min(SPAMSUM_LENGTH / 2 - 1);
+            second.extend_from_slice(&block.digest[..len]);
+            if h != 0 {
+                second.push(B64[(block.half_h % 64) as usize]);
+            } else if block.half_digest != 0 {
+                second.push(block.half_digest);
+            }
+        } else if h != 0 {
+            second.push(B64[(self.blocks[bi].h % 64) as usize]);
+        }
+
+        Ok(format!(
+            "{}:{}:{}",
+            block_size(bi),
+            String::from_utf8_lossy(&first),
+            String::from_utf8_lossy(&second)
+        ))
+    }
+}
+
+/// Splits `blocksize:hash:hash`, ignoring a trailing `,"filename"` as
+/// written by the ssdeep tool.
+fn parse(digest: &str) -> Result<(u64, &[u8], &[u8]), String> {
+    let invalid = || format!("Invalid ssdeep digest '{}'", digest);
+    let mut parts = digest.trim().splitn(3, ':');
+    let (Some(size), Some(first), Some(second)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid());
+    };
+    let size = size.parse::<u64>().map_err(|_| invalid())?;
+    let second = second.split(',').next().unwrap_or_default();
+    if first.len() > SPAMSUM_LENGTH || second.len() > SPAMSUM_LENGTH {
+        return Err(invalid());
+    }
+    Ok((size, first.as_bytes(), second.as_bytes()))
+}
+
+/// Shortens runs of a repeated character to three; they carry little information.
+fn eliminate_sequences(s: &[u8]) -> Vec<u8> {
+    s.iter()
+        .enumerate()
+        .filter(|&(i, &c)| i < 3 || c != s[i - 1] || c != s[i - 2] || c != s[i - 3])
+        .map(|(_, &c)| c)
+        .collect()
+}
+
+/// Edit distance with insertions and deletions costing 1, substitutions 2.
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, &ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let replace = previous[j] + if ca == cb { 0 } else { 2 };
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn score_strings(a: &[u8], b: &[u8], block_size: u64) -> u32 {
+    if a.len() > SPAMSUM_LENGTH || b.len() > SPAMSUM_LENGTH {
+        return 0;
+    }
+    // Unrelated unless the digests share a run as long as the rolling window
+    let common = a.windows(ROLLING_WINDOW).any(|w| b.windows(ROLLING_WINDOW).any(|v| v == w));
+    if !common {
+        return 0;
+    }
+    let distance = edit_distance(a, b) * SPAMSUM_LENGTH / (a.len() + b.len());
+    let distance = 100 * distance / SPAMSUM_LENGTH;
+    if distance >= 100 {
+        return 0;
+    }
+    let score = (100 - distance) as u64;
+    // Small block sizes would exaggerate how much data matched
+    let cap_from = (99 + ROLLING_WINDOW as u64) / ROLLING_WINDOW as u64 * MIN_BLOCKSIZE;
+    if block_size >= cap_from {
+        return score as u32;
+    }
+    score.min(block_size / MIN_BLOCKSIZE * a.len().min(b.len()) as u64) as u32
+}
+
+/// Match score of two digests, from 0 (unrelated) to 100.
+pub fn compare(a: &str, b: &str) -> Result<u32, String> {
+    let (size_a, a1, a2) = parse(a)?;
+    let (size_b, b1, b2) = parse(b)?;
+    // Block sizes come from the digests, so doubling one may overflow
+    let double = |size: u64| size.checked_mul(2);
+    if size_a != size_b && double(size_b) != Some(size_a) && double(size_a) != Some(size_b) {
+        return Ok(0);
+    }
+    let (a1, a2) = (eliminate_sequences(a1), eliminate_sequences(a2));
+    let (b1, b2) = (eliminate_sequences(b1), eliminate_sequences(b2));
+    if size_a == size_b && a1 == b1 {
+        return Ok(100);
+    }
+    Ok(if size_a == size_b {
+        score_strings(&a1, &b1, size_a).max(score_strings(&a2, &b2, size_a.saturating_mul(2)))
+    } else if double(size_a) == Some(size_b) {
+        score_strings(&b1, &a2, size_b)
+    } else {
+        score_strings(&a1, &b2, size_a)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Expected digests and scores come from the reference ssdeep 2.14.1
+    // library (fuzzy_hash_buf, fuzzy_compare) run on the same bytes.
+
+    /// Deterministic bytes that look random (a 64-bit LCG's high byte).
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    const SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+    fn digest(data: &[u8]) -> String {
+        let mut hasher = Ssdeep::new(data.len());
+        // Odd chunks, so pieces straddle update calls
+        data.chunks(997).for_each(|chunk| hasher.update(chunk));
+        hasher.finish().unwrap()
+    }
+
+    /// The noise, with 400 bytes zeroed; with its tail reversed; followed by its first half.
+    fn variants() -> [Vec<u8>; 3] {
+        let a = noise(20000, SEED);
+        let mut zeroed = a.clone();
+        zeroed[5000..5400].fill(0);
+        let mut reversed = a.clone();
+        reversed[12000..].reverse();
+        let mut extended = a.clone();
+        extended.extend_from_slice(&a[..20000]);
+        [zeroed, reversed, extended]
+    }
+
+    #[test]
+    fn digests_match_ssdeep() {
+        assert_eq!(digest(b""), "3::");
+        assert_eq!(
+            digest(b"some data to hash for the purposes of running a test"),
+            "3:HEREar5MFUul0U6R9n:knl8lqH"
+        );
+        assert_eq!(
+            digest(&noise(20000, SEED)),
+            "384:3DGbkpfvlY9PqMHCq2tol7saQaVx+Vbo6oFX+mLr51:3DGbkpfvlYRqMHCq2tuIAx+Ov9J"
+        );
+        assert_eq!(
+            digest(&noise(20000, 1)),
+            "384:+Ur/r5vber5MZVOFPnzJ5lNikMk3Hd5CbXxsayGcFM5tcvKwaIBNs:rS1JbNikMkt5Qxhx5ytFBNs"
+        );
+        assert_eq!(
+            digest(&noise(300000, SEED)),
+            "6144:SvnNcbY9sioN6WeDLxGEm9P3gNCUFT/LDEc/zwZcC/2:SvKbYELytGqNCafzcZ5/2"
+        );
+        let [zeroed, reversed, extended] = variants();
+        assert_eq!(
+            digest(&zeroed),
+            "384:3DGbkpfvlY9PqMHIq2tol7saQaVx+Vbo6oFX+mLr51:3DGbkpfvlYRqMHIq2tuIAx+Ov9J"
+        );
+        assert_eq!(
+            digest(&reversed),
+            "384:3DGbkpfvlY9PqMHCq2tol7sa4nxHSxaX+3krIJ9Lam:3DGbkpfvlYRqMHCq2tuIHxU4+3krIfam"
+        );
+        assert_eq!(
+            digest(&extended),
+            "768:3DGbkpfvlYRqMHCq2tuIAx+Ov9lDGbkpfvlYRqMHCq2tuIAx+Ov9J:3ibyYB3V+w3ibyYB3V+wn"
+        );
+    }
+
+    #[test]
+    fn scores_match_ssdeep() {
+        let a = digest(&noise(20000, SEED));
+        let [zeroed, reversed, extended] = variants().map(|data| digest(&data));
+        assert_eq!(compare(&a, &a), Ok(100));
+        assert_eq!(compare(&a, &zeroed), Ok(99));
+        assert_eq!(compare(&a, &reversed), Ok(75));
+        // Block sizes 384 and 768 compare through the second part of the first
+        assert_eq!(compare(&a, &extended), Ok(69));
+        assert_eq!(compare(&extended, &a), Ok(69));
+        assert_eq!(compare(&a, &digest(&noise(20000, 1))), Ok(0));
+        assert_eq!(
+            compare(
+                "96:U57GjXnLt9co6pZwvLhJluvrszNgMFwO6MFG8SvkpjTWf:Hj3BeoEcNJ0TspgIG8SvkpjTg",
+                "96:U57GjXnLt9co6pZwvLhJluvrs1eRTxYARdEallia:Hj3BeoEcNJ0TsI9xYeia3R"
+            ),
+            Ok(63)
+        );
+    }
+
+    #[test]
+    fn compare_accepts_tool_output_and_rejects_garbage() {
+        let a = digest(&noise(20000, SEED));
+        assert_eq!(compare(&format!("{},\"a.bin\"", a), &a), Ok(100));
+        assert!(compare("not a digest", &a).is_err());
+        assert!(compare("x:abc:def", &a).is_err());
+    }
+
+    #[test]
+    fn huge_block_sizes_dont_overflow() {
+        let max = u64::MAX;
+        assert_eq!(compare(&format!("{}:a:b", max), "3:abc:def"), Ok(0));
+        assert_eq!(compare("3:abc:def", &format!("{}:a:b", max)), Ok(0));
+        assert_eq!(compare(&format!("{}:abcdefgh:ijkl", max), &format!("{}:abcdefgh:ijkl", max)), Ok(100));
+        assert!(compare(&format!("{}:abcdefgh:ijkl", max), &format!("{}:abcdefgx:ijkl", max)).is_ok());
+    }
+
+    #[test]
+    fn helpers() {
+        assert_eq!(eliminate_sequences(b"aaaaabbbbc"), b"aaabbbc");
+        assert_eq!(edit_distance(b"", b"abc"), 3);
+        assert_eq!(edit_distance(b"abc", b"abd"), 2);
+        assert_eq!(edit_distance(b"abc", b"abxc"), 1);
+    }
+}
@@ -10,14 +10,162 @@ pub struct BinarySegment {
     pub label: Option<String>,
 }
 
+/// Values accepted for `Bookmark::kind`.
+pub const BOOKMARK_KINDS: [&str; 4] = ["code", "data", "string", "struct"];
+
+/// A named position or range of the buffer.
+#[derive(Clone, Debug, Default)]
+pub struct Bookmark {
+    pub offset: usize,
+    /// Number of bytes covered; 0 marks a single position
+    pub length: usize,
+    /// One of `BOOKMARK_KINDS`
+    pub kind: Option<String>,
+    pub color: Option<String>,
+    pub comment: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl Bookmark {
+    /// Whether the bookmark covers `offset`; a position covers only itself.
+    pub fn covers(&self, offset: usize) -> bool {
+        offset >= self.offset && offset - self.offset < self.length.max(1)
+    }
+
+    /// One line summary, e.g. `0x00000040-0x00000080 pe_header [struct] #blue {pe} // DOS stub ends here`.
+    pub fn describe(&self, name: &str) -> String {
+        let mut line = if self.length > 0 {
+            format!("0x{:08X}-0x{:08X} {}", self.offset, self.offset.saturating_add(self.length), name)
+        } else {
+            format!("0x{:08X} {}", self.offset, name)
+        };
+        if let Some(kind) = &self.kind {
+            line.push_str(&format!(" [{}]", kind));
+        }
+        if let Some(color) = &self.color {
+            line.push_str(&format!(" #{}", color.trim_start_matches('#')));
+        }
+        if !self.tags.is_empty() {
+            line.push_str(&format!(" {{{}}}", self.tags.join(", ")));
+        }
+        if let Some(comment) = &self.comment {
+            line.push_str(&format!(" // {}", comment));
+        }
+        line
+    }
+}
+
+/// An analysis note, optionally anchored to an offset, range or bookmark.
+#[derive(Clone, Debug, Default)]
+pub struct Note {
+    /// Stable id, never reused
+    pub id: u64,
+    pub text: String,
+    /// Offset the note describes, if anchored
+    pub offset: Option<usize>,
+    /// Number of bytes covered from `offset`; 0 marks a single position
+    pub length: usize,
+    /// Bookmark the note was attached to
+    pub bookmark: Option<String>,
+    pub tags: Vec<String>,
+    /// Session that wrote the note
+    pub author: String,
+    /// Unix time in seconds
+    pub created: u64,
+    pub updated: Option<u64>,
+}
+
+impl Note {
+    /// Whether the note's anchor overlaps `start..end`; unanchored notes never do.
+    pub fn overlaps(&self, start: usize, end: usize) -> bool {
+        self.offset.is_some_and(|offset| {
+            offset < end.max(start.saturating_add(1)) && start < offset.saturating_add(self.length.max(1))
+        })
+    }
+
+    /// One line summary, e.g. `#3 0x00000040 (pe_header) {todo} by default at 2024-05-01T12:00:00Z: text`.
+    pub fn describe(&self) -> String {
+        let mut line = format!("#{}", self.id);
+        match (self.offset, self.length) {
+            (Some(offset), 0) => line.push_str(&format!(" 0x{:08X}", offset)),
+            (Some(offset), length) => {
+                line.push_str(&format!(" 0x{:08X}-0x{:08X}", offset, offset.saturating_add(length)))
+            }
+            (None, _) => {}
+        }
+        if let Some(bookmark) = &self.bookmark {
+            line.push_str(&format!(" ({})", bookmark));
+        }
+        if !self.tags.is_empty() {
+            line.push_str(&format!(" {{{}}}", self.tags.join(", ")));
+        }
+        line.push_str(&format!(" by {} at {}", self.author, format_timestamp(self.created)));
+        if let Some(updated) = self.updated {
+            line.push_str(&format!(", edited {}", format_timestamp(updated)));
+        }
+        line.push_str(&format!(": {}", self.text));
+        line
+    }
+}
+
+/// Current Unix time in seconds.
+pub fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Formats Unix seconds as an RFC 3339 UTC timestamp.
+pub fn format_timestamp(secs: u64) -> String {
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let time = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, time / 3_600, time / 60 % 60, time % 60
+    )
+}
+
+/// A chunked `load_bytes` upload that hasn't been finalized.
+#[derive(Clone, Debug, Default)]
+pub struct Upload {
+    pub data: Vec<u8>,
+    /// Unix time of the last chunk received
+    pub updated: u64,
+}
+
+/// A part of the state that was modified by a tool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StateChange {
+    Buffer,
+    Bookmarks,
+    Segments,
+    Notes,
+    Output,
+}
+
 #[derive(Clone, Debug)]
 pub struct ServerState {
     pub buffer: Vec<u8>,
     pub file_loaded: Option<String>,
-    pub bookmarks: HashMap<String, usize>,
+    pub bookmarks: HashMap<String, Bookmark>,
     pub segments: Vec<BinarySegment>,
-    pub analysis_notes: Vec<String>,
+    pub analysis_notes: Vec<Note>,
+    next_note_id: u64,
     pub output: String,
+    /// Chunked uploads of `load_bytes` that haven't been finalized, by upload id
+    pub uploads: HashMap<String, Upload>,
+    changes: Vec<StateChange>,
 }
 
 impl ServerState {
@@ -28,36 +176,234 @@ impl ServerState {
             bookmarks: HashMap::new(),
             segments: Vec::new(),
             analysis_notes: Vec::new(),
+            next_note_id: 1,
             output: String::new(),
+            uploads: HashMap::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Records that part of the state was modified; the executor takes these
+    /// when a tool call finishes, to record and publish them.
+    pub fn mark_changed(&mut self, change: StateChange) {
+        if !self.changes.contains(&change) {
+            self.changes.push(change);
+        }
+    }
+
+    /// Replaces the buffer with newly loaded data, discarding the bookmarks and
+    /// segments that referred to the old one. Notes are kept, but detached
+    /// from their offsets and bookmarks.
+    pub fn replace_buffer(&mut self, data: Vec<u8>, name: String) {
+        self.buffer = data;
+        self.file_loaded = Some(name);
+        self.bookmarks.clear();
+        self.segments.clear();
+        self.mark_changed(StateChange::Buffer);
+        self.mark_changed(StateChange::Bookmarks);
+        self.mark_changed(StateChange::Segments);
+        
+        let mut detached = false;
+        for note in &mut self.analysis_notes {
+            if note.offset.is_some() || note.bookmark.is_some() {
+                (note.offset, note.length, note.bookmark) = (None, 0, None);
+                detached = true;
+            }
+        }
+        if detached {
+            self.mark_changed(StateChange::Notes);
+        }
+    }
+
+    /// Points the notes attached to bookmark `from` at `to`, or detaches them
+    /// from it (keeping their range) when `to` is `None`. Returns how many
+    /// notes changed.
+    pub fn retarget_notes(&mut self, from: &str, to: Option<&str>) -> usize {
+        let mut changed = 0;
+        for note in &mut self.analysis_notes {
+            if note.bookmark.as_deref() == Some(from) {
+                note.bookmark = to.map(str::to_string);
+                changed += 1;
+            }
+        }
+        if changed > 0 {
+            self.mark_changed(StateChange::Notes);
         }
+        changed
     }
 
+    /// Fits bookmarks, anchored notes and segments to a buffer shrunk to `len`
+    /// bytes: ranges running past the end are clipped, and those starting
+    /// past it (or at it, for ranges) are dropped. Returns a line per change.
+    pub fn clip_to_buffer(&mut self, len: usize) -> Vec<String> {
+        let past_end = |offset: usize, length: usize| offset > len || (length > 0 && offset >= len);
+        let mut lines = Vec::new();
+
+        let mut names: Vec<String> = self.bookmarks.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            let bookmark = &self.bookmarks[&name];
+            if past_end(bookmark.offset, bookmark.length) {
+                lines.push(format!("dropped bookmark '{}' at 0x{:08X}", name, bookmark.offset));
+                self.bookmarks.remove(&name);
+            } else if bookmark.offset.saturating_add(bookmark.length) > len {
+                let bookmark = self.bookmarks.get_mut(&name).expect("bookmark exists");
+                bookmark.length = len - bookmark.offset;
+                lines.push(format!("clipped bookmark '{}' to {} bytes", name, bookmark.length));
+            } else {
+                continue;
+            }
+            self.mark_changed(StateChange::Bookmarks);
+        }
+
+        let before = self.analysis_notes.len();
+        self.analysis_notes.retain(|note| match note.offset {
+            Some(offset) if past_end(offset, note.length) => {
+                lines.push(format!("dropped note #{} at 0x{:08X}", note.id, offset));
+                false
+            }
+            _ => true,
+        });
+        let mut notes_changed = self.analysis_notes.len() != before;
+        for note in &mut self.analysis_notes {
+            if let Some(offset) = note.offset.filter(|&o| o.saturating_add(note.length) > len) {
+                note.length = len - offset;
+                lines.push(format!("clipped note #{} to {} bytes", note.id, note.length));
+                notes_changed = true;
+            }
+        }
+        if notes_changed {
+            self.mark_changed(StateChange::Notes);
+        }
+
+        let mut index = 0;
+        let mut segments_changed = false;
+        self.segments.retain_mut(|seg| {
+            let name = seg.label.as_ref().map_or(format!("#{}", index), |l| format!("'{}'", l));
+            index += 1;
+            let offset = usize::try_from(seg.offset).unwrap_or(usize::MAX);
+            if past_end(offset, seg.data.len()) {
+                lines.push(format!("dropped segment {} at 0x{:08X}", name, seg.offset));
+                segments_changed = true;
+                return false;
+            }
+            if offset.saturating_add(seg.data.len()) > len {
+                seg.data.truncate(len - offset);
+                lines.push(format!("clipped segment {} to {} bytes", name, seg.data.len()));
+                segments_changed = true;
+            }
+            true
+        });
+        if segments_changed {
+            self.mark_changed(StateChange::Segments);
+        }
+        lines
+    }
+
+    /// Resolves the `segment` argument of the read tools: `None` means the
+    /// buffer, a number is an index, anything else a label.
+    pub fn resolve_segment(&self, segment: Option<&str>) -> Result<Option<usize>, String> {
+        let Some(key) = segment else {
+            return Ok(None);
+        };
+        match key.parse::<usize>() {
+            Ok(index) if index < self.segments.len() => Ok(Some(index)),
+            Ok(_) => Err(format!("No segment numbered {} ({} segment(s))", key, self.segments.len())),
+            Err(_) => self.segments
+                .iter()
+                .position(|seg| seg.label.as_deref() == Some(key))
+                .map(Some)
+                .ok_or_else(|| format!("No segment labelled '{}'", key)),
+        }
+    }
+
+    /// Checks a label for a new or relabelled segment (`except` is its own
+    /// index). Labels are unique and never a plain number, which would read
+    /// as an index.
+    pub fn check_segment_label(&self, label: &str, except: Option<usize>) -> Result<(), String> {
+        if label.parse::<usize>().is_ok() {
+            return Err(format!("Segment label '{}' would be taken for an index", label));
+        }
+        if self.segments.iter().enumerate().any(|(i, seg)| Some(i) != except && seg.label.as_deref() == Some(label)) {
+            return Err(format!("Another segment is already labelled '{}'", label));
+        }
+        Ok(())
+    }
+
+    /// The buffer, or the bytes a segment captured when it was extracted. A
+    /// segment keeps its snapshot even if the buffer changes afterwards.
+    pub fn source(&self, segment: Option<usize>) -> &[u8] {
+        match segment {
+            Some(index) => &self.segments[index].data,
+            None => &self.buffer,
+        }
+    }
+
+    /// Bookmarks ordered by offset, then name.
+    pub fn sorted_bookmarks(&self) -> Vec<(&String, &Bookmark)> {
+        let mut bookmarks: Vec<_> = self.bookmarks.iter().collect();
+        bookmarks.sort_by_key(|(name, b)| (b.offset, name.as_str()));
+        bookmarks
+    }
+
+    /// Stores a note, assigning its id and creation time.
+    pub fn add_note(&mut self, mut note: Note) -> u64 {
+        note.id = self.next_note_id;
+        note.created = unix_time();
+        self.next_note_id += 1;
+        self.analysis_notes.push(note);
+        self.mark_changed(StateChange::Notes);
+        self.next_note_id - 1
+    }
+
+    /// Uses up a note id without storing a note, for a replay that skips the
+    /// call which added it.
+    pub fn skip_note_id(&mut self) {
+        self.next_note_id += 1;
+    }
+
+    pub fn take_changes(&mut self) -> Vec<StateChange> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Name used for the active buffer in resource URIs: the loaded file's
+    /// base name, or "active" when nothing has been loaded.
+    pub fn buffer_name(&self) -> String {
+        self.file_loaded
+            .as_deref()
+            .and_then(|path| std::path::Path::new(path).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "active".to_string())
+    }
+
+    /// Pretty dump of the whole state, written to stderr so it never mixes
+    /// with protocol traffic. Only used with `--debug-state`.
     pub fn display(&self) {
-        println!("\n{}", "=".repeat(70));
-        println!("🔬 BINARY ANALYSIS SERVER STATE");
-        println!("{}", "=".repeat(70));
+        eprintln!("\n{}", "=".repeat(70));
+        eprintln!("🔬 BINARY ANALYSIS SERVER STATE");
+        eprintln!("{}", "=".repeat(70));
         
-        println!("\n📂 Loaded File: {}", 
+        eprintln!("\n📂 Loaded File: {}", 
             self.file_loaded.as_deref().unwrap_or("None"));
         
-        println!("\n📊 Buffer: {} bytes", self.buffer.len());
+        eprintln!("\n📊 Buffer: {} bytes", self.buffer.len());
         if !self.buffer.is_empty() {
             let preview_len = self.buffer.len().min(64);
-            println!("  First {} bytes (hex):", preview_len);
-            println!("  {}", hex::encode(&self.buffer[..preview_len]));
+            eprintln!("  First {} bytes (hex):", preview_len);
+            eprintln!("  {}", hex::encode(&self.buffer[..preview_len]));
             if self.buffer.len() > 64 {
-                println!("  ... ({} more bytes)", self.buffer.len() - 64);
+                eprintln!("  ... ({} more bytes)", self.buffer.len() - 64);
             }
         }
         
-        println!("\n🔖 Bookmarks: {}", self.bookmarks.len());
-        for (name, offset) in &self.bookmarks {
-            println!("  {} -> 0x{:08X}", name, offset);
+        eprintln!("\n🔖 Bookmarks: {}", self.bookmarks.len());
+        for (name, bookmark) in self.sorted_bookmarks() {
+            eprintln!("  {}", bookmark.describe(name));
         }
         
-        println!("\n📦 Segments: {}", self.segments.len());
+        eprintln!("\n📦 Segments: {}", self.segments.len());
         for (i, seg) in self.segments.iter().enumerate() {
-            println!("  [{}] 0x{:08X}: {} bytes{}", 
+            eprintln!("  [{}] 0x{:08X}: {} bytes{}", 
                 i, 
                 seg.offset, 
                 seg.data.len(),
@@ -65,23 +411,86 @@ impl ServerState {
             );
         }
         
-        println!("\n📝 Analysis Notes: {}", self.analysis_notes.len());
-        for (i, note) in self.analysis_notes.iter().enumerate() {
-            let preview = if note.len() > 60 {
-                format!("{}...", &note[..60])
+        eprintln!("\n📝 Analysis Notes: {}", self.analysis_notes.len());
+        for note in &self.analysis_notes {
+            let preview = if note.text.chars().count() > 60 {
+                format!("{}...", note.text.chars().take(60).collect::<String>())
             } else {
-                note.clone()
+                note.text.clone()
             };
-            println!("  [{}] {}", i, preview);
+            eprintln!("  [#{}] {}", note.id, preview);
         }
         
-        println!("\n📤 Output:");
+        eprintln!("\n📤 Output:");
         if self.output.is_empty() {
-            println!("  [Empty]");
+            eprintln!("  [Empty]");
         } else {
-            println!("  {}", self.output);
+            eprintln!("  {}", self.output);
         }
         
-        println!("\n{}", "=".repeat(70));
+        eprintln!("\n{}", "=".repeat(70));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(label: Option<&str>) -> BinarySegment {
+        BinarySegment { offset: 0, data: vec![0; 4], label: label.map(str::to_string) }
+    }
+
+    #[test]
+    fn numbers_select_segments_by_index() {
+        let mut s = ServerState::new();
+        s.segments = vec![segment(Some("header")), segment(None)];
+        assert_eq!(s.resolve_segment(None), Ok(None));
+        assert_eq!(s.resolve_segment(Some("1")), Ok(Some(1)));
+        assert_eq!(s.resolve_segment(Some("header")), Ok(Some(0)));
+        assert!(s.resolve_segment(Some("2")).is_err());
+        assert!(s.resolve_segment(Some("payload")).is_err());
+    }
+
+    #[test]
+    fn segment_labels_are_unique_and_not_numbers() {
+        let mut s = ServerState::new();
+        s.segments = vec![segment(Some("header")), segment(None)];
+        assert!(s.check_segment_label("1", None).is_err());
+        assert!(s.check_segment_label("header", None).is_err());
+        assert!(s.check_segment_label("header", Some(1)).is_err());
+        assert!(s.check_segment_label("header", Some(0)).is_ok());
+        assert!(s.check_segment_label("payload", None).is_ok());
+    }
+
+    #[test]
+    fn bookmarks_cover_their_range() {
+        let position = Bookmark { offset: 0x10, ..Bookmark::default() };
+        assert!(position.covers(0x10));
+        assert!(!position.covers(0x0F) && !position.covers(0x11));
+
+        let range = Bookmark { offset: 0x10, length: 4, ..Bookmark::default() };
+        assert_eq!((0x0E..0x16).filter(|&o| range.covers(o)).collect::<Vec<_>>(), [0x10, 0x11, 0x12, 0x13]);
+
+        let last = Bookmark { offset: usize::MAX - 1, length: 8, ..Bookmark::default() };
+        assert!(last.covers(usize::MAX) && !last.covers(0));
+    }
+
+    #[test]
+    fn bookmarks_describe_themselves() {
+        let bookmark = Bookmark {
+            offset: 0x40,
+            length: 0x40,
+            kind: Some("struct".to_string()),
+            color: Some("#blue".to_string()),
+            comment: Some("DOS stub ends here".to_string()),
+            tags: vec!["pe".to_string(), "header".to_string()],
+        };
+        assert_eq!(
+            bookmark.describe("pe_header"),
+            "0x00000040-0x00000080 pe_header [struct] #blue {pe, header} // DOS stub ends here"
+        );
+        assert_eq!(Bookmark { offset: 8, ..Bookmark::default() }.describe("entry"), "0x00000008 entry");
+        let last = Bookmark { offset: usize::MAX, length: 2, ..Bookmark::default() };
+        assert!(last.describe("end").ends_with(&format!("-0x{:08X} end", usize::MAX)));
     }
 }
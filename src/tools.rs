@@ -1,21 +1,98 @@
 // ============================================================================
 // src/tools.rs
 // ============================================================================
-use rust_mcp_sdk::schema::{schema_utils::CallToolError, CallToolResult, TextContent};
+use rust_mcp_sdk::schema::{
+    schema_utils::CallToolError, CallToolResult, ContentBlock, TextContent, Tool, ToolOutputSchema,
+};
 use rust_mcp_sdk::macros::{mcp_tool, JsonSchema};
 use rust_mcp_sdk::tool_box;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::fs;
 use sha2::{Sha256, Digest};
-use crate::state::ServerState;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use crate::diff;
+use crate::entropy;
+use crate::groups::{ToolGroup, ToolGroups};
+use crate::hashing::{self, Algorithm};
+use crate::history::History;
+use crate::jobs::{JobContext, PROGRESS_STEP};
+use crate::patch::{self, PatchFormat};
+use crate::resources;
+use crate::sampling::{self, SamplingRequest, MAX_SAMPLED_BYTES};
+use crate::state::{unix_time, Bookmark, Note, ServerState, StateChange, BOOKMARK_KINDS};
+
+/// Builds an output schema from a `JsonSchema` derived struct's schema.
+fn output_schema(json_schema: serde_json::Map<String, serde_json::Value>) -> ToolOutputSchema {
+    let required = json_schema
+        .get("required")
+        .and_then(|r| r.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    let properties = json_schema
+        .get("properties")
+        .and_then(|p| p.as_object())
+        .map(|props| {
+            props
+                .iter()
+                .filter_map(|(k, v)| v.as_object().map(|obj| (k.clone(), obj.clone())))
+                .collect()
+        });
+    ToolOutputSchema::new(required, properties)
+}
+
+/// Returns the text for humans alongside the same data as `structuredContent`.
+fn structured_result(text: String, output: &impl serde::Serialize)
+    -> Result<CallToolResult, CallToolError>
+{
+    let structured = match serde_json::to_value(output) {
+        Ok(serde_json::Value::Object(map)) => map,
+        Ok(_) => return Err(CallToolError::from_message("Structured output must be an object")),
+        Err(e) => return Err(CallToolError::from_message(format!("Failed to encode output: {}", e))),
+    };
+    Ok(CallToolResult::text_content(vec![TextContent::from(text)])
+        .with_structured_content(structured))
+}
+
+/// Describes the annotations that loading `new_name` would discard, if any.
+fn replacement_loss(s: &ServerState, new_name: &str) -> Option<String> {
+    let anchored = s.analysis_notes.iter()
+        .filter(|n| n.offset.is_some() || n.bookmark.is_some())
+        .count();
+    if s.bookmarks.is_empty() && s.segments.is_empty() && anchored == 0 {
+        return None;
+    }
+    Some(format!(
+        "Loading '{}' will discard {} bookmark(s) and {} segment(s) of '{}'{}.",
+        new_name,
+        s.bookmarks.len(),
+        s.segments.len(),
+        s.file_loaded.as_deref().unwrap_or("the current buffer"),
+        if anchored > 0 { format!(" and detach {} note(s) from their offsets", anchored) } else { String::new() }
+    ))
+}
+
+/// Where a read happened, for tool output: empty for the buffer.
+fn source_suffix(segment: &Option<String>) -> String {
+    segment.as_ref().map(|seg| format!(" in segment '{}'", seg)).unwrap_or_default()
+}
+
+/// Name for a buffer that didn't come from a file, e.g. `upload_3f2a9c1b`.
+fn synthetic_name(prefix: &str, data: &[u8]) -> String {
+    format!("{}_{}", prefix, &hex::encode(Sha256::digest(data))[..8])
+}
 
 //****************//
 //  LoadBinary    //
 //****************//
 #[mcp_tool(
     name = "load_binary",
-    description = "Loads a binary file into the buffer for analysis"
+    description = "Loads a binary file into the buffer for analysis",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = true,
+    open_world_hint = true
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
 pub struct LoadBinary {
@@ -24,21 +101,220 @@ pub struct LoadBinary {
 }
 
 impl LoadBinary {
+    /// Describes the annotations a load would discard, if any.
+    pub fn pending_loss(&self, s: &ServerState) -> Option<String> {
+        replacement_loss(s, &self.path)
+    }
+
     pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
         -> Result<CallToolResult, CallToolError> 
     {
         let data = fs::read(&self.path).await
             .map_err(|e| CallToolError::from_message(format!("Failed to read file: {}", e)))?;
         
+        let size = data.len();
+        state.write().await.replace_buffer(data, self.path.clone());
+        
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!("✅ Loaded {} bytes from '{}'", size, self.path))
+        ]))
+    }
+}
+
+//**************//
+//  LoadBytes   //
+//**************//
+#[mcp_tool(
+    name = "load_bytes",
+    description = "Loads base64 encoded bytes sent by the client into the buffer. Large files can be sent in chunks sharing an upload_id; the last call sets finalize=true and the optional sha256 is verified before loading. At most 4 unfinished uploads are kept",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = false,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct LoadBytes {
+    /// Base64 encoded bytes: the whole file, or one chunk of an upload (may be empty when finalizing)
+    pub data: String,
+    /// Name for the buffer (default: generated from the content hash)
+    pub name: Option<String>,
+    /// Identifier of a chunked upload; chunks with the same id are appended in order
+    pub upload_id: Option<String>,
+    /// Offset of this chunk in the file, checked against the bytes received so far
+    pub chunk_offset: Option<u64>,
+    /// Ends a chunked upload and loads the assembled bytes
+    pub finalize: Option<bool>,
+    /// Expected SHA-256 (hex) of the complete file; nothing is loaded on mismatch
+    pub sha256: Option<String>,
+}
+
+impl LoadBytes {
+    /// Largest upload kept in memory before it is finalized.
+    const MAX_UPLOAD_SIZE: usize = 512 * 1024 * 1024;
+    /// Unfinished uploads kept at once; a new one drops the one idle the longest.
+    const MAX_PENDING_UPLOADS: usize = 4;
+
+    /// Whether this call replaces the buffer rather than only storing a chunk.
+    pub fn loads(&self) -> bool {
+        self.upload_id.is_none() || self.finalize.unwrap_or(false)
+    }
+
+    pub fn pending_loss(&self, s: &ServerState) -> Option<String> {
+        if !self.loads() {
+            return None;
+        }
+        let name = self.name.as_deref().or(self.upload_id.as_deref()).unwrap_or("the uploaded bytes");
+        replacement_loss(s, name)
+    }
+
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let chunk = BASE64.decode(self.data.trim())
+            .map_err(|e| CallToolError::from_message(format!("Invalid base64 data: {}", e)))?;
+        
         let mut s = state.write().await;
-        s.buffer = data.clone();
-        s.file_loaded = Some(self.path.clone());
-        s.bookmarks.clear();
-        s.segments.clear();
-        s.display();
+        
+        let data = match &self.upload_id {
+            None => chunk,
+            Some(id) => {
+                let received = s.uploads.get(id).map_or(0, |upload| upload.data.len());
+                if let Some(offset) = self.chunk_offset {
+                    if offset != received as u64 {
+                        return Err(CallToolError::from_message(format!(
+                            "Chunk offset {} does not match the {} bytes received for upload '{}'",
+                            offset, received, id
+                        )));
+                    }
+                }
+                if received + chunk.len() > Self::MAX_UPLOAD_SIZE {
+                    s.uploads.remove(id);
+                    return Err(CallToolError::from_message(format!(
+                        "Upload '{}' exceeds {} bytes and was discarded", id, Self::MAX_UPLOAD_SIZE
+                    )));
+                }
+                
+                // Make room for a new upload by dropping the one idle the longest
+                let mut dropped = None;
+                if !s.uploads.contains_key(id) && s.uploads.len() >= Self::MAX_PENDING_UPLOADS {
+                    dropped = s.uploads.iter()
+                        .min_by_key(|(_, upload)| upload.updated)
+                        .map(|(stale, _)| stale.clone());
+                    if let Some(stale) = &dropped {
+                        s.uploads.remove(stale);
+                    }
+                }
+                let upload = s.uploads.entry(id.clone()).or_default();
+                upload.data.extend_from_slice(&chunk);
+                upload.updated = unix_time();
+                
+                if !self.loads() {
+                    return Ok(CallToolResult::text_content(vec![
+                        TextContent::from(format!(
+                            "📥 Received {} bytes for upload '{}' ({} bytes so far); call load_bytes with finalize=true to load it{}",
+                            chunk.len(), id, upload.data.len(),
+                            dropped.map(|stale| format!("\n🗑️ Dropped the idle upload '{}'", stale)).unwrap_or_default()
+                        ))
+                    ]));
+                }
+                s.uploads.remove(id).unwrap_or_default().data
+            }
+        };
+        
+        if let Some(expected) = &self.sha256 {
+            let actual = hex::encode(Sha256::digest(&data));
+            if !expected.trim().eq_ignore_ascii_case(&actual) {
+                return Err(CallToolError::from_message(format!(
+                    "SHA-256 mismatch: expected {}, got {}; nothing was loaded{}",
+                    expected.trim(),
+                    actual,
+                    if self.upload_id.is_some() { " and the upload was discarded" } else { "" }
+                )));
+            }
+        }
+        
+        let name = self.name.clone().unwrap_or_else(|| synthetic_name("upload", &data));
+        let size = data.len();
+        s.replace_buffer(data, name.clone());
+        
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!("✅ Loaded {} bytes as '{}'", size, name))
+        ]))
+    }
+}
+
+//************//
+//  LoadHex   //
+//************//
+#[mcp_tool(
+    name = "load_hex",
+    description = "Loads hex text into the buffer, e.g. a memory dump pasted from a debugger. Address columns ending in ':' or at least 6 digits long, 0x prefixes, separators and trailing ASCII columns are ignored",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct LoadHex {
+    /// Hex text, one or more lines
+    pub hex: String,
+    /// Name for the buffer (default: generated from the content hash)
+    pub name: Option<String>,
+}
+
+impl LoadHex {
+    pub fn pending_loss(&self, s: &ServerState) -> Option<String> {
+        replacement_loss(s, self.name.as_deref().unwrap_or("the pasted hex"))
+    }
+
+    /// Parses dump lines such as `00401000  4D 5A 90 00  MZ..`, `0000: 4d5a 9000`
+    /// or `0x4d, 0x5a`. On each line an address column is skipped and parsing
+    /// stops at the first token that isn't hex bytes (the ASCII column).
+    fn parse(text: &str) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.replace(['`', '-', ','], " ");
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let mut tokens = tokens.as_slice();
+            
+            if let [first, rest @ ..] = tokens {
+                let is_address = first.ends_with(':')
+                    || (!rest.is_empty() && first.len() >= 6 && first.chars().all(|c| c.is_ascii_hexdigit()));
+                if is_address {
+                    tokens = rest;
+                }
+            }
+            
+            let line_start = bytes.len();
+            for token in tokens {
+                let digits = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token);
+                if digits.is_empty() || digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    break;
+                }
+                bytes.extend(hex::decode(digits).map_err(|e| format!("line {}: {}", number + 1, e))?);
+            }
+            if bytes.len() == line_start && !tokens.is_empty() {
+                return Err(format!("line {}: no hex bytes found", number + 1));
+            }
+        }
+        Ok(bytes)
+    }
+
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let data = Self::parse(&self.hex)
+            .map_err(|e| CallToolError::from_message(format!("Invalid hex text: {}", e)))?;
+        if data.is_empty() {
+            return Err(CallToolError::from_message("Invalid hex text: no bytes found"));
+        }
+        
+        let name = self.name.clone().unwrap_or_else(|| synthetic_name("hex", &data));
+        let size = data.len();
+        state.write().await.replace_buffer(data, name.clone());
         
         Ok(CallToolResult::text_content(vec![
-            TextContent::from(format!("✅ Loaded {} bytes from '{}'", data.len(), self.path))
+            TextContent::from(format!("✅ Loaded {} bytes as '{}'", size, name))
         ]))
     }
 }
@@ -48,7 +324,9 @@ impl LoadBinary {
 //****************//
 #[mcp_tool(
     name = "read_bytes",
-    description = "Reads a specified number of bytes from the buffer at a given offset, returns hex dump"
+    description = "Reads a specified number of bytes from the buffer at a given offset, returns hex dump",
+    read_only_hint = true,
+    open_world_hint = false
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
 pub struct ReadBytes {
@@ -56,6 +334,24 @@ pub struct ReadBytes {
     pub offset: u64,
     /// Number of bytes to read
     pub length: u64,
+    /// Segment label or index to read instead of the buffer; offsets are then relative to the segment
+    pub segment: Option<String>,
+}
+
+/// Structured result of `read_bytes`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct ReadBytesOutput {
+    /// Offset of the first byte read
+    pub offset: u64,
+    /// Number of bytes read
+    pub length: u64,
+    /// Bytes as a lowercase hex string
+    pub hex: String,
+    /// Printable ASCII rendering, '.' for other bytes
+    pub ascii: String,
+    /// Segment read instead of the buffer, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment: Option<String>,
 }
 
 impl ReadBytes {
@@ -63,14 +359,15 @@ impl ReadBytes {
         -> Result<CallToolResult, CallToolError> 
     {
         let s = state.read().await;
+        let data = s.source(s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?);
         
-        if self.offset + self.length > s.buffer.len() as u64 {
+        if self.offset.checked_add(self.length).is_none_or(|end| end > data.len() as u64) {
             return Err(CallToolError::from_message("Read exceeds buffer bounds"));
         }
         
         let start = self.offset as usize;
         let end = start + self.length as usize;
-        let bytes = &s.buffer[start..end];
+        let bytes = &data[start..end];
         let hex_dump = hex::encode(bytes);
 
         let ascii: String = bytes.iter()
@@ -78,11 +375,189 @@ impl ReadBytes {
             .collect();
 
         let output = format!(
-            "Offset 0x{:08X} ({} bytes):\nHex: {}\nASCII: {}",
-            self.offset, self.length, hex_dump, ascii
+            "Offset 0x{:08X}{} ({} bytes):\nHex: {}\nASCII: {}",
+            self.offset, source_suffix(&self.segment), self.length, hex_dump, ascii
+        );
+        
+        structured_result(output, &ReadBytesOutput {
+            offset: self.offset,
+            length: self.length,
+            hex: hex_dump,
+            ascii,
+            segment: self.segment.clone(),
+        })
+    }
+}
+
+//**************//
+//  Hexdump     //
+//**************//
+#[mcp_tool(
+    name = "hexdump",
+    description = "Renders bytes in xxd / hexdump -C layout with configurable width, grouping, offset base and virtual addresses, interleaving bookmark, segment and note markers. Output is paginated",
+    read_only_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct Hexdump {
+    /// Starting offset (default 0)
+    pub offset: Option<u64>,
+    /// Number of bytes to dump (default 256)
+    pub length: Option<u64>,
+    /// Bytes per line, 1 to 64 (default 16)
+    pub width: Option<u64>,
+    /// Bytes per group, e.g. 2 for xxd style (default 1)
+    pub group: Option<u64>,
+    /// Offset column base: 'hex' or 'dec' (default 'hex')
+    pub base: Option<String>,
+    /// Address shown for the first byte of the buffer or segment, e.g. its load address; wraps past 2^64
+    pub base_address: Option<u64>,
+    /// Segment label or index to dump instead of the buffer; offsets are then relative to the segment
+    pub segment: Option<String>,
+    /// Show bookmark, segment and note markers (default true)
+    pub overlays: Option<bool>,
+    /// Page of the range to show, starting at 0; each page holds at most 4096 bytes
+    pub page: Option<u64>,
+}
+
+impl Hexdump {
+    const PAGE_BYTES: usize = 4096;
+    const MAX_WIDTH: usize = 64;
+
+    /// Marker lines for annotations starting in `line_start..line_end` (buffer
+    /// offsets), or covering the line when it is the first one shown.
+    fn overlay_lines(s: &ServerState, line_start: usize, line_end: usize, first: bool) -> Vec<(usize, String)> {
+        let starts_here = |offset: usize| offset >= line_start && offset < line_end;
+        let mut markers = Vec::new();
+        
+        for (name, b) in s.sorted_bookmarks() {
+            if starts_here(b.offset) {
+                markers.push((b.offset, format!("🔖 {}", b.describe(name))));
+            } else if first && b.covers(line_start) {
+                markers.push((line_start, format!("🔖 (continued) {}", b.describe(name))));
+            }
+        }
+        for (i, seg) in s.segments.iter().enumerate() {
+            let offset = seg.offset as usize;
+            if starts_here(offset) {
+                markers.push((offset, format!(
+                    "▣ segment {}{} ({} bytes)",
+                    i,
+                    seg.label.as_ref().map(|l| format!(" '{}'", l)).unwrap_or_default(),
+                    seg.data.len()
+                )));
+            }
+        }
+        for note in &s.analysis_notes {
+            if let Some(offset) = note.offset.filter(|&o| starts_here(o)) {
+                let text: String = note.text.chars().take(60).collect();
+                let ellipsis = if note.text.chars().count() > 60 { "..." } else { "" };
+                markers.push((offset, format!("✎ #{} {}{}", note.id, text, ellipsis)));
+            }
+        }
+        
+        markers.sort_by_key(|(offset, _)| *offset);
+        markers
+    }
+
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let s = state.read().await;
+        let source = s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?;
+        let data = s.source(source);
+        // Buffer offset of data[0], used to place overlays
+        let origin = source.map(|i| s.segments[i].offset as usize).unwrap_or(0);
+        
+        let width = self.width.unwrap_or(16) as usize;
+        if width == 0 || width > Self::MAX_WIDTH {
+            return Err(CallToolError::from_message(format!("Width must be between 1 and {}", Self::MAX_WIDTH)));
+        }
+        let group = (self.group.unwrap_or(1) as usize).clamp(1, width);
+        let decimal = match self.base.as_deref().unwrap_or("hex") {
+            "hex" => false,
+            "dec" => true,
+            other => return Err(CallToolError::from_message(format!("Unknown offset base '{}'; expected 'hex' or 'dec'", other))),
+        };
+        
+        let start = self.offset.unwrap_or(0) as usize;
+        let end = start
+            .checked_add(self.length.unwrap_or(256) as usize)
+            .map(|end| end.min(data.len()))
+            .filter(|_| start <= data.len())
+            .ok_or_else(|| CallToolError::from_message("Offset exceeds buffer size"))?;
+        
+        // Pages are whole lines so the layout stays aligned across pages
+        let page_bytes = (Self::PAGE_BYTES / width * width).max(width);
+        let pages = (end - start).div_ceil(page_bytes).max(1);
+        let page = self.page.unwrap_or(0) as usize;
+        if page >= pages {
+            return Err(CallToolError::from_message(format!("Page {} out of range; the dump has {} page(s)", page, pages)));
+        }
+        let page_start = start + page * page_bytes;
+        let page_end = (page_start + page_bytes).min(end);
+        
+        let base_address = self.base_address.unwrap_or(0);
+        let address_width = if decimal { 10 } else { 8 };
+        let format_address = |offset: usize| {
+            let address = base_address.wrapping_add(offset as u64);
+            if decimal { format!("{:010}", address) } else { format!("{:08x}", address) }
+        };
+        let overlays = self.overlays.unwrap_or(true);
+        
+        let mut lines = Vec::new();
+        for line_start in (page_start..page_end).step_by(width) {
+            let line_end = (line_start + width).min(page_end);
+            let bytes = &data[line_start..line_end];
+            
+            let mut hex_column = String::new();
+            for i in 0..width {
+                if i > 0 && i % group == 0 {
+                    hex_column.push(' ');
+                    // hexdump -C style gap in the middle of byte-wise lines
+                    if group == 1 && width >= 8 && width.is_multiple_of(2) && i == width / 2 {
+                        hex_column.push(' ');
+                    }
+                }
+                match bytes.get(i) {
+                    Some(b) => hex_column.push_str(&format!("{:02x}", b)),
+                    None => hex_column.push_str("  "),
+                }
+            }
+            let ascii: String = bytes.iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            lines.push(format!("{}  {}  |{}|", format_address(line_start), hex_column, ascii));
+            
+            if overlays {
+                let markers = Self::overlay_lines(
+                    &s, origin + line_start, origin + line_end, line_start == page_start
+                );
+                for (offset, text) in markers {
+                    lines.push(format!(
+                        "{:width$}  └─ +{:<3} {}",
+                        "",
+                        format!("{:x}", offset.saturating_sub(origin + line_start)),
+                        text,
+                        width = address_width
+                    ));
+                }
+            }
+        }
+        
+        let mut header = format!(
+            "Hexdump of 0x{:08X}-0x{:08X}{} ({} bytes)",
+            page_start, page_end, source_suffix(&self.segment), page_end - page_start
         );
+        if pages > 1 {
+            header.push_str(&format!(", page {} of {}", page + 1, pages));
+        }
+        let mut text = format!("{}:\n{}", header, lines.join("\n"));
+        if page + 1 < pages {
+            text.push_str(&format!("\n... call hexdump with page={} for the next {} bytes", page + 1, (end - page_end).min(page_bytes)));
+        }
         
-        Ok(CallToolResult::text_content(vec![TextContent::from(output)]))
+        Ok(CallToolResult::text_content(vec![TextContent::from(text)]))
     }
 }
 
@@ -91,35 +566,74 @@ impl ReadBytes {
 //*******************//
 #[mcp_tool(
     name = "search_pattern",
-    description = "Searches for a hex pattern in the buffer, returns all matching offsets"
+    description = "Searches for a hex pattern in the buffer, returns all matching offsets. The search can \
+                   be cancelled, but cancelling any request of the session stops every running search, \
+                   hash and entropy scan in it",
+    read_only_hint = true,
+    open_world_hint = false
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
 pub struct SearchPattern {
     /// Hex string pattern to search for (e.g., '4D5A' for PE header)
     pub pattern: String,
+    /// Segment label or index to search instead of the buffer; offsets are then relative to the segment
+    pub segment: Option<String>,
+    /// Optional token for notifications/progress while scanning
+    pub progress_token: Option<String>,
+}
+
+/// Structured result of `search_pattern`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct SearchPatternOutput {
+    /// The pattern searched for, as given
+    pub pattern: String,
+    /// Number of matches
+    pub count: u64,
+    /// Offsets of all matches in ascending order
+    pub offsets: Vec<u64>,
+    /// Segment searched instead of the buffer, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment: Option<String>,
 }
 
 impl SearchPattern {
-    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>, job: JobContext) 
         -> Result<CallToolResult, CallToolError> 
     {
-        let s = state.read().await;
-        
         let pattern = hex::decode(&self.pattern)
             .map_err(|e| CallToolError::from_message(format!("Invalid hex pattern: {}", e)))?;
         
-        let mut matches = Vec::new();
-        for i in 0..=s.buffer.len().saturating_sub(pattern.len()) {
-            if &s.buffer[i..i + pattern.len()] == pattern.as_slice() {
-                matches.push(i);
+        // Scan on a blocking thread so the executor keeps serving pings and other requests
+        let s = state.clone().read_owned().await;
+        let source = s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?;
+        let matches = tokio::task::spawn_blocking(move || {
+            let data = s.source(source);
+            let total = data.len();
+            let mut matches = Vec::new();
+            for i in 0..=total.saturating_sub(pattern.len()) {
+                if i % PROGRESS_STEP == 0 && i > 0 {
+                    if job.is_cancelled() {
+                        return None;
+                    }
+                    job.report(i as u64, total as u64);
+                }
+                if data[i..i + pattern.len()] == pattern[..] {
+                    matches.push(i);
+                }
             }
-        }
+            job.report(total as u64, total as u64);
+            Some(matches)
+        })
+        .await
+        .map_err(|e| CallToolError::from_message(format!("Search failed: {}", e)))?
+        .ok_or_else(|| CallToolError::from_message("Search cancelled"))?;
         
         let output = if matches.is_empty() {
             "No matches found".to_string()
         } else {
-            format!("Found {} matches at offsets:\n{}", 
+            format!("Found {} matches{} at offsets:\n{}", 
                 matches.len(),
+                source_suffix(&self.segment),
                 matches.iter()
                     .map(|&off| format!("  0x{:08X}", off))
                     .collect::<Vec<_>>()
@@ -127,7 +641,12 @@ impl SearchPattern {
             )
         };
         
-        Ok(CallToolResult::text_content(vec![TextContent::from(output)]))
+        structured_result(output, &SearchPatternOutput {
+            pattern: self.pattern.clone(),
+            count: matches.len() as u64,
+            offsets: matches.iter().map(|&off| off as u64).collect(),
+            segment: self.segment.clone(),
+        })
     }
 }
 
@@ -136,7 +655,11 @@ impl SearchPattern {
 //*******************//
 #[mcp_tool(
     name = "extract_segment",
-    description = "Extracts a segment of bytes and stores it for later reference"
+    description = "Extracts a segment of bytes and stores it for later reference",
+    read_only_hint = false,
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = false
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
 pub struct ExtractSegment {
@@ -144,7 +667,7 @@ pub struct ExtractSegment {
     pub offset: u64,
     /// Length of segment
     pub length: u64,
-    /// Optional label for the segment
+    /// Optional label for the segment; unique and not a plain number
     pub label: Option<String>,
 }
 
@@ -154,9 +677,12 @@ impl ExtractSegment {
     {
         let mut s = state.write().await;
         
-        if self.offset + self.length > s.buffer.len() as u64 {
+        if self.offset.checked_add(self.length).is_none_or(|end| end > s.buffer.len() as u64) {
             return Err(CallToolError::from_message("Segment exceeds buffer bounds"));
         }
+        if let Some(label) = &self.label {
+            s.check_segment_label(label, None).map_err(CallToolError::from_message)?;
+        }
         
         let start = self.offset as usize;
         let end = start + self.length as usize;
@@ -168,7 +694,7 @@ impl ExtractSegment {
         };
         
         s.segments.push(segment);
-        s.display();
+        s.mark_changed(StateChange::Segments);
         
         Ok(CallToolResult::text_content(vec![
             TextContent::from(format!(
@@ -181,196 +707,1323 @@ impl ExtractSegment {
     }
 }
 
-//****************//
-//  AddBookmark   //
-//****************//
+//*****************//
+//  ListSegments   //
+//*****************//
 #[mcp_tool(
-    name = "add_bookmark",
-    description = "Creates a named bookmark at a specific offset for quick reference"
+    name = "list_segments",
+    description = "Lists extracted segments with their index, label, source offset, size and whether they still match the buffer",
+    read_only_hint = true,
+    open_world_hint = false
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
-pub struct AddBookmark {
-    /// Name for the bookmark
-    pub name: String,
-    /// Offset to bookmark
-    pub offset: u64,
-}
+pub struct ListSegments {}
 
-impl AddBookmark {
+impl ListSegments {
     pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
         -> Result<CallToolResult, CallToolError> 
     {
-        let mut s = state.write().await;
-        
-        let offset = self.offset as usize;
+        let s = state.read().await;
         
-        if offset > s.buffer.len() {
-            return Err(CallToolError::from_message("Offset exceeds buffer size"));
+        if s.segments.is_empty() {
+            return Ok(CallToolResult::text_content(vec![TextContent::from("No segments")]));
         }
         
-        s.bookmarks.insert(self.name.clone(), offset);
-        s.display();
+        let lines = s.segments.iter().enumerate()
+            .map(|(i, seg)| {
+                let start = seg.offset as usize;
+                let matches = s.buffer.get(start..start + seg.data.len()) == Some(&seg.data[..]);
+                format!(
+                    "  [{}] {:<20} 0x{:08X} {:>10} bytes  {}",
+                    i,
+                    seg.label.as_deref().unwrap_or("-"),
+                    seg.offset,
+                    seg.data.len(),
+                    if matches { "matches buffer" } else { "differs from buffer (snapshot)" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
         
         Ok(CallToolResult::text_content(vec![
-            TextContent::from(format!("✅ Bookmark '{}' added at 0x{:08X}", self.name, offset))
+            TextContent::from(format!("Segments ({}):\n{}", s.segments.len(), lines))
         ]))
     }
 }
 
-//****************//
-//  ReadString    //
-//****************//
+//******************//
+//  RemoveSegment   //
+//******************//
 #[mcp_tool(
-    name = "read_string",
-    description = "Attempts to read bytes as ASCII/UTF-8 string from specified offset"
+    name = "remove_segment",
+    description = "Removes an extracted segment; the indices of later segments shift down by one",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = false,
+    open_world_hint = false
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
-pub struct ReadString {
-    /// Starting offset
-    pub offset: u64,
-    /// Maximum length to read
-    pub max_length: u64,
+pub struct RemoveSegment {
+    /// Segment label or index
+    pub segment: String,
 }
 
-impl ReadString {
+impl RemoveSegment {
     pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
         -> Result<CallToolResult, CallToolError> 
     {
-        let s = state.read().await;
-        
-        let offset = self.offset as usize;
-        let max_length = self.max_length as usize;
-        
-        let end = (offset + max_length).min(s.buffer.len());
-        let bytes = &s.buffer[offset..end];
-        
-        let null_pos = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
-        let str_bytes = &bytes[..null_pos];
+        let mut s = state.write().await;
+        let index = s.resolve_segment(Some(&self.segment))
+            .map_err(CallToolError::from_message)?
+            .unwrap_or_default();
         
-        let text = String::from_utf8_lossy(str_bytes);
+        let removed = s.segments.remove(index);
+        s.mark_changed(StateChange::Segments);
         
         Ok(CallToolResult::text_content(vec![
-            TextContent::from(format!("String at 0x{:08X} ({} bytes):\n{}", 
-                offset, str_bytes.len(), text))
+            TextContent::from(format!(
+                "✅ Removed segment {}{} ({} bytes from 0x{:08X})",
+                index,
+                removed.label.as_ref().map(|l| format!(" ({})", l)).unwrap_or_default(),
+                removed.data.len(),
+                removed.offset
+            ))
         ]))
     }
 }
 
-//****************//
-//  ReadInteger   //
-//****************//
+//*******************//
+//  RelabelSegment   //
+//*******************//
 #[mcp_tool(
-    name = "read_integer",
-    description = "Reads bytes as integer (u8, u16, u32, u64) with specified endianness"
+    name = "relabel_segment",
+    description = "Changes the label of an extracted segment; labels must be unique and not plain numbers",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = true,
+    open_world_hint = false
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
-pub struct ReadInteger {
-    /// Starting offset
-    pub offset: u64,
-    /// Integer size: 1, 2, 4, or 8 bytes
-    pub size: u8,
-    /// Endianness: 'little' or 'big'
-    pub endian: String,
+pub struct RelabelSegment {
+    /// Segment label or index
+    pub segment: String,
+    /// New label; empty to remove the label
+    pub label: String,
 }
 
-impl ReadInteger {
+impl RelabelSegment {
     pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
         -> Result<CallToolResult, CallToolError> 
     {
-        let s = state.read().await;
-        
-        let offset = self.offset as usize;
+        let mut s = state.write().await;
+        let index = s.resolve_segment(Some(&self.segment))
+            .map_err(CallToolError::from_message)?
+            .unwrap_or_default();
         
-        if offset + self.size as usize > s.buffer.len() {
-            return Err(CallToolError::from_message("Read exceeds buffer bounds"));
+        let label = Some(self.label.trim().to_string()).filter(|l| !l.is_empty());
+        if let Some(label) = &label {
+            s.check_segment_label(label, Some(index)).map_err(CallToolError::from_message)?;
         }
         
-        let bytes = &s.buffer[offset..offset + self.size as usize];
-        
-        let value = match (self.size, self.endian.as_str()) {
-            (1, _) => bytes[0] as u64,
-            (2, "little") => u16::from_le_bytes([bytes[0], bytes[1]]) as u64,
-            (2, "big") => u16::from_be_bytes([bytes[0], bytes[1]]) as u64,
-            (4, "little") => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64,
-            (4, "big") => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64,
-            (8, "little") => u64::from_le_bytes(bytes.try_into().unwrap()),
-            (8, "big") => u64::from_be_bytes(bytes.try_into().unwrap()),
-            _ => return Err(CallToolError::from_message("Invalid size or endianness")),
-        };
+        s.segments[index].label = label.clone();
+        s.mark_changed(StateChange::Segments);
         
         Ok(CallToolResult::text_content(vec![
-            TextContent::from(format!(
-                "u{} at 0x{:08X} ({} endian): {} (0x{:X})",
-                self.size * 8, offset, self.endian, value, value
-            ))
+            TextContent::from(match label {
+                Some(label) => format!("✅ Segment {} labelled '{}'", index, label),
+                None => format!("✅ Label of segment {} removed", index),
+            })
         ]))
     }
 }
 
-//******************//
-//  CalculateHash   //
-//******************//
+/// Resolves a relative file name inside the output directory, refusing
+/// absolute paths and `..` so exports can't escape it.
+fn output_path(output_dir: Option<&Path>, file: &str) -> Result<PathBuf, CallToolError> {
+    let output_dir = output_dir.ok_or_else(|| CallToolError::from_message(
+        "Writing files is disabled; start the server with --output-dir to allow it"
+    ))?;
+    let relative = Path::new(file);
+    if file.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(CallToolError::from_message(format!(
+            "'{}' must be a relative path inside the output directory", file
+        )));
+    }
+    Ok(output_dir.join(relative))
+}
+
+/// Returns exported bytes as an embedded blob resource.
+fn export(uri: String, data: &[u8]) -> CallToolResult {
+    let mime_type = resources::guess_mime_type(data);
+    CallToolResult {
+        content: vec![
+            TextContent::from(format!("✅ Exported {} bytes as {} ({})", data.len(), uri, mime_type)).into(),
+            ContentBlock::embedded_resource(resources::blob_contents(&uri, data, mime_type).into()),
+        ],
+        is_error: None,
+        meta: None,
+        structured_content: None,
+    }
+}
+
+/// Writes bytes to `file` under the output directory.
+async fn write_output(data: &[u8], file: &str, output_dir: Option<&Path>)
+    -> Result<CallToolResult, CallToolError>
+{
+    let mime_type = resources::guess_mime_type(data);
+    let path = output_path(output_dir, file)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await
+            .map_err(|e| CallToolError::from_message(format!("Failed to create directory: {}", e)))?;
+    }
+    fs::write(&path, data).await
+        .map_err(|e| CallToolError::from_message(format!("Failed to write file: {}", e)))?;
+
+    Ok(CallToolResult::text_content(vec![
+        TextContent::from(format!("✅ Wrote {} bytes ({}) to '{}'", data.len(), mime_type, path.display()))
+    ]))
+}
+
+//****************//
+//  ExportRange   //
+//****************//
 #[mcp_tool(
-    name = "calculate_hash",
-    description = "Calculates SHA-256 hash of the entire buffer or a segment"
+    name = "export_range",
+    description = "Exports a byte range as an embedded resource blob with a MIME type guessed from its content; save_bytes writes it to a file instead",
+    read_only_hint = true,
+    open_world_hint = false
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
-pub struct CalculateHash {
-    /// Optional offset (if None, hash entire buffer)
-    pub offset: Option<u64>,
-    /// Optional length (if None, hash from offset to end)
-    pub length: Option<u64>,
+pub struct ExportRange {
+    /// Starting offset
+    pub offset: u64,
+    /// Number of bytes to export
+    pub length: u64,
 }
 
-impl CalculateHash {
+impl ExportRange {
     pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
         -> Result<CallToolResult, CallToolError> 
     {
         let s = state.read().await;
-        
-        let offset = self.offset.unwrap_or(0) as usize;
-        let end = self.length
-            .map(|len| offset + len as usize)
-            .unwrap_or(s.buffer.len());
-        
-        if end > s.buffer.len() {
-            return Err(CallToolError::from_message("Range exceeds buffer size"));
-        }
-        
-        let data = &s.buffer[offset..end];
-        let mut hasher = Sha256::new();
-        hasher.update(data);
-        let hash = hasher.finalize();
-        
-        Ok(CallToolResult::text_content(vec![
-            TextContent::from(format!(
-                "SHA-256 (0x{:08X} - 0x{:08X}, {} bytes):\n{}",
-                offset, end, data.len(), hex::encode(hash)
-            ))
-        ]))
+        let start = self.offset as usize;
+        let end = start
+            .checked_add(self.length as usize)
+            .filter(|&end| end <= s.buffer.len())
+            .ok_or_else(|| CallToolError::from_message("Range exceeds buffer bounds"))?;
+
+        Ok(export(resources::range_uri(&s.buffer_name(), start, end - start), &s.buffer[start..end]))
     }
 }
 
-//************//
-//  GetInfo   //
-//************//
+//******************//
+//  ExportSegment   //
+//******************//
 #[mcp_tool(
-    name = "get_info",
-    description = "Returns detailed information about the current buffer state"
+    name = "export_segment",
+    description = "Exports an extracted segment as an embedded resource blob with a MIME type guessed from its content; save_bytes writes it to a file instead",
+    read_only_hint = true,
+    open_world_hint = false
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
-pub struct GetInfo {}
+pub struct ExportSegment {
+    /// Segment label or index
+    pub segment: String,
+}
 
-impl GetInfo {
+impl ExportSegment {
     pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
         -> Result<CallToolResult, CallToolError> 
     {
         let s = state.read().await;
-        
-        let info = format!(
-            "📊 Buffer Information:\n\
-             File: {}\n\
-             Size: {} bytes (0x{:X})\n\
+        let index = s.resolve_segment(Some(&self.segment))
+            .map_err(CallToolError::from_message)?
+            .unwrap_or_default();
+
+        Ok(export(resources::segment_uri(index), &s.segments[index].data))
+    }
+}
+
+//**************//
+//  SaveBytes   //
+//**************//
+#[mcp_tool(
+    name = "save_bytes",
+    description = "Writes a byte range of the buffer or a segment to a file under the server's output directory, replacing any file already there",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = true,
+    open_world_hint = true
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct SaveBytes {
+    /// File to write, relative to the output directory
+    pub file: String,
+    /// Segment label or index to save instead of the buffer; offsets are then relative to the segment
+    pub segment: Option<String>,
+    /// Starting offset (default 0)
+    pub offset: Option<u64>,
+    /// Number of bytes to save (default: up to the end)
+    pub length: Option<u64>,
+}
+
+impl SaveBytes {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>, output_dir: Option<&Path>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let data = {
+            let s = state.read().await;
+            let data = s.source(s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?);
+            let start = self.offset.unwrap_or(0) as usize;
+            let end = self.length
+                .map_or(Some(data.len()), |len| start.checked_add(len as usize))
+                .filter(|&end| start <= end && end <= data.len())
+                .ok_or_else(|| CallToolError::from_message("Range exceeds the source bounds"))?;
+            data[start..end].to_vec()
+        };
+
+        write_output(&data, &self.file, output_dir).await
+    }
+}
+
+//****************//
+//  AddBookmark   //
+//****************//
+#[mcp_tool(
+    name = "add_bookmark",
+    description = "Creates or replaces a named bookmark at an offset or over a range, with an optional type (code, data, string, struct), color, comment and tags",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct AddBookmark {
+    /// Name for the bookmark
+    pub name: String,
+    /// Offset to bookmark
+    pub offset: u64,
+    /// Number of bytes covered (default 0: a single position)
+    pub length: Option<u64>,
+    /// Type: 'code', 'data', 'string' or 'struct'
+    pub kind: Option<String>,
+    /// Display color, e.g. 'red' or '#ff8800'
+    pub color: Option<String>,
+    /// Free-form comment
+    pub comment: Option<String>,
+    /// Tags for grouping and filtering
+    pub tags: Option<Vec<String>>,
+}
+
+impl AddBookmark {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let mut s = state.write().await;
+        
+        let offset = self.offset as usize;
+        let length = self.length.unwrap_or(0) as usize;
+        
+        if offset > s.buffer.len() {
+            return Err(CallToolError::from_message("Offset exceeds buffer size"));
+        }
+        if offset.checked_add(length).is_none_or(|end| end > s.buffer.len()) {
+            return Err(CallToolError::from_message("Range exceeds buffer bounds"));
+        }
+        let kind = self.kind.as_ref().map(|k| k.trim().to_lowercase());
+        if let Some(kind) = &kind {
+            if !BOOKMARK_KINDS.contains(&kind.as_str()) {
+                return Err(CallToolError::from_message(format!(
+                    "Unknown bookmark type '{}'; expected one of {}", kind, BOOKMARK_KINDS.join(", ")
+                )));
+            }
+        }
+        
+        let bookmark = Bookmark {
+            offset,
+            length,
+            kind,
+            color: self.color.clone(),
+            comment: self.comment.clone(),
+            tags: self.tags.clone().unwrap_or_default(),
+        };
+        let line = bookmark.describe(&self.name);
+        let replaced = s.bookmarks.insert(self.name.clone(), bookmark).is_some();
+        s.mark_changed(StateChange::Bookmarks);
+        
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!(
+                "✅ Bookmark '{}' {}: {}",
+                self.name,
+                if replaced { "replaced" } else { "added" },
+                line
+            ))
+        ]))
+    }
+}
+
+//******************//
+//  ListBookmarks   //
+//******************//
+#[mcp_tool(
+    name = "list_bookmarks",
+    description = "Lists bookmarks sorted by offset, optionally filtered by type or tag",
+    read_only_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct ListBookmarks {
+    /// Only bookmarks of this type
+    pub kind: Option<String>,
+    /// Only bookmarks carrying this tag
+    pub tag: Option<String>,
+}
+
+impl ListBookmarks {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let s = state.read().await;
+        
+        let lines: Vec<String> = s.sorted_bookmarks().into_iter()
+            .filter(|(_, b)| self.kind.as_ref()
+                .is_none_or(|kind| b.kind.as_deref().is_some_and(|k| k.eq_ignore_ascii_case(kind))))
+            .filter(|(_, b)| self.tag.as_ref().is_none_or(|tag| b.tags.contains(tag)))
+            .map(|(name, b)| format!("  {}", b.describe(name)))
+            .collect();
+        
+        let text = if lines.is_empty() {
+            "No bookmarks".to_string()
+        } else {
+            format!("Bookmarks ({}):\n{}", lines.len(), lines.join("\n"))
+        };
+        Ok(CallToolResult::text_content(vec![TextContent::from(text)]))
+    }
+}
+
+//*******************//
+//  RenameBookmark   //
+//*******************//
+#[mcp_tool(
+    name = "rename_bookmark",
+    description = "Renames a bookmark, keeping its range and attributes",
+    read_only_hint = false,
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct RenameBookmark {
+    /// Current name
+    pub name: String,
+    /// New name; must not be in use
+    pub new_name: String,
+}
+
+impl RenameBookmark {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let mut s = state.write().await;
+        
+        if s.bookmarks.contains_key(&self.new_name) {
+            return Err(CallToolError::from_message(format!("A bookmark named '{}' already exists", self.new_name)));
+        }
+        let bookmark = s.bookmarks.remove(&self.name)
+            .ok_or_else(|| CallToolError::from_message(format!("No bookmark named '{}'", self.name)))?;
+        s.bookmarks.insert(self.new_name.clone(), bookmark);
+        s.mark_changed(StateChange::Bookmarks);
+        let notes = s.retarget_notes(&self.name, Some(&self.new_name));
+        
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!(
+                "✅ Bookmark '{}' renamed to '{}'{}",
+                self.name,
+                self.new_name,
+                if notes > 0 { format!("; {} note(s) follow it", notes) } else { String::new() }
+            ))
+        ]))
+    }
+}
+
+//*******************//
+//  RemoveBookmark   //
+//*******************//
+#[mcp_tool(
+    name = "remove_bookmark",
+    description = "Removes a bookmark",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct RemoveBookmark {
+    /// Name of the bookmark
+    pub name: String,
+}
+
+impl RemoveBookmark {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let mut s = state.write().await;
+        
+        let removed = s.bookmarks.remove(&self.name)
+            .ok_or_else(|| CallToolError::from_message(format!("No bookmark named '{}'", self.name)))?;
+        s.mark_changed(StateChange::Bookmarks);
+        let notes = s.retarget_notes(&self.name, None);
+        
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!(
+                "✅ Removed bookmark {}{}",
+                removed.describe(&self.name),
+                if notes > 0 { format!("; {} note(s) keep its range", notes) } else { String::new() }
+            ))
+        ]))
+    }
+}
+
+//****************//
+//  BookmarksAt   //
+//****************//
+#[mcp_tool(
+    name = "bookmarks_at",
+    description = "Lists the bookmarks covering an offset, innermost (shortest) first",
+    read_only_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct BookmarksAt {
+    /// Offset to look up
+    pub offset: u64,
+}
+
+impl BookmarksAt {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let s = state.read().await;
+        let offset = self.offset as usize;
+        
+        let mut covering: Vec<_> = s.bookmarks.iter()
+            .filter(|(_, b)| b.covers(offset))
+            .collect();
+        covering.sort_by_key(|(name, b)| (b.length, std::cmp::Reverse(b.offset), name.as_str()));
+        
+        let text = if covering.is_empty() {
+            format!("No bookmark covers 0x{:08X}", offset)
+        } else {
+            format!(
+                "Bookmarks covering 0x{:08X}:\n{}",
+                offset,
+                covering.iter()
+                    .map(|(name, b)| format!("  {}", b.describe(name)))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        };
+        Ok(CallToolResult::text_content(vec![TextContent::from(text)]))
+    }
+}
+
+//****************//
+//  ReadString    //
+//****************//
+#[mcp_tool(
+    name = "read_string",
+    description = "Attempts to read bytes as ASCII/UTF-8 string from specified offset",
+    read_only_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct ReadString {
+    /// Starting offset
+    pub offset: u64,
+    /// Maximum length to read
+    pub max_length: u64,
+}
+
+impl ReadString {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let s = state.read().await;
+        
+        let offset = self.offset as usize;
+        let max_length = self.max_length as usize;
+        
+        let end = (offset + max_length).min(s.buffer.len());
+        let bytes = &s.buffer[offset..end];
+        
+        let null_pos = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        let str_bytes = &bytes[..null_pos];
+        
+        let text = String::from_utf8_lossy(str_bytes);
+        
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!("String at 0x{:08X} ({} bytes):\n{}", 
+                offset, str_bytes.len(), text))
+        ]))
+    }
+}
+
+//****************//
+//  ReadInteger   //
+//****************//
+#[mcp_tool(
+    name = "read_integer",
+    description = "Reads bytes as integer (u8, u16, u32, u64) with specified endianness",
+    read_only_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct ReadInteger {
+    /// Starting offset
+    pub offset: u64,
+    /// Integer size: 1, 2, 4, or 8 bytes
+    pub size: u8,
+    /// Endianness: 'little' or 'big'
+    pub endian: String,
+    /// Segment label or index to read instead of the buffer; offsets are then relative to the segment
+    pub segment: Option<String>,
+}
+
+/// Structured result of `read_integer`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct ReadIntegerOutput {
+    /// Offset the integer was read from
+    pub offset: u64,
+    /// Integer size in bytes
+    pub size: u8,
+    /// Endianness used: 'little' or 'big'
+    pub endian: String,
+    /// Decoded unsigned value
+    pub value: u64,
+    /// Segment read instead of the buffer, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment: Option<String>,
+}
+
+impl ReadInteger {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let s = state.read().await;
+        let data = s.source(s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?);
+        
+        let offset = self.offset as usize;
+        
+        if offset.checked_add(self.size as usize).is_none_or(|end| end > data.len()) {
+            return Err(CallToolError::from_message("Read exceeds buffer bounds"));
+        }
+        
+        let bytes = &data[offset..offset + self.size as usize];
+        
+        let value = match (self.size, self.endian.as_str()) {
+            (1, _) => bytes[0] as u64,
+            (2, "little") => u16::from_le_bytes([bytes[0], bytes[1]]) as u64,
+            (2, "big") => u16::from_be_bytes([bytes[0], bytes[1]]) as u64,
+            (4, "little") => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64,
+            (4, "big") => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64,
+            (8, "little") => u64::from_le_bytes(bytes.try_into().unwrap()),
+            (8, "big") => u64::from_be_bytes(bytes.try_into().unwrap()),
+            _ => return Err(CallToolError::from_message("Invalid size or endianness")),
+        };
+        
+        let text = format!(
+            "u{} at 0x{:08X}{} ({} endian): {} (0x{:X})",
+            self.size * 8, offset, source_suffix(&self.segment), self.endian, value, value
+        );
+
+        structured_result(text, &ReadIntegerOutput {
+            offset: self.offset,
+            size: self.size,
+            endian: self.endian.clone(),
+            value,
+            segment: self.segment.clone(),
+        })
+    }
+}
+
+//******************//
+//  CalculateHash   //
+//******************//
+#[mcp_tool(
+    name = "calculate_hash",
+    description = "Calculates hashes of the entire buffer, a range or a segment: SHA-256 by default, \
+                   or any of md5, sha1, sha256, sha512, sha3-256, sha3-512, blake2b, blake2s, blake3, \
+                   crc32, the ssdeep and tlsh similarity digests, and for PE images imphash and \
+                   authentihash, in one pass. Cancellable, but cancelling any request of the session \
+                   stops every running search, hash and entropy scan in it",
+    read_only_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct CalculateHash {
+    /// Optional offset (if None, hash entire buffer)
+    pub offset: Option<u64>,
+    /// Optional length (if None, hash from offset to end)
+    pub length: Option<u64>,
+    /// Segment label or index to hash instead of the buffer; offsets are then relative to the segment
+    pub segment: Option<String>,
+    /// Algorithms to compute (default ['sha256']); imphash and authentihash need the range to be a PE image
+    pub algorithms: Option<Vec<String>>,
+    /// Optional token for notifications/progress while hashing
+    pub progress_token: Option<String>,
+}
+
+/// One digest of `calculate_hash`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct HashDigest {
+    /// Hash algorithm, e.g. 'sha256'
+    pub algorithm: String,
+    /// Lowercase hex digest, if it could be computed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    /// Why the digest couldn't be computed, e.g. the range is not a PE image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Structured result of `calculate_hash`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct CalculateHashOutput {
+    /// First algorithm computed, e.g. 'sha256'
+    pub algorithm: String,
+    /// Start offset of the hashed range
+    pub start: u64,
+    /// End offset (exclusive) of the hashed range
+    pub end: u64,
+    /// Number of bytes hashed
+    pub length: u64,
+    /// Lowercase hex digest of `algorithm`
+    pub digest: String,
+    /// Every requested algorithm, in request order
+    pub digests: Vec<HashDigest>,
+    /// Segment hashed instead of the buffer, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment: Option<String>,
+}
+
+impl CalculateHash {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>, job: JobContext) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let mut algorithms = Vec::new();
+        for name in self.algorithms.iter().flatten() {
+            let algorithm = Algorithm::parse(name).map_err(CallToolError::from_message)?;
+            if !algorithms.contains(&algorithm) {
+                algorithms.push(algorithm);
+            }
+        }
+        if algorithms.is_empty() {
+            algorithms.push(Algorithm::Sha256);
+        }
+
+        let s = state.clone().read_owned().await;
+        let source = s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?;
+        let size = s.source(source).len();
+        
+        let offset = self.offset.unwrap_or(0) as usize;
+        let end = self.length
+            .map_or(Some(size), |len| offset.checked_add(len as usize))
+            .filter(|&end| offset <= end && end <= size)
+            .ok_or_else(|| CallToolError::from_message("Range exceeds buffer size"))?;
+        
+        // Hash on a blocking thread so the executor keeps serving pings and other requests
+        let requested = algorithms.clone();
+        let results = tokio::task::spawn_blocking(move || {
+            let data = &s.source(source)[offset..end];
+            let mut hashers: Vec<_> = requested.iter()
+                .filter_map(|a| a.hasher(data.len()).map(|h| (*a, h)))
+                .collect();
+            for (i, chunk) in data.chunks(PROGRESS_STEP).enumerate() {
+                if job.is_cancelled() {
+                    return None;
+                }
+                hashers.iter_mut().for_each(|(_, hasher)| hasher.update(chunk));
+                job.report((i * PROGRESS_STEP + chunk.len()) as u64, data.len() as u64);
+            }
+            let mut streamed: Vec<_> = hashers.into_iter()
+                .map(|(algorithm, hasher)| (algorithm, hasher.finish()))
+                .collect();
+            Some(requested.iter()
+                .map(|&algorithm| match streamed.iter().position(|(a, _)| *a == algorithm) {
+                    Some(i) => (algorithm, streamed.swap_remove(i).1),
+                    None => (algorithm, algorithm.pe_digest(data)),
+                })
+                .collect::<Vec<_>>())
+        })
+        .await
+        .map_err(|e| CallToolError::from_message(format!("Hashing failed: {}", e)))?
+        .ok_or_else(|| CallToolError::from_message("Hashing cancelled"))?;
+
+        let Some((first, digest)) = results.iter()
+            .find_map(|(a, r)| r.as_ref().ok().map(|d| (*a, d.clone())))
+        else {
+            let errors: Vec<String> = results.iter()
+                .filter_map(|(a, r)| r.as_ref().err().map(|e| format!("{}: {}", a.label(), e)))
+                .collect();
+            return Err(CallToolError::from_message(errors.join("; ")));
+        };
+
+        let range = format!(
+            "0x{:08X} - 0x{:08X}{}, {} bytes",
+            offset, end, source_suffix(&self.segment), end - offset
+        );
+        let text = if results.len() == 1 {
+            format!("{} ({}):\n{}", first.label(), range, digest)
+        } else {
+            let width = results.iter().map(|(a, _)| a.label().len()).max().unwrap_or(0);
+            let lines: Vec<String> = results.iter()
+                .map(|(a, r)| match r {
+                    Ok(d) => format!("  {:<width$}  {}", a.label(), d),
+                    Err(e) => format!("  {:<width$}  ⚠️ {}", a.label(), e),
+                })
+                .collect();
+            format!("Hashes ({}):\n{}", range, lines.join("\n"))
+        };
+
+        structured_result(text, &CalculateHashOutput {
+            algorithm: first.name().to_string(),
+            start: offset as u64,
+            end: end as u64,
+            length: (end - offset) as u64,
+            digest,
+            digests: results.into_iter()
+                .map(|(a, r)| HashDigest {
+                    algorithm: a.name().to_string(),
+                    digest: r.as_ref().ok().cloned(),
+                    error: r.err(),
+                })
+                .collect(),
+            segment: self.segment.clone(),
+        })
+    }
+}
+
+//******************//
+//  CompareHashes   //
+//******************//
+#[mcp_tool(
+    name = "compare_hashes",
+    description = "Scores how similar the buffer (or a range or segment) is to a file, a segment, an ssdeep or \
+                   TLSH digest, or by default to every segment, using ssdeep (0-100, higher is more similar) \
+                   and TLSH (distance, 0 is identical; below about 50 usually means related)",
+    read_only_hint = true,
+    open_world_hint = true
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct CompareHashes {
+    /// Optional offset (if None, compare from the start)
+    pub offset: Option<u64>,
+    /// Optional length (if None, compare from offset to end)
+    pub length: Option<u64>,
+    /// Segment label or index to compare instead of the buffer; offsets are then relative to the segment
+    pub segment: Option<String>,
+    /// Path of a file on the server to compare against
+    pub other_path: Option<String>,
+    /// Segment label or index to compare against
+    pub other_segment: Option<String>,
+    /// ssdeep or TLSH digest to compare against; it is scored with its own algorithm only
+    pub digest: Option<String>,
+    /// Similarity algorithms to use: 'ssdeep', 'tlsh' (default both)
+    pub algorithms: Option<Vec<String>>,
+}
+
+/// One target scored by `compare_hashes`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct HashComparison {
+    /// What was compared against, e.g. "segment 'payload'"
+    pub target: String,
+    /// ssdeep match score from 0 to 100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssdeep_score: Option<u32>,
+    /// TLSH distance, 0 for identical data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tlsh_distance: Option<i32>,
+    /// Why a score is missing, e.g. data too short for TLSH
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+/// Structured result of `compare_hashes`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct CompareHashesOutput {
+    /// Start offset of the compared range
+    pub start: u64,
+    /// End offset (exclusive) of the compared range
+    pub end: u64,
+    /// Number of bytes compared
+    pub length: u64,
+    /// ssdeep digest of the range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssdeep: Option<String>,
+    /// TLSH digest of the range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tlsh: Option<String>,
+    /// Scores per target; against every segment, most similar first
+    pub comparisons: Vec<HashComparison>,
+    /// Segment compared instead of the buffer, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment: Option<String>,
+}
+
+impl CompareHashes {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let mut algorithms = Vec::new();
+        for name in self.algorithms.iter().flatten() {
+            let algorithm = Algorithm::parse(name).map_err(CallToolError::from_message)?;
+            if !matches!(algorithm, Algorithm::Ssdeep | Algorithm::Tlsh) {
+                return Err(CallToolError::from_message(format!(
+                    "{} is not a similarity digest; use 'ssdeep' or 'tlsh'", algorithm.label()
+                )));
+            }
+            if !algorithms.contains(&algorithm) {
+                algorithms.push(algorithm);
+            }
+        }
+        if algorithms.is_empty() {
+            algorithms = vec![Algorithm::Ssdeep, Algorithm::Tlsh];
+        }
+        if let Some(digest) = &self.digest {
+            let kind = hashing::fuzzy_kind(digest).ok_or_else(|| CallToolError::from_message(
+                "digest is neither an ssdeep ('blocksize:hash:hash') nor a TLSH digest",
+            ))?;
+            algorithms = vec![kind];
+        }
+
+        let given = [self.other_path.is_some(), self.other_segment.is_some(), self.digest.is_some()];
+        if given.iter().filter(|&&g| g).count() > 1 {
+            return Err(CallToolError::from_message(
+                "Give at most one of other_path, other_segment and digest",
+            ));
+        }
+        let other_file = match &self.other_path {
+            Some(path) => Some(fs::read(path).await.map_err(|e| {
+                CallToolError::from_message(format!("Failed to read '{}': {}", path, e))
+            })?),
+            None => None,
+        };
+
+        let s = state.clone().read_owned().await;
+        let source = s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?;
+        let other_segment = s.resolve_segment(self.other_segment.as_deref())
+            .map_err(CallToolError::from_message)?;
+        let size = s.source(source).len();
+
+        let offset = self.offset.unwrap_or(0) as usize;
+        let end = self.length
+            .map_or(Some(size), |len| offset.checked_add(len as usize))
+            .filter(|&end| offset <= end && end <= size)
+            .ok_or_else(|| CallToolError::from_message("Range exceeds buffer size"))?;
+
+        let every_segment = given.iter().all(|&g| !g);
+        if every_segment && s.segments.iter().enumerate().all(|(i, _)| Some(i) == source) {
+            return Err(CallToolError::from_message(
+                "No segments to compare against; give other_path, other_segment or digest",
+            ));
+        }
+
+        // Digest on a blocking thread; ssdeep walks every byte of every target
+        let digest = self.digest.clone();
+        let other_path = self.other_path.clone();
+        let (own, mut comparisons) = tokio::task::spawn_blocking(move || {
+            let own: Vec<_> = algorithms.iter()
+                .map(|&a| (a, hashing::fuzzy_digest(a, &s.source(source)[offset..end])))
+                .collect();
+            let score = |target: String, digests: Vec<(Algorithm, Result<String, String>)>| {
+                let mut comparison = HashComparison {
+                    target,
+                    ssdeep_score: None,
+                    tlsh_distance: None,
+                    errors: Vec::new(),
+                };
+                for ((algorithm, mine), (_, theirs)) in own.iter().zip(digests) {
+                    let result = match (mine, theirs) {
+                        (Ok(mine), Ok(theirs)) => match algorithm {
+                            Algorithm::Ssdeep => hashing::ssdeep_score(mine, &theirs)
+                                .map(|score| comparison.ssdeep_score = Some(score)),
+                            _ => hashing::tlsh_distance(mine, &theirs)
+                                .map(|distance| comparison.tlsh_distance = Some(distance)),
+                        },
+                        (Err(e), _) => Err(e.clone()),
+                        (_, Err(e)) => Err(e),
+                    };
+                    if let Err(e) = result {
+                        comparison.errors.push(format!("{}: {}", algorithm.label(), e));
+                    }
+                }
+                comparison
+            };
+            let digests_of = |data: &[u8]| -> Vec<_> {
+                algorithms.iter().map(|&a| (a, hashing::fuzzy_digest(a, data))).collect()
+            };
+
+            let comparisons = if let Some(digest) = digest {
+                vec![score("digest".to_string(), vec![(algorithms[0], Ok(digest))])]
+            } else if let (Some(data), Some(path)) = (&other_file, &other_path) {
+                vec![score(format!("file '{}'", path), digests_of(data))]
+            } else if let Some(index) = other_segment {
+                vec![score(segment_name(&s, index), digests_of(&s.segments[index].data))]
+            } else {
+                (0..s.segments.len())
+                    .filter(|&i| Some(i) != source)
+                    .map(|i| score(segment_name(&s, i), digests_of(&s.segments[i].data)))
+                    .collect()
+            };
+            (own, comparisons)
+        })
+        .await
+        .map_err(|e| CallToolError::from_message(format!("Comparison failed: {}", e)))?;
+
+        if every_segment {
+            comparisons.sort_by_key(|c| {
+                (std::cmp::Reverse(c.ssdeep_score), c.tlsh_distance.unwrap_or(i32::MAX))
+            });
+        }
+
+        let mut text = format!(
+            "Similarity of 0x{:08X} - 0x{:08X}{} ({} bytes):",
+            offset, end, source_suffix(&self.segment), end - offset
+        );
+        for (algorithm, digest) in &own {
+            match digest {
+                Ok(digest) => text.push_str(&format!("\n  {:<7} {}", algorithm.label(), digest)),
+                Err(e) => text.push_str(&format!("\n  {:<7} ⚠️ {}", algorithm.label(), e)),
+            }
+        }
+        text.push('\n');
+        for comparison in &comparisons {
+            let mut scores = Vec::new();
+            if let Some(score) = comparison.ssdeep_score {
+                scores.push(format!("ssdeep {}/100", score));
+            }
+            if let Some(distance) = comparison.tlsh_distance {
+                scores.push(format!("TLSH distance {}", distance));
+            }
+            scores.extend(comparison.errors.iter().map(|e| format!("⚠️ {}", e)));
+            text.push_str(&format!("\n  {}: {}", comparison.target, scores.join(", ")));
+        }
+
+        let digest_of = |wanted: Algorithm| own.iter()
+            .find(|(a, _)| *a == wanted)
+            .and_then(|(_, d)| d.as_ref().ok().cloned());
+        structured_result(text, &CompareHashesOutput {
+            start: offset as u64,
+            end: end as u64,
+            length: (end - offset) as u64,
+            ssdeep: digest_of(Algorithm::Ssdeep),
+            tlsh: digest_of(Algorithm::Tlsh),
+            comparisons,
+            segment: self.segment.clone(),
+        })
+    }
+}
+
+/// How a segment is named in results: its label, or its index.
+fn segment_name(s: &ServerState, index: usize) -> String {
+    match &s.segments[index].label {
+        Some(label) => format!("segment '{}'", label),
+        None => format!("segment {}", index),
+    }
+}
+
+//*********************//
+//  CalculateEntropy   //
+//*********************//
+#[mcp_tool(
+    name = "calculate_entropy",
+    description = "Computes the Shannon entropy (bits per byte) of the buffer, a range or a segment and a \
+                   sliding-window profile, flagging high-entropy regions (encrypted or compressed data) and \
+                   low-entropy regions (padding), optionally saving them as labelled segments. Cancellable, \
+                   but cancelling any request of the session stops every running search, hash and entropy \
+                   scan in it",
+    read_only_hint = false,
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct CalculateEntropy {
+    /// Optional offset (if None, start of the buffer)
+    pub offset: Option<u64>,
+    /// Optional length (if None, from offset to end)
+    pub length: Option<u64>,
+    /// Segment label or index to analyze instead of the buffer; offsets are then relative to the segment
+    pub segment: Option<String>,
+    /// Window size in bytes (default 1024)
+    pub window: Option<u64>,
+    /// Distance between window starts in bytes (default: the window size)
+    pub step: Option<u64>,
+    /// Windows at or above this entropy are flagged high (default 7.2)
+    pub high_threshold: Option<f64>,
+    /// Windows at or below this entropy are flagged low (default 1.0)
+    pub low_threshold: Option<f64>,
+    /// Flagged regions shorter than this many bytes are ignored (default 0)
+    pub min_length: Option<u64>,
+    /// Maximum number of regions listed (default 100)
+    pub max_regions: Option<u64>,
+    /// Maximum number of profile points returned (default 1024)
+    pub max_points: Option<u64>,
+    /// Save each listed region as a segment labelled 'high_entropy_<offset>' or 'low_entropy_<offset>' (default false)
+    pub create_segments: Option<bool>,
+    /// Optional token for notifications/progress while scanning
+    pub progress_token: Option<String>,
+}
+
+/// One flagged region of `calculate_entropy`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct EntropyRegion {
+    /// 'high' or 'low'
+    pub kind: String,
+    /// Offset of the region, relative to the segment if one was analyzed
+    pub offset: u64,
+    /// Length of the region in bytes
+    pub length: u64,
+    /// Entropy of the whole region in bits per byte
+    pub entropy: f64,
+    /// Label of the segment saved for the region, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment: Option<String>,
+}
+
+/// Structured result of `calculate_entropy`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct CalculateEntropyOutput {
+    /// Start offset of the analyzed range
+    pub start: u64,
+    /// End offset (exclusive) of the analyzed range
+    pub end: u64,
+    /// Entropy of the whole range in bits per byte, from 0 to 8
+    pub entropy: f64,
+    /// Window size in bytes
+    pub window: u64,
+    /// Distance between window starts in bytes
+    pub step: u64,
+    /// Number of windows in the profile, including any not returned
+    pub window_count: u64,
+    /// Entropy of the first max_points windows; window i starts at start + i × step,
+    /// except a final window ending at end when the steps fall short of it
+    pub profile: Vec<f64>,
+    /// Number of flagged regions, including any not listed
+    pub region_count: u64,
+    /// The first max_regions flagged regions, in offset order
+    pub regions: Vec<EntropyRegion>,
+    /// Segment analyzed instead of the buffer, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment: Option<String>,
+}
+
+/// Rounds an entropy for display and structured output.
+fn round_entropy(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+/// A line of block characters showing a profile, at most `width` wide; each
+/// column shows the highest entropy of the windows it covers.
+fn sparkline(profile: &[f64], width: usize) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    if profile.is_empty() {
+        return String::new();
+    }
+    let per_column = profile.len().div_ceil(width);
+    profile.chunks(per_column)
+        .map(|chunk| {
+            let peak = chunk.iter().cloned().fold(0.0, f64::max);
+            BLOCKS[((peak / 8.0 * 7.0).round() as usize).min(7)]
+        })
+        .collect()
+}
+
+impl CalculateEntropy {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>, job: JobContext) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let window = self.window.unwrap_or(1024) as usize;
+        let step = self.step.map_or(window, |step| step as usize);
+        let high = self.high_threshold.unwrap_or(7.2);
+        let low = self.low_threshold.unwrap_or(1.0);
+        let min_length = self.min_length.unwrap_or(0) as usize;
+        let max_regions = self.max_regions.unwrap_or(100) as usize;
+        let max_points = self.max_points.unwrap_or(1024) as usize;
+        if window == 0 || step == 0 {
+            return Err(CallToolError::from_message("window and step must be at least 1 byte"));
+        }
+        if low >= high {
+            return Err(CallToolError::from_message("low_threshold must be below high_threshold"));
+        }
+
+        let s = state.clone().read_owned().await;
+        let source = s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?;
+        let size = s.source(source).len();
+
+        let offset = self.offset.unwrap_or(0) as usize;
+        let end = self.length
+            .map_or(Some(size), |len| offset.checked_add(len as usize))
+            .filter(|&end| offset <= end && end <= size)
+            .ok_or_else(|| CallToolError::from_message("Range exceeds buffer size"))?;
+
+        // Scan on a blocking thread so the executor keeps serving pings and other requests
+        let (total, profile, flagged) = tokio::task::spawn_blocking(move || {
+            let data = &s.source(source)[offset..end];
+            let mut next_report = PROGRESS_STEP;
+            let profile = entropy::profile(data, window, step, |at| {
+                if at >= next_report {
+                    next_report = at + PROGRESS_STEP;
+                    job.report(at as u64, data.len() as u64);
+                }
+                !job.is_cancelled()
+            })?;
+            job.report(data.len() as u64, data.len() as u64);
+
+            let mut flagged: Vec<_> = [("high", true), ("low", false)].into_iter()
+                .flat_map(|(kind, is_high)| {
+                    entropy::regions(&profile, data.len(), window, step, |e| {
+                        if is_high { e >= high } else { e <= low }
+                    })
+                    .into_iter()
+                    .map(move |(at, length)| (kind, at, length))
+                })
+                .filter(|&(_, _, length)| length >= min_length)
+                .map(|(kind, at, length)| (kind, at, length, entropy::entropy(&data[at..at + length])))
+                .collect();
+            flagged.sort_by_key(|&(_, at, _, _)| at);
+            Some((entropy::entropy(data), profile, flagged))
+        })
+        .await
+        .map_err(|e| CallToolError::from_message(format!("Entropy scan failed: {}", e)))?
+        .ok_or_else(|| CallToolError::from_message("Entropy scan cancelled"))?;
+
+        // Segments are saved in buffer coordinates, with the bytes that were analyzed
+        let mut regions: Vec<EntropyRegion> = flagged.iter()
+            .take(max_regions)
+            .map(|&(kind, at, length, value)| EntropyRegion {
+                kind: kind.to_string(),
+                offset: (offset + at) as u64,
+                length: length as u64,
+                entropy: round_entropy(value),
+                segment: None,
+            })
+            .collect();
+        if self.create_segments.unwrap_or(false) && !regions.is_empty() {
+            let mut s = state.write().await;
+            let source = s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?;
+            let base = source.map_or(0, |i| s.segments[i].offset);
+            let mut created = false;
+            for region in &mut regions {
+                let base_label = format!("{}_entropy_0x{:08X}", region.kind, base + region.offset);
+                let mut label = base_label.clone();
+                let mut exists = false;
+                for n in 2.. {
+                    match s.segments.iter().find(|seg| seg.label.as_deref() == Some(label.as_str())) {
+                        Some(seg) if seg.offset == base + region.offset && seg.data.len() as u64 == region.length => {
+                            exists = true;
+                            break;
+                        }
+                        Some(_) => label = format!("{}_{}", base_label, n),
+                        None => break,
+                    }
+                }
+                if !exists {
+                    let from = region.offset as usize;
+                    let data = s.source(source)[from..from + region.length as usize].to_vec();
+                    s.segments.push(crate::state::BinarySegment {
+                        offset: base + region.offset,
+                        data,
+                        label: Some(label.clone()),
+                    });
+                    created = true;
+                }
+                region.segment = Some(label);
+            }
+            if created {
+                s.mark_changed(StateChange::Segments);
+            }
+        }
+
+        let mut text = format!(
+            "Entropy of 0x{:08X} - 0x{:08X}{} ({} bytes): {:.3} bits/byte\n\
+             {} window(s) of {} bytes every {} bytes",
+            offset, end, source_suffix(&self.segment), end - offset, total,
+            profile.len(), window, step
+        );
+        if !profile.is_empty() {
+            text.push_str(&format!("\nProfile: {}", sparkline(&profile, 64)));
+        }
+        if flagged.is_empty() {
+            text.push_str(&format!("\nNo regions at or above {} or at or below {} bits/byte", high, low));
+        } else {
+            text.push_str(&format!("\n\n{} flagged region(s):", flagged.len()));
+            for region in &regions {
+                text.push_str(&format!(
+                    "\n  {} 0x{:08X} - 0x{:08X} ({} bytes): {:.3} bits/byte{}",
+                    if region.kind == "high" { "🔒 high" } else { "⬜ low " },
+                    region.offset, region.offset + region.length, region.length, region.entropy,
+                    region.segment.as_ref().map(|l| format!(" → segment '{}'", l)).unwrap_or_default()
+                ));
+            }
+            if flagged.len() > regions.len() {
+                text.push_str(&format!("\n  … {} more", flagged.len() - regions.len()));
+            }
+        }
+
+        structured_result(text, &CalculateEntropyOutput {
+            start: offset as u64,
+            end: end as u64,
+            entropy: round_entropy(total),
+            window: window as u64,
+            step: step as u64,
+            window_count: profile.len() as u64,
+            profile: profile.iter().take(max_points).cloned().map(round_entropy).collect(),
+            region_count: flagged.len() as u64,
+            regions,
+            segment: self.segment.clone(),
+        })
+    }
+}
+
+//************//
+//  GetInfo   //
+//************//
+#[mcp_tool(
+    name = "get_info",
+    description = "Returns detailed information about the current buffer state",
+    read_only_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct GetInfo {}
+
+/// Structured result of `get_info`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct GetInfoOutput {
+    /// Path of the loaded file, omitted when nothing is loaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Buffer size in bytes
+    pub size: u64,
+    /// Number of bookmarks
+    pub bookmarks: u64,
+    /// Number of extracted segments
+    pub segments: u64,
+    /// Number of analysis notes
+    pub notes: u64,
+}
+
+impl GetInfo {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let s = state.read().await;
+        
+        let info = format!(
+            "📊 Buffer Information:\n\
+             File: {}\n\
+             Size: {} bytes (0x{:X})\n\
              Bookmarks: {}\n\
              Segments: {}\n\
              Notes: {}",
@@ -382,33 +2035,612 @@ impl GetInfo {
             s.analysis_notes.len()
         );
         
-        Ok(CallToolResult::text_content(vec![TextContent::from(info)]))
+        structured_result(info, &GetInfoOutput {
+            file: s.file_loaded.clone(),
+            size: s.buffer.len() as u64,
+            bookmarks: s.bookmarks.len() as u64,
+            segments: s.segments.len() as u64,
+            notes: s.analysis_notes.len() as u64,
+        })
+    }
+}
+
+//****************//
+//  DiffBuffers   //
+//****************//
+#[mcp_tool(
+    name = "diff_buffers",
+    description = "Aligns the buffer (or a segment) with a file or another segment, detecting inserted and deleted bytes rather than comparing byte by byte, and lists the changed regions with the bookmarks and segments they touch",
+    read_only_hint = true,
+    open_world_hint = true
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct DiffBuffers {
+    /// Segment label or index to compare instead of the buffer
+    pub segment: Option<String>,
+    /// Path of a file on the server to compare against
+    pub other_path: Option<String>,
+    /// Segment label or index to compare against
+    pub other_segment: Option<String>,
+    /// Changes separated by fewer equal bytes are reported as one region (default 8)
+    pub merge_gap: Option<u64>,
+    /// Maximum number of regions listed (default 100)
+    pub max_regions: Option<u64>,
+    /// Name the bookmarks and segments each region overlaps (default true)
+    pub annotate: Option<bool>,
+}
+
+/// One changed region found by `diff_buffers`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct DiffRegion {
+    /// 'changed', 'inserted' or 'deleted'
+    pub kind: String,
+    /// Offset of the region in the original
+    pub old_offset: u64,
+    /// Bytes of the original replaced or deleted
+    pub old_length: u64,
+    /// Offset of the region in the other buffer
+    pub new_offset: u64,
+    /// Bytes of the other buffer replacing them
+    pub new_length: u64,
+    /// Bookmarks overlapping the region in the original
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<String>,
+    /// Segments overlapping the region in the original, by label or index
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<String>,
+}
+
+/// Structured result of `diff_buffers`
+#[derive(Debug, Clone, serde::Serialize, JsonSchema)]
+pub struct DiffBuffersOutput {
+    /// Size of the original in bytes
+    pub old_size: u64,
+    /// Size of the other buffer in bytes
+    pub new_size: u64,
+    /// Bytes present in both, in the same order
+    pub equal: u64,
+    /// Bytes of the original missing from the other buffer
+    pub deleted: u64,
+    /// Bytes of the other buffer missing from the original
+    pub inserted: u64,
+    /// 2 × equal / (old_size + new_size), 1.0 for identical buffers
+    pub similarity: f64,
+    /// Number of changed regions, including any not listed
+    pub region_count: u64,
+    /// The first max_regions changed regions, in order
+    pub regions: Vec<DiffRegion>,
+    /// Segment compared instead of the buffer, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment: Option<String>,
+}
+
+impl DiffBuffers {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let other_file = match (&self.other_path, &self.other_segment) {
+            (Some(path), None) => Some(fs::read(path).await.map_err(|e| {
+                CallToolError::from_message(format!("Failed to read '{}': {}", path, e))
+            })?),
+            (None, Some(_)) => None,
+            _ => return Err(CallToolError::from_message(
+                "Give exactly one of other_path and other_segment",
+            )),
+        };
+        let merge_gap = self.merge_gap.unwrap_or(8) as usize;
+        let max_regions = self.max_regions.unwrap_or(100) as usize;
+        let annotate = self.annotate.unwrap_or(true);
+
+        // Align on a blocking thread; large differences take a while
+        let s = state.clone().read_owned().await;
+        let source = s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?;
+        let other_segment = s.resolve_segment(self.other_segment.as_deref())
+            .map_err(CallToolError::from_message)?;
+        let segment = self.segment.clone();
+        let output = tokio::task::spawn_blocking(move || {
+            let old = s.source(source);
+            let new = match &other_file {
+                Some(data) => &data[..],
+                None => s.source(other_segment),
+            };
+            let edits = diff::diff(old, new);
+            let (equal, deleted, inserted) = diff::totals(&edits);
+            let regions = diff::regions(&edits, merge_gap);
+
+            // Bookmarks and segments are in buffer coordinates
+            let base = source.map_or(0, |i| s.segments[i].offset as usize);
+            let overlaps = |start: usize, length: usize, region: &diff::Region| {
+                let from = base + region.old_offset;
+                let to = from + region.old_length.max(1);
+                start < to && from < start + length.max(1)
+            };
+            let listed = regions.iter()
+                .take(max_regions)
+                .map(|region| DiffRegion {
+                    kind: region.kind().to_string(),
+                    old_offset: region.old_offset as u64,
+                    old_length: region.old_length as u64,
+                    new_offset: region.new_offset as u64,
+                    new_length: region.new_length as u64,
+                    bookmarks: if annotate {
+                        s.sorted_bookmarks()
+                            .into_iter()
+                            .filter(|(_, b)| overlaps(b.offset, b.length, region))
+                            .map(|(name, _)| name.clone())
+                            .collect()
+                    } else {
+                        Vec::new()
+                    },
+                    segments: if annotate {
+                        s.segments.iter()
+                            .enumerate()
+                            .filter(|&(i, seg)| {
+                                Some(i) != source && overlaps(seg.offset as usize, seg.data.len(), region)
+                            })
+                            .map(|(i, seg)| seg.label.clone().unwrap_or_else(|| i.to_string()))
+                            .collect()
+                    } else {
+                        Vec::new()
+                    },
+                })
+                .collect();
+
+            let total = old.len() + new.len();
+            DiffBuffersOutput {
+                old_size: old.len() as u64,
+                new_size: new.len() as u64,
+                equal: equal as u64,
+                deleted: deleted as u64,
+                inserted: inserted as u64,
+                similarity: if total == 0 { 1.0 } else { 2.0 * equal as f64 / total as f64 },
+                region_count: regions.len() as u64,
+                regions: listed,
+                segment,
+            }
+        })
+        .await
+        .map_err(|e| CallToolError::from_message(format!("Diff failed: {}", e)))?;
+
+        let other = match (&self.other_path, &self.other_segment) {
+            (Some(path), _) => format!("'{}'", path),
+            (_, Some(seg)) => format!("segment '{}'", seg),
+            _ => String::new(),
+        };
+        let original = match &self.segment {
+            Some(seg) => format!("segment '{}'", seg),
+            None => "the buffer".to_string(),
+        };
+        if output.region_count == 0 {
+            let text = format!("✅ No differences between {} and {} ({} bytes)", original, other, output.old_size);
+            return structured_result(text, &output);
+        }
+
+        let mut lines = vec![
+            format!(
+                "🔀 Diff of {} ({} bytes) and {} ({} bytes): {:.1}% similar",
+                original, output.old_size, other, output.new_size, output.similarity * 100.0
+            ),
+            format!(
+                "{} equal, {} deleted, {} inserted bytes in {} region(s):",
+                output.equal, output.deleted, output.inserted, output.region_count
+            ),
+        ];
+        for region in &output.regions {
+            let mut line = format!(
+                "  {:<8} 0x{:08X}-0x{:08X} → 0x{:08X}-0x{:08X}",
+                region.kind,
+                region.old_offset,
+                region.old_offset + region.old_length,
+                region.new_offset,
+                region.new_offset + region.new_length
+            );
+            if !region.bookmarks.is_empty() {
+                line.push_str(&format!("  🔖 {}", region.bookmarks.join(", ")));
+            }
+            if !region.segments.is_empty() {
+                line.push_str(&format!("  ▣ {}", region.segments.join(", ")));
+            }
+            lines.push(line);
+        }
+        let unlisted = output.region_count as usize - output.regions.len();
+        if unlisted > 0 {
+            lines.push(format!("... {} more region(s); raise max_regions to list them", unlisted));
+        }
+        
+        structured_result(lines.join("\n"), &output)
+    }
+}
+
+//****************//
+//  CreatePatch   //
+//****************//
+#[mcp_tool(
+    name = "create_patch",
+    description = "Creates an IPS, BPS or bsdiff patch turning the buffer (or a segment) into a target file or segment. Returns the patch as an embedded resource, or writes it under the server's output directory, replacing any file already there",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = true,
+    open_world_hint = true
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct CreatePatch {
+    /// Patch format: 'ips', 'bps' or 'bsdiff'
+    pub format: String,
+    /// Path of the target file on the server
+    pub target_path: Option<String>,
+    /// Segment label or index holding the target
+    pub target_segment: Option<String>,
+    /// Segment label or index to patch instead of the buffer
+    pub segment: Option<String>,
+    /// Relative path under the output directory to write the patch to
+    pub file: Option<String>,
+}
+
+impl CreatePatch {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>, output_dir: Option<&Path>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let format = PatchFormat::parse(&self.format).map_err(CallToolError::from_message)?;
+        let target_file = match (&self.target_path, &self.target_segment) {
+            (Some(path), None) => Some(fs::read(path).await.map_err(|e| {
+                CallToolError::from_message(format!("Failed to read '{}': {}", path, e))
+            })?),
+            (None, Some(_)) => None,
+            _ => return Err(CallToolError::from_message(
+                "Give exactly one of target_path and target_segment",
+            )),
+        };
+
+        // Diff and compress on a blocking thread
+        let s = state.clone().read_owned().await;
+        let source = s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?;
+        let target_segment = s.resolve_segment(self.target_segment.as_deref())
+            .map_err(CallToolError::from_message)?;
+        let (data, old_size, new_size) = tokio::task::spawn_blocking(move || {
+            let old = s.source(source);
+            let new = match &target_file {
+                Some(data) => &data[..],
+                None => s.source(target_segment),
+            };
+            patch::create(format, old, new).map(|data| (data, old.len(), new.len()))
+        })
+        .await
+        .map_err(|e| CallToolError::from_message(format!("Patch creation failed: {}", e)))?
+        .map_err(CallToolError::from_message)?;
+
+        let mut result = match &self.file {
+            Some(file) => write_output(&data, file, output_dir).await?,
+            None => export(format!("{}patch.{}", resources::SCHEME, format.extension()), &data),
+        };
+        result.content.insert(0, TextContent::from(format!(
+            "🩹 {} patch of {} bytes turns {} ({} bytes) into {} bytes",
+            format.name(),
+            data.len(),
+            self.segment.as_ref().map_or("the buffer".to_string(), |seg| format!("segment '{}'", seg)),
+            old_size,
+            new_size
+        )).into());
+        Ok(result)
+    }
+}
+
+//***************//
+//  ApplyPatch   //
+//***************//
+#[mcp_tool(
+    name = "apply_patch",
+    description = "Applies an IPS, BPS or bsdiff patch to the buffer (or a segment). BPS checksums are always verified; expected_sha256 verifies the result of any format. Bookmarks, notes and segments are kept, except those past the end of a shrunk buffer, which are clipped or dropped; undo reverts the patch",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = false,
+    open_world_hint = true
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct ApplyPatch {
+    /// Path of a patch file on the server
+    pub patch_path: Option<String>,
+    /// Base64 encoded patch sent by the client
+    pub patch_data: Option<String>,
+    /// 'ips', 'bps' or 'bsdiff'; detected from the patch header when omitted
+    pub format: Option<String>,
+    /// Expected SHA-256 (hex) of the patched buffer; nothing is changed on mismatch
+    pub expected_sha256: Option<String>,
+    /// Segment label or index to patch instead of the buffer
+    pub segment: Option<String>,
+}
+
+impl ApplyPatch {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let patch_bytes = match (&self.patch_path, &self.patch_data) {
+            (Some(path), None) => fs::read(path).await.map_err(|e| {
+                CallToolError::from_message(format!("Failed to read '{}': {}", path, e))
+            })?,
+            (None, Some(data)) => BASE64.decode(data)
+                .map_err(|e| CallToolError::from_message(format!("Invalid base64 patch: {}", e)))?,
+            _ => return Err(CallToolError::from_message(
+                "Give exactly one of patch_path and patch_data",
+            )),
+        };
+        let format = match &self.format {
+            Some(name) => PatchFormat::parse(name).map_err(CallToolError::from_message)?,
+            None => PatchFormat::detect(&patch_bytes).ok_or_else(|| CallToolError::from_message(
+                "Unrecognized patch header; give the format explicitly",
+            ))?,
+        };
+
+        // Hold the write lock from reading the old bytes to storing the new ones,
+        // so no other call's change is lost. Patch on a blocking thread.
+        let s = state.clone().write_owned().await;
+        let target = s.resolve_segment(self.segment.as_deref()).map_err(CallToolError::from_message)?;
+        let (mut s, patched) = tokio::task::spawn_blocking(move || {
+            let patched = patch::apply(format, s.source(target), &patch_bytes);
+            (s, patched)
+        })
+        .await
+        .map_err(|e| CallToolError::from_message(format!("Patching failed: {}", e)))?;
+        let patched = patched.map_err(CallToolError::from_message)?;
+
+        let digest = hex::encode(Sha256::digest(&patched));
+        if let Some(expected) = &self.expected_sha256 {
+            if !expected.eq_ignore_ascii_case(&digest) {
+                return Err(CallToolError::from_message(format!(
+                    "SHA-256 mismatch: patched {} is {}, expected {}; nothing was changed",
+                    if target.is_some() { "segment" } else { "buffer" }, digest, expected
+                )));
+            }
+        }
+
+        let old_size = s.source(target).len();
+        let size = patched.len();
+        let clipped = match target {
+            Some(index) => {
+                s.segments[index].data = patched;
+                s.mark_changed(StateChange::Segments);
+                Vec::new()
+            }
+            None => {
+                s.buffer = patched;
+                s.mark_changed(StateChange::Buffer);
+                s.clip_to_buffer(size)
+            }
+        };
+
+        let verified = match (format.has_checksums(), self.expected_sha256.is_some()) {
+            (true, true) => "; CRC32s and SHA-256 verified",
+            (true, false) => "; CRC32s verified",
+            (false, true) => "; SHA-256 verified",
+            (false, false) => "",
+        };
+        let mut text = format!(
+            "✅ Applied {} patch: {} is now {} bytes (was {}), SHA-256 {}{}",
+            format.name(),
+            self.segment.as_ref().map_or("buffer".to_string(), |seg| format!("segment '{}'", seg)),
+            size, old_size, digest, verified
+        );
+        if !clipped.is_empty() {
+            text.push_str(&format!("\n\n✂️ {} annotation(s) past the new end:", clipped.len()));
+            clipped.iter().for_each(|line| text.push_str(&format!("\n  {}", line)));
+        }
+        Ok(CallToolResult::text_content(vec![TextContent::from(text)]))
+    }
+}
+
+//************//
+//  AddNote   //
+//************//
+#[mcp_tool(
+    name = "add_note",
+    description = "Adds an analysis note, optionally anchored to an offset, a range or a bookmark, with tags",
+    read_only_hint = false,
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct AddNote {
+    /// The analysis note text
+    pub note: String,
+    /// Offset the note describes
+    pub offset: Option<u64>,
+    /// Number of bytes described from offset (default 0: a single position)
+    pub length: Option<u64>,
+    /// Bookmark the note describes; its range is used when no offset is given
+    pub bookmark: Option<String>,
+    /// Tags for filtering
+    pub tags: Option<Vec<String>>,
+}
+
+impl AddNote {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>, author: &str) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let mut s = state.write().await;
+        
+        let (mut offset, mut length) = (self.offset.map(|o| o as usize), self.length.unwrap_or(0) as usize);
+        if let Some(name) = &self.bookmark {
+            let bookmark = s.bookmarks.get(name)
+                .ok_or_else(|| CallToolError::from_message(format!("No bookmark named '{}'", name)))?;
+            if offset.is_none() {
+                (offset, length) = (Some(bookmark.offset), bookmark.length);
+            }
+        }
+        if offset.is_some_and(|o| o.checked_add(length).is_none_or(|end| end > s.buffer.len())) {
+            return Err(CallToolError::from_message("Range exceeds buffer bounds"));
+        }
+        
+        let id = s.add_note(Note {
+            text: self.note.clone(),
+            offset,
+            length,
+            bookmark: self.bookmark.clone(),
+            tags: self.tags.clone().unwrap_or_default(),
+            author: author.to_string(),
+            ..Default::default()
+        });
+        
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!("✅ Note #{} added", id))
+        ]))
     }
 }
 
-//************//
-//  AddNote   //
-//************//
+//**************//
+//  ListNotes   //
+//**************//
 #[mcp_tool(
-    name = "add_note",
-    description = "Adds a textual analysis note to the current session"
+    name = "list_notes",
+    description = "Lists analysis notes, optionally only those overlapping a range, carrying a tag, written by an author or containing a text",
+    read_only_hint = true,
+    open_world_hint = false
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
-pub struct AddNote {
-    /// The analysis note text
-    pub note: String,
+pub struct ListNotes {
+    /// Only notes anchored at or overlapping this offset
+    pub offset: Option<u64>,
+    /// Length of the range starting at offset (default 1)
+    pub length: Option<u64>,
+    /// Only notes carrying this tag
+    pub tag: Option<String>,
+    /// Only notes containing this text (case-insensitive)
+    pub text: Option<String>,
+    /// Only notes written by this session
+    pub author: Option<String>,
 }
 
-impl AddNote {
+impl ListNotes {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let s = state.read().await;
+        let text = self.text.as_ref().map(|t| t.to_lowercase());
+        let range = self.offset
+            .map(|offset| {
+                let start = offset as usize;
+                start.checked_add(self.length.unwrap_or(1) as usize)
+                    .map(|end| (start, end))
+                    .ok_or_else(|| CallToolError::from_message("Range exceeds the addressable size"))
+            })
+            .transpose()?;
+        
+        let lines: Vec<String> = s.analysis_notes.iter()
+            .filter(|n| range.is_none_or(|(start, end)| n.overlaps(start, end)))
+            .filter(|n| self.tag.as_ref().is_none_or(|tag| n.tags.contains(tag)))
+            .filter(|n| self.author.as_ref().is_none_or(|author| &n.author == author))
+            .filter(|n| text.as_ref().is_none_or(|t| n.text.to_lowercase().contains(t)))
+            .map(|n| format!("  {}", n.describe()))
+            .collect();
+        
+        let text = if lines.is_empty() {
+            "No notes".to_string()
+        } else {
+            format!("Notes ({}):\n{}", lines.len(), lines.join("\n"))
+        };
+        Ok(CallToolResult::text_content(vec![TextContent::from(text)]))
+    }
+}
+
+//***************//
+//  UpdateNote   //
+//***************//
+#[mcp_tool(
+    name = "update_note",
+    description = "Edits the text, tags or anchor of an analysis note",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct UpdateNote {
+    /// Note id
+    pub id: u64,
+    /// New text
+    pub note: Option<String>,
+    /// New tags, replacing the current ones
+    pub tags: Option<Vec<String>>,
+    /// New anchor offset
+    pub offset: Option<u64>,
+    /// New anchor length
+    pub length: Option<u64>,
+}
+
+impl UpdateNote {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let mut s = state.write().await;
+        let buffer_len = s.buffer.len();
+        
+        let note = s.analysis_notes.iter_mut()
+            .find(|n| n.id == self.id)
+            .ok_or_else(|| CallToolError::from_message(format!("No note #{}", self.id)))?;
+        
+        let offset = self.offset.map(|o| o as usize).or(note.offset);
+        let length = self.length.map(|l| l as usize).unwrap_or(note.length);
+        if offset.is_some_and(|o| o.checked_add(length).is_none_or(|end| end > buffer_len)) {
+            return Err(CallToolError::from_message("Range exceeds buffer bounds"));
+        }
+        
+        if let Some(text) = &self.note {
+            note.text = text.clone();
+        }
+        if let Some(tags) = &self.tags {
+            note.tags = tags.clone();
+        }
+        // A moved note no longer describes the bookmark it was attached to
+        let detached = if (offset, length) != (note.offset, note.length) {
+            note.bookmark.take()
+        } else {
+            None
+        };
+        (note.offset, note.length) = (offset, length);
+        note.updated = Some(unix_time());
+        s.mark_changed(StateChange::Notes);
+        
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!(
+                "✅ Note #{} updated{}",
+                self.id,
+                detached.map(|b| format!("; detached from bookmark '{}'", b)).unwrap_or_default()
+            ))
+        ]))
+    }
+}
+
+//***************//
+//  DeleteNote   //
+//***************//
+#[mcp_tool(
+    name = "delete_note",
+    description = "Deletes an analysis note",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct DeleteNote {
+    /// Note id
+    pub id: u64,
+}
+
+impl DeleteNote {
     pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
         -> Result<CallToolResult, CallToolError> 
     {
         let mut s = state.write().await;
-        s.analysis_notes.push(self.note.clone());
-        s.display();
+        
+        let index = s.analysis_notes.iter()
+            .position(|n| n.id == self.id)
+            .ok_or_else(|| CallToolError::from_message(format!("No note #{}", self.id)))?;
+        s.analysis_notes.remove(index);
+        s.mark_changed(StateChange::Notes);
         
         Ok(CallToolResult::text_content(vec![
-            TextContent::from("✅ Note added")
+            TextContent::from(format!("✅ Note #{} deleted", self.id))
         ]))
     }
 }
@@ -418,7 +2650,11 @@ impl AddNote {
 //**************//
 #[mcp_tool(
     name = "set_output",
-    description = "Sets the final analysis output text"
+    description = "Sets the final analysis output text",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = true,
+    open_world_hint = false
 )]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
 pub struct SetOutput {
@@ -427,12 +2663,23 @@ pub struct SetOutput {
 }
 
 impl SetOutput {
+    /// Describes the output that would be overwritten, if any.
+    pub fn pending_loss(&self, s: &ServerState) -> Option<String> {
+        if s.output.is_empty() || s.output == self.text {
+            return None;
+        }
+        Some(format!(
+            "This will replace the current output ({} characters).",
+            s.output.chars().count()
+        ))
+    }
+
     pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>) 
         -> Result<CallToolResult, CallToolError> 
     {
         let mut s = state.write().await;
         s.output = self.text.clone();
-        s.display();
+        s.mark_changed(StateChange::Output);
         
         Ok(CallToolResult::text_content(vec![
             TextContent::from("✅ Output set")
@@ -440,6 +2687,352 @@ impl SetOutput {
     }
 }
 
+//********************//
+//  SummarizeRegion   //
+//********************//
+#[mcp_tool(
+    name = "summarize_region",
+    description = "Asks the client's model to summarize a decoded structure or byte range and stores the answer as a model-generated note",
+    read_only_hint = false,
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = true
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct SummarizeRegion {
+    /// Starting offset
+    pub offset: u64,
+    /// Number of bytes (at most 512 are sent to the model)
+    pub length: u64,
+    /// What is already known, e.g. decoded field values or the suspected format
+    pub context: Option<String>,
+}
+
+impl SummarizeRegion {
+    pub fn sampling_request(&self, s: &ServerState) -> Result<SamplingRequest, CallToolError> {
+        let start = self.offset as usize;
+        let end = start
+            .checked_add(self.length as usize)
+            .filter(|&end| end <= s.buffer.len() && self.length > 0)
+            .ok_or_else(|| CallToolError::from_message("Range exceeds buffer bounds"))?;
+        let shown = &s.buffer[start..end.min(start + MAX_SAMPLED_BYTES)];
+
+        let context = self.context.as_deref()
+            .map(|c| format!("\nWhat is known so far: {}\n", c))
+            .unwrap_or_default();
+        Ok(SamplingRequest {
+            subject: format!("region 0x{:08X}-0x{:08X}", start, end),
+            offset: Some(start),
+            length: end - start,
+            bookmark: None,
+            prompt: format!(
+                "Summarize the structure at offset 0x{:08X} ({} bytes) of '{}'.{}\n\
+                 Bytes (hex{}):\n{}\n\n\
+                 Describe what it most likely is and its fields, in at most five sentences.",
+                start,
+                end - start,
+                s.buffer_name(),
+                context,
+                if shown.len() < end - start { ", truncated" } else { "" },
+                hex::encode(shown)
+            ),
+            max_tokens: 400,
+        })
+    }
+}
+
+//************************//
+//  ProposeBookmarkName   //
+//************************//
+#[mcp_tool(
+    name = "propose_bookmark_name",
+    description = "Asks the client's model to propose a descriptive name for a bookmark and stores the proposal as a model-generated note",
+    read_only_hint = false,
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = true
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct ProposeBookmarkName {
+    /// Current name of the bookmark
+    pub name: String,
+}
+
+impl ProposeBookmarkName {
+    pub fn sampling_request(&self, s: &ServerState) -> Result<SamplingRequest, CallToolError> {
+        let bookmark = s.bookmarks.get(&self.name)
+            .ok_or_else(|| CallToolError::from_message(format!("No bookmark named '{}'", self.name)))?;
+        let offset = bookmark.offset;
+        let bytes = s.buffer.get(offset..).ok_or_else(|| CallToolError::from_message(format!(
+            "Bookmark '{}' at 0x{:08X} lies past the end of the {} byte buffer",
+            self.name, offset, s.buffer.len()
+        )))?;
+        let bytes = &bytes[..bytes.len().min(64)];
+
+        Ok(SamplingRequest {
+            subject: format!("bookmark '{}'", self.name),
+            offset: Some(offset),
+            length: bookmark.length,
+            bookmark: Some(self.name.clone()),
+            prompt: format!(
+                "A bookmark named '{}' marks offset 0x{:08X} of '{}'. The 64 bytes there are \
+                 (hex):\n{}\n\n\
+                 Propose one short snake_case name describing what starts at this offset. \
+                 Reply with the name, then one sentence of justification.",
+                self.name,
+                offset,
+                s.buffer_name(),
+                hex::encode(bytes)
+            ),
+            max_tokens: 100,
+        })
+    }
+}
+
+//********************//
+//  ClassifyStrings   //
+//********************//
+#[mcp_tool(
+    name = "classify_strings",
+    description = "Extracts printable strings from a range and asks the client's model to classify them (paths, URLs, keys, messages, ...), storing the answer as a model-generated note",
+    read_only_hint = false,
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = true
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct ClassifyStrings {
+    /// Starting offset (default 0)
+    pub offset: Option<u64>,
+    /// Number of bytes to scan (default: to the end of the buffer)
+    pub length: Option<u64>,
+    /// Minimum string length (default 4)
+    pub min_length: Option<u64>,
+}
+
+impl ClassifyStrings {
+    /// Strings sent to the model at most.
+    const MAX_STRINGS: usize = 100;
+
+    pub fn sampling_request(&self, s: &ServerState) -> Result<SamplingRequest, CallToolError> {
+        let start = self.offset.unwrap_or(0) as usize;
+        let end = match self.length {
+            Some(length) => start.checked_add(length as usize),
+            None => Some(s.buffer.len()),
+        }
+        .filter(|&end| start <= end && end <= s.buffer.len())
+        .ok_or_else(|| CallToolError::from_message("Range exceeds buffer bounds"))?;
+
+        let strings = sampling::ascii_strings(&s.buffer[start..end], self.min_length.unwrap_or(4) as usize);
+        if strings.is_empty() {
+            return Err(CallToolError::from_message("No printable strings found in range"));
+        }
+        let listed = strings.iter()
+            .take(Self::MAX_STRINGS)
+            .map(|(offset, text)| format!("0x{:08X}: {}", start + offset, text))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(SamplingRequest {
+            subject: format!("strings in 0x{:08X}-0x{:08X}", start, end),
+            offset: Some(start),
+            length: end - start,
+            bookmark: None,
+            prompt: format!(
+                "These printable strings were found in '{}' ({} of {} shown):\n{}\n\n\
+                 Group them into clusters (e.g. file paths, URLs or hosts, crypto constants or \
+                 keys, error messages, format strings, compiler or library artifacts) and say \
+                 what the clusters suggest about the binary.",
+                s.buffer_name(),
+                strings.len().min(Self::MAX_STRINGS),
+                strings.len(),
+                listed
+            ),
+            max_tokens: 600,
+        })
+    }
+}
+
+//*********//
+//  Undo   //
+//*********//
+#[mcp_tool(
+    name = "undo",
+    description = "Reverts the most recent state changes (loads, bookmarks, segments, notes, output)",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = false,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct Undo {
+    /// Number of operations to undo (default 1)
+    pub steps: Option<u64>,
+}
+
+impl Undo {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>, history: &RwLock<History>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let mut s = state.write().await;
+        let undone = history.write().await.undo(&mut s, self.steps.unwrap_or(1) as usize);
+        
+        if undone.is_empty() {
+            return Err(CallToolError::from_message("Nothing to undo"));
+        }
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!("↩️ Undid {} operation(s):\n  {}", undone.len(), undone.join("\n  ")))
+        ]))
+    }
+}
+
+//*********//
+//  Redo   //
+//*********//
+#[mcp_tool(
+    name = "redo",
+    description = "Re-applies operations reverted with undo; any new change clears what can be redone",
+    read_only_hint = false,
+    destructive_hint = true,
+    idempotent_hint = false,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct Redo {
+    /// Number of operations to redo (default 1)
+    pub steps: Option<u64>,
+}
+
+impl Redo {
+    pub async fn call_tool(&self, state: &Arc<RwLock<ServerState>>, history: &RwLock<History>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let mut s = state.write().await;
+        let redone = history.write().await.redo(&mut s, self.steps.unwrap_or(1) as usize);
+        
+        if redone.is_empty() {
+            return Err(CallToolError::from_message("Nothing to redo"));
+        }
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!("↪️ Redid {} operation(s):\n  {}", redone.len(), redone.join("\n  ")))
+        ]))
+    }
+}
+
+//****************//
+//  ListHistory   //
+//****************//
+#[mcp_tool(
+    name = "list_history",
+    description = "Lists the operations that can be undone, oldest first, and those that can be redone",
+    read_only_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct ListHistory {}
+
+impl ListHistory {
+    pub async fn call_tool(&self, history: &RwLock<History>) 
+        -> Result<CallToolResult, CallToolError> 
+    {
+        let (undo, redo) = history.read().await.list();
+        
+        let mut text = if undo.is_empty() {
+            "Nothing to undo".to_string()
+        } else {
+            format!(
+                "Undoable ({}):\n{}",
+                undo.len(),
+                undo.iter().enumerate()
+                    .map(|(i, op)| format!("  {}. {}", i + 1, op))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        };
+        if !redo.is_empty() {
+            text.push_str(&format!("\nRedoable ({}):\n  {}", redo.len(), redo.join("\n  ")));
+        }
+        Ok(CallToolResult::text_content(vec![TextContent::from(text)]))
+    }
+}
+
+//*******************//
+//  ListToolGroups   //
+//*******************//
+#[mcp_tool(
+    name = "list_tool_groups",
+    description = "Lists the tool groups and whether each is enabled for this session",
+    read_only_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct ListToolGroups {}
+
+impl ListToolGroups {
+    pub async fn call_tool(&self, groups: &ToolGroups)
+        -> Result<CallToolResult, CallToolError>
+    {
+        let lines = ToolGroup::ALL.iter()
+            .map(|g| format!(
+                "  [{}] {:<12} {}",
+                if groups.contains(*g) { "x" } else { " " },
+                g.name(),
+                g.description()
+            ))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(CallToolResult::text_content(vec![
+            TextContent::from(format!("Tool groups:\n{}", lines))
+        ]))
+    }
+}
+
+//******************//
+//  SetToolGroup    //
+//******************//
+#[mcp_tool(
+    name = "set_tool_group",
+    description = "Enables or disables a tool group (core, annotation, editing, crypto, sampling) for this session or for all sessions",
+    read_only_hint = false,
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false
+)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct SetToolGroup {
+    /// Group name: 'core', 'annotation', 'editing', 'crypto' or 'sampling'
+    pub group: String,
+    /// Whether the group should be enabled
+    pub enabled: bool,
+    /// Apply to every session and to new sessions (default: this session only)
+    pub all_sessions: Option<bool>,
+}
+
+impl SetToolGroup {
+    pub fn parse_group(&self) -> Result<ToolGroup, CallToolError> {
+        ToolGroup::parse(&self.group)
+            .ok_or_else(|| CallToolError::from_message(format!("Unknown tool group '{}'", self.group)))
+    }
+
+    pub async fn call_tool(&self, groups: &mut ToolGroups)
+        -> Result<CallToolResult, CallToolError>
+    {
+        let group = self.parse_group()?;
+        let changed = groups.set(group, self.enabled)
+            .map_err(CallToolError::from_message)?;
+
+        let status = if self.enabled { "enabled" } else { "disabled" };
+        let text = if changed {
+            format!("✅ Tool group '{}' {}", group.name(), status)
+        } else {
+            format!("Tool group '{}' already {}", group.name(), status)
+        };
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(text)]))
+    }
+}
+
 //*****************//
 //  BinaryTools    //
 //*****************//
@@ -447,15 +3040,401 @@ tool_box!(
     BinaryTools,
     [
         LoadBinary,
+        LoadBytes,
+        LoadHex,
         ReadBytes,
+        Hexdump,
         SearchPattern,
         ExtractSegment,
+        ExportRange,
+        ExportSegment,
+        SaveBytes,
+        ListSegments,
+        RemoveSegment,
+        RelabelSegment,
         AddBookmark,
+        ListBookmarks,
+        RenameBookmark,
+        RemoveBookmark,
+        BookmarksAt,
         ReadString,
         ReadInteger,
         CalculateHash,
+        CompareHashes,
+        CalculateEntropy,
         GetInfo,
+        DiffBuffers,
+        CreatePatch,
+        ApplyPatch,
         AddNote,
-        SetOutput
+        ListNotes,
+        UpdateNote,
+        DeleteNote,
+        SetOutput,
+        SummarizeRegion,
+        ProposeBookmarkName,
+        ClassifyStrings,
+        Undo,
+        Redo,
+        ListHistory,
+        ListToolGroups,
+        SetToolGroup
     ]
 );
+
+/// All tools with the output schemas of those returning structured content.
+pub fn tool_list() -> Vec<Tool> {
+    BinaryTools::tools()
+        .into_iter()
+        .map(|mut tool| {
+            tool.output_schema = match tool.name.as_str() {
+                "read_bytes" => Some(output_schema(ReadBytesOutput::json_schema())),
+                "search_pattern" => Some(output_schema(SearchPatternOutput::json_schema())),
+                "read_integer" => Some(output_schema(ReadIntegerOutput::json_schema())),
+                "diff_buffers" => Some(output_schema(DiffBuffersOutput::json_schema())),
+                "calculate_hash" => Some(output_schema(CalculateHashOutput::json_schema())),
+                "compare_hashes" => Some(output_schema(CompareHashesOutput::json_schema())),
+                "calculate_entropy" => Some(output_schema(CalculateEntropyOutput::json_schema())),
+                "get_info" => Some(output_schema(GetInfoOutput::json_schema())),
+                _ => None,
+            };
+            tool
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_mcp_sdk::schema::ContentBlock;
+    use std::sync::atomic::AtomicBool;
+
+    fn state() -> Arc<RwLock<ServerState>> {
+        Arc::new(RwLock::new(ServerState::new()))
+    }
+
+    /// The text blocks of a result, joined by newlines.
+    fn text(result: &CallToolResult) -> String {
+        result.content.iter()
+            .filter_map(|block| match block {
+                ContentBlock::TextContent(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn chunk(id: &str, data: &[u8], offset: Option<u64>, finalize: bool) -> LoadBytes {
+        LoadBytes {
+            data: BASE64.encode(data),
+            name: None,
+            upload_id: Some(id.to_string()),
+            chunk_offset: offset,
+            finalize: Some(finalize),
+            sha256: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn chunked_uploads_are_checked_before_they_are_kept() {
+        let state = state();
+        chunk("a", b"MZ", Some(0), false).call_tool(&state).await.unwrap();
+        assert!(chunk("a", b"??", Some(5), false).call_tool(&state).await.is_err());
+        assert!(chunk("b", b"??", Some(2), false).call_tool(&state).await.is_err());
+        assert_eq!(state.read().await.uploads.len(), 1);
+
+        let mut last = chunk("a", b"\x90\x00", Some(2), true);
+        last.sha256 = Some(hex::encode(Sha256::digest(b"MZ\x90\x00")));
+        last.call_tool(&state).await.unwrap();
+        let s = state.read().await;
+        assert_eq!(s.buffer, b"MZ\x90\x00");
+        assert!(s.uploads.is_empty());
+    }
+
+    #[tokio::test]
+    async fn the_idle_upload_is_dropped_past_the_limit() {
+        let state = state();
+        for i in 0..LoadBytes::MAX_PENDING_UPLOADS {
+            chunk(&i.to_string(), b"x", None, false).call_tool(&state).await.unwrap();
+        }
+        state.write().await.uploads.get_mut("2").unwrap().updated = 0;
+        let result = chunk("new", b"x", None, false).call_tool(&state).await.unwrap();
+        assert!(text(&result).contains("Dropped the idle upload '2'"));
+        let s = state.read().await;
+        assert_eq!(s.uploads.len(), LoadBytes::MAX_PENDING_UPLOADS);
+        assert!(!s.uploads.contains_key("2"));
+    }
+
+    async fn loaded(data: &[u8]) -> Arc<RwLock<ServerState>> {
+        let state = state();
+        state.write().await.replace_buffer(data.to_vec(), "test".to_string());
+        state
+    }
+
+    #[tokio::test]
+    async fn reads_past_the_end_are_rejected() {
+        let state = loaded(b"MZ\x90\x00payload").await;
+        state.write().await.segments.push(crate::state::BinarySegment {
+            offset: 4,
+            data: b"payload".to_vec(),
+            label: Some("body".to_string()),
+        });
+        let read = |offset, length, segment: Option<&str>| ReadBytes {
+            offset,
+            length,
+            segment: segment.map(str::to_string),
+        };
+        assert!(read(4, 7, Some("body")).call_tool(&state).await.is_err());
+        assert!(read(u64::MAX, 2, None).call_tool(&state).await.is_err());
+        assert!(text(&read(2, 2, Some("body")).call_tool(&state).await.unwrap()).contains("796c"));
+
+        let integer = |offset, size| ReadInteger { offset, size, endian: "little".to_string(), segment: None };
+        assert!(integer(u64::MAX, 4).call_tool(&state).await.is_err());
+        assert!(integer(8, 4).call_tool(&state).await.is_err());
+        assert!(text(&integer(0, 2).call_tool(&state).await.unwrap()).contains("23117"));
+    }
+
+    fn hexdump(length: u64) -> Hexdump {
+        Hexdump {
+            offset: None,
+            length: Some(length),
+            width: None,
+            group: None,
+            base: None,
+            base_address: None,
+            segment: None,
+            overlays: Some(false),
+            page: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn hexdump_layout() {
+        let state = loaded(b"MZ\x90\x00payload!\x00\x01 ~ABCD").await;
+        let dump = text(&hexdump(20).call_tool(&state).await.unwrap());
+        assert_eq!(dump.lines().skip(1).collect::<Vec<_>>(), [
+            "00000000  4d 5a 90 00 70 61 79 6c  6f 61 64 21 00 01 20 7e  |MZ..payload!.. ~|",
+            "00000010  41 42 43 44                                       |ABCD|",
+        ]);
+
+        let xxd = Hexdump { width: Some(8), group: Some(2), base: Some("dec".to_string()), ..hexdump(20) };
+        let dump = text(&xxd.call_tool(&state).await.unwrap());
+        assert_eq!(dump.lines().skip(1).collect::<Vec<_>>(), [
+            "0000000000  4d5a 9000 7061 796c  |MZ..payl|",
+            "0000000008  6f61 6421 0001 207e  |oad!.. ~|",
+            "0000000016  4142 4344            |ABCD|",
+        ]);
+
+        let high = Hexdump { width: Some(4), base_address: Some(0xFFFF_FFFF_FFFF_FFFC), ..hexdump(8) };
+        let dump = text(&high.call_tool(&state).await.unwrap());
+        assert_eq!(dump.lines().skip(1).collect::<Vec<_>>(), [
+            "fffffffffffffffc  4d 5a 90 00  |MZ..|",
+            "00000000  70 61 79 6c  |payl|",
+        ]);
+        assert!(Hexdump { width: Some(65), ..hexdump(8) }.call_tool(&state).await.is_err());
+        assert!(Hexdump { base: Some("oct".to_string()), ..hexdump(8) }.call_tool(&state).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn note_anchors_follow_their_bookmark() {
+        let state = loaded(&[0; 64]).await;
+        AddBookmark {
+            name: "header".to_string(),
+            offset: 0,
+            length: Some(16),
+            kind: None,
+            color: None,
+            comment: None,
+            tags: None,
+        }
+        .call_tool(&state).await.unwrap();
+        let note = |bookmark: &str| AddNote {
+            note: "text".to_string(),
+            offset: None,
+            length: None,
+            bookmark: Some(bookmark.to_string()),
+            tags: None,
+        };
+        for _ in 0..2 {
+            note("header").call_tool(&state, "test").await.unwrap();
+        }
+        let bookmarks = |s: &ServerState| s.analysis_notes.iter().map(|n| n.bookmark.clone()).collect::<Vec<_>>();
+
+        RenameBookmark { name: "header".to_string(), new_name: "hdr".to_string() }
+            .call_tool(&state).await.unwrap();
+        assert_eq!(bookmarks(&*state.read().await), [Some("hdr".to_string()), Some("hdr".to_string())]);
+
+        let update = UpdateNote { id: 1, note: None, tags: None, offset: Some(32), length: None };
+        assert!(text(&update.call_tool(&state).await.unwrap()).contains("detached from bookmark 'hdr'"));
+        RemoveBookmark { name: "hdr".to_string() }.call_tool(&state).await.unwrap();
+        {
+            let s = state.read().await;
+            assert_eq!(bookmarks(&s), [None, None]);
+            assert_eq!(s.analysis_notes[1].offset, Some(0));
+            assert_eq!(s.analysis_notes[1].length, 16);
+        }
+
+        let mut s = state.write().await;
+        s.take_changes();
+        s.replace_buffer(vec![1; 8], "other".to_string());
+        assert!(s.analysis_notes.iter().all(|n| n.offset.is_none() && n.bookmark.is_none()));
+        assert!(s.take_changes().contains(&StateChange::Notes));
+    }
+
+    #[tokio::test]
+    async fn structured_results_match_their_schemas() {
+        let state = loaded(b"MZ\x90\x00 hello MZ").await;
+        let tools = tool_list();
+        let check = |name: &str, result: CallToolResult| {
+            let schema = tools.iter().find(|t| t.name == name).and_then(|t| t.output_schema.clone())
+                .unwrap_or_else(|| panic!("{} has no output schema", name));
+            let properties = schema.properties.unwrap_or_default();
+            let structured = result.structured_content.unwrap_or_else(|| panic!("{} has no structured content", name));
+            for key in &schema.required {
+                assert!(structured.contains_key(key), "{} lacks {}", name, key);
+            }
+            for key in structured.keys() {
+                assert!(properties.contains_key(key), "{} returns {} outside its schema", name, key);
+            }
+            structured
+        };
+
+        let read = ReadBytes { offset: 0, length: 2, segment: None };
+        let output = check("read_bytes", read.call_tool(&state).await.unwrap());
+        assert_eq!(output["hex"], "4d5a");
+        assert!(!output.contains_key("segment"));
+
+        let integer = ReadInteger { offset: 0, size: 2, endian: "big".to_string(), segment: None };
+        assert_eq!(check("read_integer", integer.call_tool(&state).await.unwrap())["value"], 0x4D5A);
+
+        let search = SearchPattern { pattern: "4D5A".to_string(), segment: None, progress_token: None };
+        let output = check("search_pattern", search.call_tool(&state, JobContext::default()).await.unwrap());
+        assert_eq!(output["offsets"], serde_json::json!([0, 11]));
+
+        let hash = CalculateHash {
+            offset: None,
+            length: None,
+            segment: None,
+            algorithms: Some(vec!["md5".to_string(), "imphash".to_string()]),
+            progress_token: None,
+        };
+        let output = check("calculate_hash", hash.call_tool(&state, JobContext::default()).await.unwrap());
+        // A failing algorithm reports an error in place of its digest
+        let imphash = &output["digests"][1];
+        assert!(imphash.get("digest").is_none() && imphash["error"].is_string(), "{}", imphash);
+
+        let info = GetInfo {};
+        let output = check("get_info", info.call_tool(&state).await.unwrap());
+        assert_eq!(output["size"], 13);
+    }
+
+    #[tokio::test]
+    async fn cancelled_jobs_stop_with_an_error() {
+        let state = loaded(&vec![0; PROGRESS_STEP * 3]).await;
+        let cancelled = JobContext::new(Arc::new(AtomicBool::new(true)), None);
+
+        let search = SearchPattern { pattern: "01".to_string(), segment: None, progress_token: None };
+        let error = search.call_tool(&state, cancelled.clone()).await.unwrap_err();
+        assert_eq!(error.to_string(), "Search cancelled");
+        let hash = CalculateHash { offset: None, length: None, segment: None, algorithms: None, progress_token: None };
+        let error = hash.call_tool(&state, cancelled).await.unwrap_err();
+        assert_eq!(error.to_string(), "Hashing cancelled");
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let job = JobContext::new(Arc::new(AtomicBool::new(false)), Some(sender));
+        hash.call_tool(&state, job).await.unwrap();
+        let mut reports = Vec::new();
+        while let Ok(report) = receiver.try_recv() {
+            reports.push(report);
+        }
+        let total = (PROGRESS_STEP * 3) as u64;
+        assert_eq!(reports, (1..=3).map(|i| (i * PROGRESS_STEP as u64, total)).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn only_losing_work_asks_for_confirmation() {
+        let state = loaded(&[0; 16]).await;
+        let load = LoadBinary { path: "next.bin".to_string() };
+        let output = SetOutput { text: "report".to_string() };
+        assert_eq!(load.pending_loss(&*state.read().await), None);
+        assert_eq!(output.pending_loss(&*state.read().await), None);
+
+        AddBookmark {
+            name: "header".to_string(),
+            offset: 0,
+            length: Some(4),
+            kind: None,
+            color: None,
+            comment: None,
+            tags: None,
+        }
+        .call_tool(&state).await.unwrap();
+        AddNote { note: "text".to_string(), offset: None, length: None, bookmark: Some("header".to_string()), tags: None }
+            .call_tool(&state, "test").await.unwrap();
+        output.call_tool(&state).await.unwrap();
+
+        let s = state.read().await;
+        assert_eq!(
+            load.pending_loss(&s).unwrap(),
+            "Loading 'next.bin' will discard 1 bookmark(s) and 0 segment(s) of 'test' \
+             and detach 1 note(s) from their offsets."
+        );
+        let paste = LoadHex { hex: "00".to_string(), name: None };
+        assert!(paste.pending_loss(&s).unwrap().starts_with("Loading 'the pasted hex'"));
+        // Intermediate chunks of an upload don't load anything yet
+        assert_eq!(chunk("a", b"x", None, false).pending_loss(&s), None);
+        assert!(chunk("a", b"x", None, true).pending_loss(&s).unwrap().starts_with("Loading 'a'"));
+
+        assert_eq!(output.pending_loss(&s), None);
+        let other = SetOutput { text: "other".to_string() };
+        assert_eq!(other.pending_loss(&s).unwrap(), "This will replace the current output (6 characters).");
+    }
+
+    #[test]
+    fn hex_dumps_parse() {
+        let parse = LoadHex::parse;
+        assert_eq!(parse("00401000  4D 5A 90 00  MZ..").unwrap(), b"MZ\x90\x00");
+        assert_eq!(parse("0000: 4d5a 9000").unwrap(), b"MZ\x90\x00");
+        assert_eq!(parse("0x4d, 0x5a,\n0X90").unwrap(), b"MZ\x90");
+        assert_eq!(parse("4d5a9000").unwrap(), b"MZ\x90\x00");
+        assert_eq!(parse("`4d-5a`").unwrap(), b"MZ");
+        assert_eq!(
+            parse("00000000  4d 5a 90 00 03 00 00 00  04 00 00 00 ff ff 00 00  |MZ..............|\n\
+                   00000010  b8 00                                             |..|\n").unwrap(),
+            hex::decode("4d5a90000300000004000000ffff0000b800").unwrap()
+        );
+        assert_eq!(parse("00000000: 4d5a 9000 0300 0000  MZ......").unwrap(), hex::decode("4d5a900003000000").unwrap());
+        assert_eq!(parse("\n  \n").unwrap(), b"");
+
+        assert_eq!(parse("4d5").unwrap_err(), "line 1: no hex bytes found");
+        assert_eq!(parse("4d 5a\n\nzz").unwrap_err(), "line 3: no hex bytes found");
+        assert_eq!(parse("0000:").unwrap(), b"", "an address alone is an empty line");
+    }
+
+    #[test]
+    fn output_paths_stay_in_the_output_directory() {
+        let dir = Path::new("/srv/out");
+        assert_eq!(output_path(Some(dir), "a.bin").unwrap(), dir.join("a.bin"));
+        assert_eq!(output_path(Some(dir), "carved/seg.bin").unwrap(), dir.join("carved/seg.bin"));
+        for file in ["", "/etc/passwd", "../a.bin", "carved/../../a.bin", "./a.bin"] {
+            let error = output_path(Some(dir), file).unwrap_err();
+            assert!(error.to_string().contains("must be a relative path"), "{}", file);
+        }
+        assert!(output_path(None, "a.bin").unwrap_err().to_string().contains("--output-dir"));
+    }
+
+    #[tokio::test]
+    async fn saved_bytes_land_in_the_output_directory() {
+        let dir = std::env::temp_dir().join(format!("save-test-{}", std::process::id()));
+        let state = loaded(b"MZ\x90\x00rest").await;
+        let save = SaveBytes { file: "sub/head.bin".to_string(), segment: None, offset: Some(0), length: Some(4) };
+        let result = save.call_tool(&state, Some(&dir)).await.unwrap();
+        assert!(text(&result).contains("application/vnd.microsoft.portable-executable"));
+        assert_eq!(std::fs::read(dir.join("sub/head.bin")).unwrap(), b"MZ\x90\x00");
+
+        let past_end = SaveBytes { file: "x".to_string(), segment: None, offset: Some(6), length: Some(4) };
+        assert!(past_end.call_tool(&state, Some(&dir)).await.is_err());
+        assert!(!dir.join("x").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,492 @@
+// ============================================================================
+// src/tui.rs
+// ============================================================================
+use crate::executor::Executor;
+use crate::handler::Publisher;
+use crate::jobs::JobContext;
+use crate::journal::Journal;
+use crate::state::ServerState;
+use crate::tools::BinaryTools;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Tabs, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use rust_mcp_sdk::schema::{
+    schema_utils::CallToolError, CallToolRequestParams, CallToolResult, ContentBlock,
+};
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::RwLock;
+
+/// Author recorded on notes added from the terminal UI, and its journal session.
+const AUTHOR: &str = "console";
+
+/// Log lines kept for the Log view.
+const MAX_LOG_LINES: usize = 1000;
+
+/// Bytes per hex view row.
+const HEX_WIDTH: usize = 16;
+
+/// Rows moved by PgUp/PgDn.
+const PAGE: usize = 20;
+
+/// Runs tool commands typed into the terminal UI the way MCP requests are
+/// run: through the shared executor and history, into the journal, and
+/// published to every session.
+pub struct Console {
+    pub executor: Arc<Executor>,
+    pub journal: Option<Arc<Journal>>,
+    pub publisher: Publisher,
+}
+
+impl Console {
+    pub async fn call(&self, tool: &str, arguments: Map<String, Value>) -> CallToolResult {
+        let params = CallToolRequestParams {
+            name: tool.to_string(),
+            arguments: Some(arguments.clone()),
+        };
+        let parsed = match BinaryTools::try_from(params) {
+            Ok(parsed) => parsed,
+            Err(e) => return CallToolResult::from(CallToolError::new(e)),
+        };
+        let mut call = self.executor.begin(&parsed).await;
+        let result = self.executor.run(parsed, AUTHOR, JobContext::default())
+            .await
+            .unwrap_or_else(CallToolResult::from);
+        let changes = self.executor.finish(&mut call).await;
+        if let Some(journal) = &self.journal {
+            let s = self.executor.state.read().await;
+            journal.record(&s, &changes, AUTHOR, tool, Some(arguments), &result);
+        }
+        drop(call);
+        self.publisher.publish(changes).await;
+        result
+    }
+}
+
+/// Tracing output captured for the Log view, since the terminal belongs to the UI.
+#[derive(Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<String>>>);
+
+impl LogBuffer {
+    fn lines(&self) -> Vec<String> {
+        self.0.lock().map(|lines| lines.iter().cloned().collect()).unwrap_or_default()
+    }
+}
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Ok(mut lines) = self.0.lock() {
+            for line in String::from_utf8_lossy(buf).lines() {
+                lines.push_back(line.to_string());
+            }
+            while lines.len() > MAX_LOG_LINES {
+                lines.pop_front();
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for LogBuffer {
+    type Writer = LogBuffer;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum View {
+    Hex,
+    Bookmarks,
+    Segments,
+    Notes,
+    Output,
+    Log,
+}
+
+impl View {
+    const ALL: [View; 6] = [
+        View::Hex,
+        View::Bookmarks,
+        View::Segments,
+        View::Notes,
+        View::Output,
+        View::Log,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            View::Hex => "Hex",
+            View::Bookmarks => "Bookmarks",
+            View::Segments => "Segments",
+            View::Notes => "Notes",
+            View::Output => "Output",
+            View::Log => "Log",
+        }
+    }
+
+    fn index(self) -> usize {
+        View::ALL.iter().position(|&v| v == self).unwrap_or(0)
+    }
+}
+
+struct App {
+    console: Console,
+    state: Arc<RwLock<ServerState>>,
+    logs: LogBuffer,
+    view: View,
+    /// Scroll position of each view; rows for the hex view, lines otherwise
+    scroll: [usize; View::ALL.len()],
+    input: String,
+    /// Output of the last command
+    result: String,
+}
+
+/// Shows the live analysis state until the user quits. Must run on a
+/// blocking thread of the server's runtime.
+pub fn run(console: Console, logs: LogBuffer) -> std::io::Result<()> {
+    let state = console.executor.state.clone();
+    let mut app = App {
+        console,
+        state,
+        logs,
+        view: View::Hex,
+        scroll: [0; View::ALL.len()],
+        input: String::new(),
+        result: "Type a tool command such as 'get_info' or 'read_bytes offset=0x10 length=32', \
+            or 'help'."
+            .to_string(),
+    };
+
+    let mut terminal = ratatui::init();
+    let outcome = app.run(&mut terminal);
+    ratatui::restore();
+    outcome
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        let handle = Handle::current();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            // Redraw periodically so changes made by MCP clients show up
+            if !event::poll(Duration::from_millis(250))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.on_key(key, &handle) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Handles a key press; returns false to quit.
+    fn on_key(&mut self, key: KeyEvent, handle: &Handle) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let last = self.line_count().saturating_sub(1);
+        let scroll = &mut self.scroll[self.view.index()];
+        match key.code {
+            KeyCode::Char('c') | KeyCode::Char('q') if ctrl => return false,
+            KeyCode::Tab => self.view = View::ALL[(self.view.index() + 1) % View::ALL.len()],
+            KeyCode::BackTab => {
+                self.view = View::ALL[(self.view.index() + View::ALL.len() - 1) % View::ALL.len()]
+            }
+            KeyCode::Up => *scroll = scroll.saturating_sub(1),
+            KeyCode::Down => *scroll = (*scroll + 1).min(last),
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(PAGE).min(last),
+            KeyCode::PageDown => *scroll = scroll.saturating_add(PAGE).min(last),
+            KeyCode::Home => *scroll = 0,
+            KeyCode::Esc => self.input.clear(),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Enter => {
+                let command = std::mem::take(&mut self.input);
+                self.execute(command.trim(), handle);
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            _ => {}
+        }
+        true
+    }
+
+    /// Number of lines in the current view; rows for the hex view.
+    fn line_count(&self) -> usize {
+        let s = self.state.blocking_read();
+        match self.view {
+            View::Hex => s.buffer.len().div_ceil(HEX_WIDTH),
+            View::Bookmarks => s.bookmarks.len(),
+            View::Segments => s.segments.len(),
+            View::Notes => s.analysis_notes.len(),
+            View::Output => s.output.lines().count(),
+            View::Log => self.logs.lines().len(),
+        }
+    }
+
+    fn execute(&mut self, command: &str, handle: &Handle) {
+        let (name, rest) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+        match name {
+            "" => {}
+            "help" => {
+                let tools: Vec<String> = BinaryTools::tools().into_iter().map(|t| t.name).collect();
+                self.result = format!(
+                    "Commands: <tool> [key=value ... | {{json}}], goto <offset>, help\n\
+                     Keys: Tab/Shift-Tab switch view, ↑↓ PgUp PgDn Home scroll, Esc clear, Ctrl-C quit\n\
+                     Tools: {}",
+                    tools.join(", ")
+                );
+            }
+            "goto" => match parse_value(rest.trim()) {
+                Value::Number(n) if n.as_u64().is_some() => {
+                    let offset = n.as_u64().unwrap_or_default() as usize;
+                    self.view = View::Hex;
+                    self.scroll[View::Hex.index()] = offset / HEX_WIDTH;
+                    self.result = format!("Showing 0x{:08X}", offset);
+                }
+                _ => self.result = format!("❌ '{}' is not an offset", rest.trim()),
+            },
+            tool => match parse_arguments(rest) {
+                Ok(arguments) => {
+                    let result = handle.block_on(self.console.call(tool, arguments));
+                    self.result = result_text(&result);
+                }
+                Err(e) => self.result = format!("❌ {}", e),
+            },
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [tabs, main, result, input] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(5),
+            Constraint::Length(8),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+
+        let s = self.state.blocking_read();
+        let titles = View::ALL.iter().map(|v| v.title());
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(self.view.index())
+                .highlight_style(Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            tabs,
+        );
+
+        let title = format!(
+            " {} · {} bytes · {} bookmarks · {} segments · {} notes ",
+            s.buffer_name(),
+            s.buffer.len(),
+            s.bookmarks.len(),
+            s.segments.len(),
+            s.analysis_notes.len()
+        );
+        let scroll = self.scroll[self.view.index()];
+        let lines = match self.view {
+            View::Hex => hex_lines(&s, scroll, main.height.saturating_sub(2) as usize),
+            View::Bookmarks => text_lines(
+                s.sorted_bookmarks().iter().map(|(name, b)| b.describe(name)),
+                "No bookmarks",
+            ),
+            View::Segments => text_lines(
+                s.segments.iter().enumerate().map(|(i, seg)| {
+                    format!(
+                        "[{}] 0x{:08X} {} bytes {}",
+                        i,
+                        seg.offset,
+                        seg.data.len(),
+                        seg.label.as_deref().unwrap_or("")
+                    )
+                }),
+                "No segments",
+            ),
+            View::Notes => {
+                text_lines(s.analysis_notes.iter().map(|n| n.describe()), "No notes")
+            }
+            View::Output => text_lines(s.output.lines().map(str::to_string), "No output"),
+            View::Log => text_lines(self.logs.lines().into_iter(), "No log messages"),
+        };
+        drop(s);
+        let line_count = lines.len();
+
+        let body = Paragraph::new(lines).block(Block::bordered().title(title));
+        let body = match self.view {
+            View::Hex => body,
+            _ => {
+                // The content may have shrunk since the last key press
+                let scroll = scroll.min(line_count.saturating_sub(1));
+                body.wrap(Wrap { trim: false }).scroll((u16::try_from(scroll).unwrap_or(u16::MAX), 0))
+            }
+        };
+        frame.render_widget(body, main);
+
+        frame.render_widget(
+            Paragraph::new(self.result.as_str())
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title(" Result ")),
+            result,
+        );
+        self.draw_input(frame, input);
+    }
+
+    fn draw_input(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(
+            Paragraph::new(format!("> {}", self.input))
+                .block(Block::bordered().title(" Command (help, Tab view, Ctrl-C quit) ")),
+            area,
+        );
+        let x = area.x + 3 + self.input.chars().count() as u16;
+        frame.set_cursor_position(Position::new(x.min(area.right().saturating_sub(2)), area.y + 1));
+    }
+}
+
+fn text_lines(items: impl Iterator<Item = String>, empty: &str) -> Vec<Line<'static>> {
+    let lines: Vec<Line> = items.map(Line::from).collect();
+    if lines.is_empty() {
+        vec![Line::from(empty.to_string())]
+    } else {
+        lines
+    }
+}
+
+/// Hex rows starting at row `first`, with bookmarked bytes in yellow and
+/// bytes of extracted segments in cyan.
+fn hex_lines(s: &ServerState, first: usize, rows: usize) -> Vec<Line<'static>> {
+    if s.buffer.is_empty() {
+        return vec![Line::from("No buffer loaded")];
+    }
+    let bookmarked = |offset: usize| s.bookmarks.values().any(|b| b.covers(offset));
+    let segmented = |offset: usize| {
+        s.segments.iter().any(|seg| {
+            let start = seg.offset as usize;
+            offset >= start && offset < start + seg.data.len()
+        })
+    };
+    let style = |offset: usize| {
+        if bookmarked(offset) {
+            Style::new().fg(Color::Yellow)
+        } else if segmented(offset) {
+            Style::new().fg(Color::Cyan)
+        } else {
+            Style::new()
+        }
+    };
+
+    let last_row = (s.buffer.len() - 1) / HEX_WIDTH;
+    (first.min(last_row)..=last_row)
+        .take(rows)
+        .map(|row| {
+            let start = row * HEX_WIDTH;
+            let bytes = &s.buffer[start..(start + HEX_WIDTH).min(s.buffer.len())];
+            let mut spans = vec![Span::styled(
+                format!("{:08X}  ", start),
+                Style::new().fg(Color::DarkGray),
+            )];
+            for i in 0..HEX_WIDTH {
+                let text = match bytes.get(i) {
+                    Some(b) => format!("{:02x} ", b),
+                    None => "   ".to_string(),
+                };
+                spans.push(Span::styled(text, style(start + i)));
+            }
+            spans.push(Span::raw(" "));
+            for (i, &b) in bytes.iter().enumerate() {
+                let c = if (0x20..0x7F).contains(&b) { b as char } else { '.' };
+                spans.push(Span::styled(c.to_string(), style(start + i)));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Accepts a JSON object or `key=value` pairs, whose values are JSON where
+/// they parse as such, numbers for `0x`-prefixed hex, and strings otherwise.
+fn parse_arguments(text: &str) -> Result<Map<String, Value>, String> {
+    let text = text.trim();
+    if text.starts_with('{') {
+        return match serde_json::from_str(text) {
+            Ok(Value::Object(map)) => Ok(map),
+            Ok(_) => Err("arguments must be a JSON object".to_string()),
+            Err(e) => Err(format!("invalid JSON arguments: {}", e)),
+        };
+    }
+    text.split_whitespace()
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => Ok((key.to_string(), parse_value(value))),
+            None => Err(format!("expected key=value, got '{}'", pair)),
+        })
+        .collect()
+}
+
+fn parse_value(text: &str) -> Value {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        if let Ok(n) = u64::from_str_radix(hex, 16) {
+            return Value::from(n);
+        }
+    }
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+fn result_text(result: &CallToolResult) -> String {
+    let text = result.content
+        .iter()
+        .map(|block| match block {
+            ContentBlock::TextContent(text) => text.text.clone(),
+            other => serde_json::to_string(other).unwrap_or_default(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if result.is_error.unwrap_or(false) {
+        format!("❌ {}", text)
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values_parse_as_hex_json_or_strings() {
+        assert_eq!(parse_value("0x10"), json!(16));
+        assert_eq!(parse_value("0XfF"), json!(255));
+        assert_eq!(parse_value("42"), json!(42));
+        assert_eq!(parse_value("true"), json!(true));
+        assert_eq!(parse_value("[1,2]"), json!([1, 2]));
+        assert_eq!(parse_value("\"quoted\""), json!("quoted"));
+        assert_eq!(parse_value("0xZZ"), json!("0xZZ"));
+        assert_eq!(parse_value("0x1_0000_0000_0000_0000"), json!("0x1_0000_0000_0000_0000"));
+        assert_eq!(parse_value("header"), json!("header"));
+    }
+
+    #[test]
+    fn arguments_parse_as_pairs_or_json() {
+        let pairs = parse_arguments(" offset=0x10  length=32 name=hdr ").unwrap();
+        assert_eq!(Value::Object(pairs), json!({"offset": 16, "length": 32, "name": "hdr"}));
+        assert_eq!(parse_arguments("").unwrap(), Map::new());
+        assert_eq!(parse_arguments("note=a=b").unwrap()["note"], json!("a=b"));
+
+        let object = parse_arguments(r#"{"offset": 16, "tags": ["x"]}"#).unwrap();
+        assert_eq!(Value::Object(object), json!({"offset": 16, "tags": ["x"]}));
+
+        assert_eq!(parse_arguments("offset").unwrap_err(), "expected key=value, got 'offset'");
+        assert_eq!(parse_arguments("[1]").unwrap_err(), "expected key=value, got '[1]'");
+        assert!(parse_arguments("{\"offset\": }").unwrap_err().starts_with("invalid JSON arguments"));
+        assert!(parse_arguments("{} ").is_ok());
+    }
+}